@@ -0,0 +1,116 @@
+//! Timestamped copies of configs.json in `<config_dir>/backups/`, one per
+//! save that changed the content, pruned to `ConfigStore.backup_retention`.
+//! Backing up never fails a save; problems are only logged.
+
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::{get_config_path, migrations, now_millis, write_file_atomic, ConfigStore};
+
+const PREFIX: &str = "configs-";
+const SUFFIX: &str = ".json";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupInfo {
+    pub filename: String,
+    /// Milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    /// `None` if the file no longer parses.
+    pub config_count: Option<usize>,
+}
+
+fn backups_dir() -> PathBuf {
+    get_config_path().with_file_name("backups")
+}
+
+fn timestamp_of(filename: &str) -> Option<u64> {
+    filename.strip_prefix(PREFIX)?.strip_suffix(SUFFIX)?.parse().ok()
+}
+
+/// Backup file names, oldest first. Zero-padded timestamps sort by name.
+fn backup_names() -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(backups_dir())
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok()?.file_name().into_string().ok())
+                .filter(|name| timestamp_of(name).is_some())
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+/// Called by `save_store` with what it just wrote.
+pub fn record(content: &[u8], retention: usize) {
+    if retention == 0 {
+        return;
+    }
+    if let Err(e) = write_backup(content, retention) {
+        eprintln!("backups: {}", e);
+    }
+}
+
+fn write_backup(content: &[u8], retention: usize) -> Result<(), String> {
+    let dir = backups_dir();
+    let names = backup_names();
+    if let Some(newest) = names.last() {
+        if fs::read(dir.join(newest)).is_ok_and(|previous| previous == content) {
+            return Ok(());
+        }
+    }
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let name = format!("{}{:013}{}", PREFIX, now_millis(), SUFFIX);
+    write_file_atomic(&dir.join(&name), content)?;
+
+    let mut names = names;
+    names.push(name);
+    let excess = names.len().saturating_sub(retention);
+    for old in &names[..excess] {
+        if let Err(e) = fs::remove_file(dir.join(old)) {
+            eprintln!("backups: failed to remove {}: {}", old, e);
+        }
+    }
+    Ok(())
+}
+
+/// Newest first.
+pub fn list() -> Vec<BackupInfo> {
+    let dir = backups_dir();
+    backup_names()
+        .into_iter()
+        .rev()
+        .map(|filename| {
+            let config_count = fs::read_to_string(dir.join(&filename))
+                .ok()
+                .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+                .and_then(|value| value.get("configs")?.as_array().map(Vec::len));
+            BackupInfo {
+                timestamp: timestamp_of(&filename).unwrap_or(0),
+                filename,
+                config_count,
+            }
+        })
+        .collect()
+}
+
+/// Replaces configs.json with a backup after checking it loads, backing up
+/// the current file first so the restore itself can be undone.
+pub fn restore(filename: &str, retention: usize) -> Result<(), String> {
+    if timestamp_of(filename).is_none() || filename.contains(['/', '\\']) {
+        return Err(format!("'{}' is not a backup file", filename));
+    }
+    let path = backups_dir().join(filename);
+    let content = fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let mut value: serde_json::Value =
+        serde_json::from_slice(&content).map_err(|e| format!("Backup {} is not valid JSON: {}", filename, e))?;
+    migrations::migrate(&mut value)?;
+    serde_json::from_value::<ConfigStore>(value).map_err(|e| format!("Backup {} is not a valid store: {}", filename, e))?;
+
+    let live = get_config_path();
+    if let Ok(current) = fs::read(&live) {
+        write_backup(&current, retention.max(1)).map_err(|e| format!("Pre-restore backup failed: {}", e))?;
+    }
+    write_file_atomic(&live, &content)
+}
@@ -0,0 +1,95 @@
+//! Daily spend per config against `ConfigOptions.cost_budget_daily_usd`.
+//! Nothing here measures spend: an optional hook (a tool's status line or
+//! post-request script) reports it through `record_usage_cost` or the CLI's
+//! `record-cost`, and the totals are kept in `usage.json` next to
+//! configs.json. Activating a config that is over budget still works; the
+//! result carries a `BudgetWarning`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::{get_config_path, now_millis, state, write_file_atomic, Config};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct DailyCost {
+    /// UTC days since the Unix epoch.
+    day: u64,
+    cost_usd: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetWarning {
+    pub config_id: String,
+    pub name: String,
+    pub budget_usd: f64,
+    pub spent_usd: f64,
+}
+
+fn usage_path() -> PathBuf {
+    get_config_path().with_file_name("usage.json")
+}
+
+fn today() -> u64 {
+    now_millis() / 86_400_000
+}
+
+fn load() -> HashMap<String, DailyCost> {
+    fs::read_to_string(usage_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// What `id` has spent today, in US dollars.
+pub fn spent_today(id: &str) -> f64 {
+    load().get(id).filter(|c| c.day == today()).map_or(0.0, |c| c.cost_usd)
+}
+
+/// Adds `cost_usd` to today's total for `id`, starting over on a new day,
+/// and returns the new total.
+pub fn record(id: &str, cost_usd: f64) -> Result<f64, String> {
+    if !cost_usd.is_finite() || cost_usd < 0.0 {
+        return Err(format!("Invalid cost '{}'", cost_usd));
+    }
+    // Serializes hook calls within this process with each other.
+    let _lock = state::lock();
+    let mut usage = load();
+    let today = today();
+    let entry = usage.entry(id.to_string()).or_default();
+    if entry.day != today {
+        *entry = DailyCost { day: today, cost_usd: 0.0 };
+    }
+    entry.cost_usd += cost_usd;
+    let total = entry.cost_usd;
+    let json = serde_json::to_string_pretty(&usage).map_err(|e| e.to_string())?;
+    write_file_atomic(&usage_path(), json.as_bytes())?;
+    Ok(total)
+}
+
+/// Drops the totals of a deleted config.
+pub fn forget(id: &str) {
+    let mut usage = load();
+    if usage.remove(id).is_none() {
+        return;
+    }
+    let saved = serde_json::to_string_pretty(&usage)
+        .map_err(|e| e.to_string())
+        .and_then(|json| write_file_atomic(&usage_path(), json.as_bytes()));
+    if let Err(e) = saved {
+        eprintln!("budget: failed to update {}: {}", usage_path().display(), e);
+    }
+}
+
+/// A warning if `config` has a daily budget and today's spend exceeds it.
+pub fn check(config: &Config) -> Option<BudgetWarning> {
+    let budget = config.options.cost_budget_daily_usd?;
+    let spent = spent_today(&config.id);
+    (spent > budget).then(|| BudgetWarning {
+        config_id: config.id.clone(),
+        name: config.name.clone(),
+        budget_usd: budget,
+        spent_usd: spent,
+    })
+}
@@ -0,0 +1,317 @@
+//! Headless entry point (`claude-config-manager --cli <command>`) for scripts
+//! and CI. Subcommands call the same functions the GUI invokes, so behaviour
+//! and the store stay identical between the two.
+
+use std::io::Read;
+
+use crate::{
+    activate_config, add_config, check_store, deactivate_config, import_config_from_json_string, load_store,
+    mask_api_key, record_usage_cost, sorted_configs, AddConfigError, Config, ConfigView,
+};
+
+const EXIT_OK: i32 = 0;
+/// IO, parse and apply failures.
+const EXIT_ERROR: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_NOT_FOUND: i32 = 3;
+
+const USAGE: &str = "\
+Usage: claude-config-manager --cli <command> [options]
+
+Commands:
+  list                              List saved configs (* marks active ones)
+  add --type <type> --name <name> --key <key> [--base-url <url>] [--model <model>] [--force] [--allow-weak-key]
+  activate <name|id>                Activate a config
+  deactivate <type>                 Deactivate the active config of a type
+  export <name|id>                  Print a config as JSON
+  import <file|->                   Add a config from JSON (as printed by export)
+  record-cost <name|id> --usd <amount>
+                                    Add to a config's spend today, for budget warnings
+
+Options:
+  --json         Machine-readable output
+  --show-keys    Print API keys in full instead of masked
+  --force        Add a config even if one with the same key and URL exists
+  --allow-weak-key
+                 Add a key even if it looks like a password";
+
+struct Args {
+    positional: Vec<String>,
+    options: Vec<(String, String)>,
+    json: bool,
+    show_keys: bool,
+    force: bool,
+    allow_weak_key: bool,
+}
+
+impl Args {
+    fn parse(raw: &[String]) -> Result<Args, String> {
+        let mut args = Args {
+            positional: Vec::new(),
+            options: Vec::new(),
+            json: false,
+            show_keys: false,
+            force: false,
+            allow_weak_key: false,
+        };
+        let mut iter = raw.iter();
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--json" => args.json = true,
+                "--show-keys" => args.show_keys = true,
+                "--force" => args.force = true,
+                "--allow-weak-key" => args.allow_weak_key = true,
+                flag if flag.starts_with("--") => {
+                    let value = iter.next().ok_or_else(|| format!("{} needs a value", flag))?;
+                    args.options.push((flag.trim_start_matches("--").to_string(), value.clone()));
+                }
+                _ => args.positional.push(arg.clone()),
+            }
+        }
+        Ok(args)
+    }
+
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options.iter().rev().find(|(k, _)| k == name).map(|(_, v)| v.as_str())
+    }
+
+    fn required(&self, name: &str) -> Result<&str, CliError> {
+        self.option(name).ok_or_else(|| CliError::usage(format!("--{} is required", name)))
+    }
+
+    fn target(&self) -> Result<&str, CliError> {
+        self.positional
+            .get(1)
+            .map(String::as_str)
+            .ok_or_else(|| CliError::usage(format!("{} needs an argument", self.positional[0])))
+    }
+}
+
+struct CliError {
+    code: i32,
+    message: String,
+}
+
+impl CliError {
+    fn usage(message: impl Into<String>) -> Self {
+        CliError { code: EXIT_USAGE, message: message.into() }
+    }
+
+    fn not_found(message: impl Into<String>) -> Self {
+        CliError { code: EXIT_NOT_FOUND, message: message.into() }
+    }
+}
+
+impl From<String> for CliError {
+    fn from(message: String) -> Self {
+        CliError { code: EXIT_ERROR, message }
+    }
+}
+
+impl From<AddConfigError> for CliError {
+    fn from(e: AddConfigError) -> Self {
+        e.to_string().into()
+    }
+}
+
+/// Runs one CLI command and returns the process exit code.
+pub fn run(raw: &[String]) -> i32 {
+    attach_console();
+    let result = Args::parse(raw)
+        .map_err(CliError::usage)
+        .and_then(|args| dispatch(&args));
+    match result {
+        Ok(()) => EXIT_OK,
+        Err(e) => {
+            eprintln!("error: {}", e.message);
+            if e.code == EXIT_USAGE {
+                eprintln!("\n{}", USAGE);
+            }
+            e.code
+        }
+    }
+}
+
+fn dispatch(args: &Args) -> Result<(), CliError> {
+    let Some(command) = args.positional.first() else {
+        println!("{}", USAGE);
+        return Ok(());
+    };
+    check_store()?;
+    match command.as_str() {
+        "list" => list(args),
+        "add" => add(args),
+        "activate" => activate(args),
+        "deactivate" => deactivate(args),
+        "export" => export(args),
+        "import" => import(args),
+        "record-cost" => record_cost(args),
+        "help" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        other => Err(CliError::usage(format!("unknown command '{}'", other))),
+    }
+}
+
+/// Matches by id first, then by exact name.
+fn find_config(target: &str) -> Result<Config, CliError> {
+    let configs = load_store().configs;
+    if let Some(config) = configs.iter().find(|c| c.id == target) {
+        return Ok(config.clone());
+    }
+    let mut by_name = configs.into_iter().filter(|c| c.name == target);
+    match (by_name.next(), by_name.next()) {
+        (Some(config), None) => Ok(config),
+        (Some(_), Some(_)) => Err(CliError::usage(format!(
+            "more than one config is named '{}', use its id instead",
+            target
+        ))),
+        (None, _) => Err(CliError::not_found(format!("no config with id or name '{}'", target))),
+    }
+}
+
+fn print_json(value: &impl serde::Serialize) -> Result<(), CliError> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    println!("{}", json);
+    Ok(())
+}
+
+fn shown_key(config: &Config, show_keys: bool) -> String {
+    if show_keys || config.api_key.is_empty() {
+        config.api_key.clone()
+    } else {
+        mask_api_key(&config.api_key)
+    }
+}
+
+fn list(args: &Args) -> Result<(), CliError> {
+    let configs = sorted_configs(load_store().configs);
+    if args.json {
+        return if args.show_keys {
+            print_json(&configs)
+        } else {
+            print_json(&configs.into_iter().map(ConfigView::from).collect::<Vec<_>>())
+        };
+    }
+    for config in &configs {
+        println!(
+            "{} [{}] {}  {}  {}  ({})",
+            if config.is_active { "*" } else { " " },
+            String::from(config.config_type.clone()),
+            config.name,
+            shown_key(config, args.show_keys),
+            if config.base_url.is_empty() { "default" } else { &config.base_url },
+            config.id
+        );
+    }
+    Ok(())
+}
+
+fn add(args: &Args) -> Result<(), CliError> {
+    let config = add_config(
+        args.required("name")?.to_string(),
+        args.required("type")?.to_string(),
+        args.required("key")?.to_string(),
+        args.option("base-url").unwrap_or_default().to_string(),
+        args.option("model").unwrap_or_default().to_string(),
+        None,
+        Some(args.force),
+        None,
+        Some(args.allow_weak_key),
+    )?;
+    report(args, "Added", &config)
+}
+
+fn activate(args: &Args) -> Result<(), CliError> {
+    let config = find_config(args.target()?)?;
+    let result = activate_config(config.id.clone(), None)?;
+    if let Some(warning) = result.budget_warning {
+        eprintln!(
+            "warning: '{}' spent ${:.2} today, over its ${:.2} daily budget",
+            warning.name, warning.spent_usd, warning.budget_usd
+        );
+    }
+    report(args, "Activated", &config)
+}
+
+fn deactivate(args: &Args) -> Result<(), CliError> {
+    let config_type = crate::parse_config_type(args.target()?)?;
+    let config = load_store()
+        .configs
+        .into_iter()
+        .find(|c| c.config_type == config_type && c.is_active)
+        .ok_or_else(|| CliError::not_found(format!("no active {} config", String::from(config_type))))?;
+    deactivate_config(config.id.clone(), None)?;
+    report(args, "Deactivated", &config)
+}
+
+fn export(args: &Args) -> Result<(), CliError> {
+    let mut config = find_config(args.target()?)?;
+    config.api_key = shown_key(&config, args.show_keys);
+    print_json(&config)
+}
+
+fn import(args: &Args) -> Result<(), CliError> {
+    let source = args.target()?;
+    let json = if source == "-" {
+        let mut buf = String::new();
+        std::io::stdin()
+            .read_to_string(&mut buf)
+            .map_err(|e| format!("Failed to read stdin: {}", e))?;
+        buf
+    } else {
+        std::fs::read_to_string(source).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => CliError::not_found(format!("{} does not exist", source)),
+            _ => CliError::from(format!("Failed to read {}: {}", source, e)),
+        })?
+    };
+    let config = import_config_from_json_string(json)?;
+    report(args, "Imported", &config)
+}
+
+fn record_cost(args: &Args) -> Result<(), CliError> {
+    let config = find_config(args.target()?)?;
+    let usd = args.required("usd")?;
+    let cost: f64 = usd
+        .parse()
+        .map_err(|_| CliError::usage(format!("--usd '{}' is not a number", usd)))?;
+    let total = record_usage_cost(config.id.clone(), cost)?;
+    if args.json {
+        return print_json(&serde_json::json!({ "id": config.id, "spent_today_usd": total }));
+    }
+    println!("'{}' spent ${:.2} today", config.name, total);
+    Ok(())
+}
+
+fn report(args: &Args, verb: &str, config: &Config) -> Result<(), CliError> {
+    if args.json {
+        return print_json(&serde_json::json!({
+            "id": config.id,
+            "name": config.name,
+            "config_type": config.config_type,
+        }));
+    }
+    println!(
+        "{} '{}' ({})",
+        verb,
+        config.name,
+        String::from(config.config_type.clone())
+    );
+    Ok(())
+}
+
+/// Release builds use the GUI subsystem on Windows, so stdout goes nowhere
+/// unless we attach to the console of the shell that started us.
+#[cfg(windows)]
+fn attach_console() {
+    use windows_sys::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+    // SAFETY: plain Win32 call without pointers; failure just means there is
+    // no parent console and output is dropped as before.
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
@@ -0,0 +1,217 @@
+//! `ccm://` URLs from Stream Deck, scripts or a browser. The scheme is
+//! registered by the deep-link plugin (`plugins.deep-link` in
+//! tauri.conf.json); the single-instance plugin makes a second launch, which
+//! is how Windows and Linux deliver a URL, hand its URLs to the running
+//! instance and exit instead of opening another window that would fight over
+//! configs.json. Both arrive here through `on_open_url`.
+//!
+//! - `ccm://activate/<id or name>`
+//! - `ccm://deactivate/<type>`
+//! - `ccm://profile/<name>`
+//!
+//! Each URL ends in a `deep-link-handled` or `deep-link-error` event.
+
+use serde::Serialize;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_deep_link::DeepLinkExt;
+
+use crate::{
+    activate_config, activate_profile, deactivate_config, load_store, parse_config_type, sorted_configs, Config,
+    ConfigType,
+};
+
+pub const SCHEME: &str = "ccm";
+const DEEP_LINK_HANDLED: &str = "deep-link-handled";
+const DEEP_LINK_ERROR: &str = "deep-link-error";
+/// Gives the webview time to register its listeners before a URL the app
+/// was launched with is handled.
+const STARTUP_DELAY: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Action {
+    Activate(String),
+    Deactivate(ConfigType),
+    Profile(String),
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct DeepLinkEvent {
+    url: String,
+    /// What was done, or why nothing was.
+    message: String,
+}
+
+fn is_deep_link(arg: &str) -> bool {
+    arg.get(..SCHEME.len() + 3)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(&format!("{}://", SCHEME)))
+}
+
+fn percent_decode(s: &str) -> Result<String, String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = s.get(i + 1..i + 3).ok_or("truncated %-escape")?;
+            out.push(u8::from_str_radix(hex, 16).map_err(|_| format!("invalid %-escape '%{}'", hex))?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).map_err(|_| "escaped text is not UTF-8".to_string())
+}
+
+fn parse(url: &str) -> Result<Action, String> {
+    if !is_deep_link(url) {
+        return Err(format!("Not a {}:// URL", SCHEME));
+    }
+    let rest = &url[SCHEME.len() + 3..];
+    let rest = rest.split(['?', '#']).next().unwrap_or(rest).trim_end_matches('/');
+    let (verb, target) = rest
+        .split_once('/')
+        .ok_or_else(|| format!("Expected {}://<action>/<target>", SCHEME))?;
+    let target = percent_decode(target)?;
+    if target.trim().is_empty() {
+        return Err(format!("'{}' needs a target", verb));
+    }
+    match verb.to_ascii_lowercase().as_str() {
+        "activate" => Ok(Action::Activate(target)),
+        "deactivate" => Ok(Action::Deactivate(parse_config_type(&target)?)),
+        "profile" => Ok(Action::Profile(target)),
+        other => Err(format!("Unknown action '{}'", other)),
+    }
+}
+
+/// By id, else by name ignoring case. Two configs with the name is an error
+/// rather than a guess.
+fn find_config(target: &str) -> Result<Config, String> {
+    let configs = sorted_configs(load_store().configs);
+    if let Some(config) = configs.iter().find(|c| c.id == target) {
+        return Ok(config.clone());
+    }
+    let name = target.trim().to_lowercase();
+    let matches: Vec<Config> = configs.into_iter().filter(|c| c.name.to_lowercase() == name).collect();
+    match &matches[..] {
+        [config] => Ok(config.clone()),
+        [] => Err(format!("No config with id or name '{}'", target)),
+        _ => Err(format!(
+            "{} configs are named '{}' ({}), use the id instead",
+            matches.len(),
+            target,
+            matches
+                .iter()
+                .map(|c| String::from(c.config_type.clone()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+fn run(action: Action) -> Result<String, String> {
+    match action {
+        Action::Activate(target) => {
+            let config = find_config(&target)?;
+            activate_config(config.id.clone(), None)?;
+            Ok(format!("Activated '{}'", config.name))
+        }
+        Action::Deactivate(config_type) => {
+            let config = load_store()
+                .configs
+                .into_iter()
+                .find(|c| c.config_type == config_type && c.is_active)
+                .ok_or_else(|| format!("No active {} config", String::from(config_type)))?;
+            deactivate_config(config.id.clone(), None)?;
+            Ok(format!("Deactivated '{}'", config.name))
+        }
+        Action::Profile(name) => {
+            let wanted = name.trim().to_lowercase();
+            let profiles: Vec<_> = load_store()
+                .profiles
+                .into_iter()
+                .filter(|p| p.name.to_lowercase() == wanted)
+                .collect();
+            let profile = match &profiles[..] {
+                [profile] => profile,
+                [] => return Err(format!("No profile named '{}'", name)),
+                _ => return Err(format!("{} profiles are named '{}'", profiles.len(), name)),
+            };
+            let result = activate_profile(profile.id.clone())?;
+            let failed: Vec<String> = result
+                .entries
+                .iter()
+                .filter_map(|e| e.error.as_ref().map(|error| format!("{}: {}", String::from(e.config_type.clone()), error)))
+                .chain(result.opencode_error.map(|e| format!("opencode: {}", e)))
+                .collect();
+            if !failed.is_empty() {
+                return Err(format!("Profile '{}' partly failed: {}", profile.name, failed.join("; ")));
+            }
+            Ok(format!("Activated profile '{}'", profile.name))
+        }
+    }
+}
+
+pub fn focus_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+/// Runs one URL and reports the outcome to the frontend.
+fn handle(app: &AppHandle, url: &str) {
+    let (event, message) = match parse(url).and_then(run) {
+        Ok(message) => (DEEP_LINK_HANDLED, message),
+        Err(e) => {
+            eprintln!("deeplink: {}: {}", url, e);
+            (DEEP_LINK_ERROR, e)
+        }
+    };
+    focus_main_window(app);
+    let payload = DeepLinkEvent {
+        url: url.to_string(),
+        message,
+    };
+    if let Err(e) = app.emit(event, payload) {
+        eprintln!("deeplink: failed to emit {}: {}", event, e);
+    }
+}
+
+/// Registers the scheme for the current user on Windows and Linux (macOS
+/// reads it from the bundle), handles URLs delivered from now on, and a URL
+/// the app itself was launched with.
+pub fn start(app: AppHandle) {
+    #[cfg(any(windows, target_os = "linux"))]
+    if let Err(e) = app.deep_link().register_all() {
+        eprintln!("deeplink: failed to register {}://: {}", SCHEME, e);
+    }
+    let handler = app.clone();
+    app.deep_link().on_open_url(move |event| {
+        let urls: Vec<String> = event.urls().iter().map(|url| url.to_string()).collect();
+        let app = handler.clone();
+        // Off the event loop: a profile can take a while to apply.
+        std::thread::spawn(move || {
+            for url in urls {
+                handle(&app, &url);
+            }
+        });
+    });
+    let urls: Vec<String> = match app.deep_link().get_current() {
+        Ok(urls) => urls.unwrap_or_default().iter().map(|url| url.to_string()).collect(),
+        Err(e) => {
+            eprintln!("deeplink: {}", e);
+            Vec::new()
+        }
+    };
+    if !urls.is_empty() {
+        std::thread::spawn(move || {
+            std::thread::sleep(STARTUP_DELAY);
+            for url in urls {
+                handle(&app, &url);
+            }
+        });
+    }
+}
@@ -0,0 +1,90 @@
+//! `config-activated`, `config-deactivated` and `opencode-updated`, emitted
+//! from the shared activation paths so switches made by failover, profiles or
+//! an `update_config` re-apply are announced too, and `config-deleted`. In CLI
+//! mode there is no app handle and nothing is sent. Configs with `ConfigOptions.webhook_url` also
+//! get an HTTP notification when activated.
+
+use serde::Serialize;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+use crate::{now_millis, Config, ConfigType, ConfigView};
+
+pub const CONFIG_ACTIVATED: &str = "config-activated";
+pub const CONFIG_DEACTIVATED: &str = "config-deactivated";
+pub const OPENCODE_UPDATED: &str = "opencode-updated";
+pub const CONFIG_DELETED: &str = "config-deleted";
+
+static APP: OnceLock<AppHandle> = OnceLock::new();
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigEvent {
+    pub id: String,
+    pub name: String,
+    pub config_type: ConfigType,
+}
+
+pub fn init(app: AppHandle) {
+    APP.set(app).ok();
+}
+
+pub fn emit(event: &str, config: &Config) {
+    let Some(app) = APP.get() else {
+        return;
+    };
+    let payload = ConfigEvent {
+        id: config.id.clone(),
+        name: config.name.clone(),
+        config_type: config.config_type.clone(),
+    };
+    if let Err(e) = app.emit(event, payload) {
+        eprintln!("events: failed to emit {}: {}", event, e);
+    }
+}
+
+/// Sends the whole deleted config, key masked as in `get_configs`, so the
+/// frontend can offer to add it back.
+pub fn emit_deleted(config: &Config) {
+    let Some(app) = APP.get() else {
+        return;
+    };
+    if let Err(e) = app.emit(CONFIG_DELETED, ConfigView::from(config.clone())) {
+        eprintln!("events: failed to emit {}: {}", CONFIG_DELETED, e);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct WebhookPayload {
+    event: &'static str,
+    config_name: String,
+    config_type: ConfigType,
+    /// Milliseconds since the Unix epoch.
+    timestamp: u64,
+}
+
+/// POSTs an `activated` payload to the config's webhook on a background
+/// thread. A failing webhook is logged and never affects the activation.
+pub fn post_webhook(config: &Config) {
+    let Some(url) = config.options.webhook_url.clone() else {
+        return;
+    };
+    let payload = WebhookPayload {
+        event: "activated",
+        config_name: config.name.clone(),
+        config_type: config.config_type.clone(),
+        timestamp: now_millis(),
+    };
+    std::thread::spawn(move || {
+        let agent: ureq::Agent = ureq::Agent::config_builder()
+            .timeout_global(Some(WEBHOOK_TIMEOUT))
+            .build()
+            .into();
+        let body = serde_json::to_string(&payload).unwrap_or_default();
+        let result = agent.post(&url).header("Content-Type", "application/json").send(body);
+        if let Err(e) = result {
+            eprintln!("webhook: POST to {} failed: {}", url, e);
+        }
+    });
+}
@@ -0,0 +1,102 @@
+//! Key expiry dates (`ConfigOptions.expires_at`) and the reminder sent to the
+//! frontend as a `configs-expiring` event: shortly after start, then twice a
+//! day while the app stays open.
+
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+use crate::{expiring_configs, load_store};
+
+const CONFIGS_EXPIRING: &str = "configs-expiring";
+/// Gives the webview time to register its listeners before the first check.
+const STARTUP_DELAY: Duration = Duration::from_secs(3);
+const CHECK_INTERVAL: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// Days since 1970-01-01 for a proleptic Gregorian date.
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (i64::from(month) + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn number(s: &str) -> Result<u32, String> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("'{}' is not a number", s));
+    }
+    s.parse().map_err(|_| format!("'{}' is out of range", s))
+}
+
+/// Parses `YYYY-MM-DD` (midnight UTC) or an RFC 3339 timestamp such as
+/// `2026-03-01T09:00:00+08:00` into milliseconds since the Unix epoch.
+pub fn parse_expiry(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let invalid = |e: String| format!("Invalid expiry date '{}': {}", value, e);
+    let num = |s: &str| number(s).map_err(invalid);
+    let (date, time) = match value.split_once(['T', 't', ' ']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (value, None),
+    };
+    let parts: Vec<&str> = date.split('-').collect();
+    let [year, month, day] = parts[..] else {
+        return Err(invalid("expected YYYY-MM-DD".to_string()));
+    };
+    let (year, month, day) = (num(year)?, num(month)?, num(day)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return Err(invalid("month or day out of range".to_string()));
+    }
+
+    let mut seconds = days_from_civil(i64::from(year), month, day) * 86_400;
+    if let Some(time) = time {
+        let (clock, offset_seconds) = if let Some(clock) = time.strip_suffix(['Z', 'z']) {
+            (clock, 0)
+        } else {
+            let split = time
+                .rfind(['+', '-'])
+                .ok_or_else(|| invalid("missing time zone offset".to_string()))?;
+            let (clock, offset) = time.split_at(split);
+            let sign = if offset.starts_with('-') { -1 } else { 1 };
+            let (hours, minutes) = offset[1..]
+                .split_once(':')
+                .ok_or_else(|| invalid("time zone offset must be ±HH:MM".to_string()))?;
+            let offset = i64::from(num(hours)?) * 3600 + i64::from(num(minutes)?) * 60;
+            (clock, sign * offset)
+        };
+        // Fractional seconds don't matter for an expiry date.
+        let clock = clock.split('.').next().unwrap_or(clock);
+        let fields: Vec<&str> = clock.split(':').collect();
+        let [hours, minutes, secs] = fields[..] else {
+            return Err(invalid("expected HH:MM:SS".to_string()));
+        };
+        let (hours, minutes, secs) = (num(hours)?, num(minutes)?, num(secs)?);
+        if hours > 23 || minutes > 59 || secs > 60 {
+            return Err(invalid("time out of range".to_string()));
+        }
+        seconds += i64::from(hours * 3600 + minutes * 60 + secs) - offset_seconds;
+    }
+    u64::try_from(seconds * 1000).map_err(|_| invalid("before 1970".to_string()))
+}
+
+fn check(app: &AppHandle) {
+    let within_days = load_store().expiry_warning_days;
+    let configs = expiring_configs(within_days);
+    if configs.is_empty() {
+        return;
+    }
+    if let Err(e) = app.emit(CONFIGS_EXPIRING, configs) {
+        eprintln!("expiry: failed to emit {}: {}", CONFIGS_EXPIRING, e);
+    }
+}
+
+pub fn start(app: AppHandle) {
+    std::thread::spawn(move || {
+        std::thread::sleep(STARTUP_DELAY);
+        loop {
+            check(&app);
+            std::thread::sleep(CHECK_INTERVAL);
+        }
+    });
+}
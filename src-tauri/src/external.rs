@@ -0,0 +1,136 @@
+//! Reads endpoint lists exported by other key-switcher tools, for
+//! `import_external`. Only parses; creating the configs (and skipping
+//! duplicates) is left to the command.
+
+use crate::{is_http_url, normalize_base_url, ConfigType};
+
+const GENERIC_SHAPE: &str = "a JSON array of { name, provider, apiKey, baseUrl } objects, \
+    with provider one of anthropic/claude, google/gemini, openai";
+
+/// One importable endpoint.
+pub struct Entry {
+    pub name: String,
+    pub config_type: ConfigType,
+    pub api_key: String,
+    pub base_url: String,
+}
+
+/// What a file holds: the entries we can import, and (name, reason) for the
+/// ones we can't.
+#[derive(Default)]
+pub struct Parsed {
+    pub entries: Vec<Entry>,
+    pub skipped: Vec<(String, String)>,
+}
+
+/// Maps a provider string to the config type it configures.
+fn provider_type(provider: &str) -> Option<ConfigType> {
+    match provider.trim().to_ascii_lowercase().as_str() {
+        "anthropic" | "claude" => Some(ConfigType::Claude),
+        "google" | "gemini" => Some(ConfigType::Gemini),
+        "openai" | "openai-response" | "codex" => Some(ConfigType::Codex),
+        _ => None,
+    }
+}
+
+fn str_field<'a>(value: &'a serde_json::Value, names: &[&str]) -> &'a str {
+    names.iter().find_map(|n| value.get(*n)?.as_str()).unwrap_or("").trim()
+}
+
+impl Parsed {
+    fn add(&mut self, name: &str, provider: &str, api_key: &str, base_url: &str) {
+        let name = if name.is_empty() { provider } else { name };
+        let skip = |reason: String| (name.to_string(), reason);
+        let entry = match provider_type(provider) {
+            None => Err(skip(format!("Unknown provider '{}'", provider))),
+            Some(_) if api_key.is_empty() => Err(skip("No API key".to_string())),
+            Some(_) if !base_url.is_empty() && !is_http_url(base_url) => {
+                Err(skip(format!("Invalid base URL '{}'", base_url)))
+            }
+            Some(config_type) => Ok(Entry {
+                name: name.to_string(),
+                config_type,
+                api_key: api_key.to_string(),
+                base_url: normalize_base_url(base_url),
+            }),
+        };
+        match entry {
+            Ok(entry) => self.entries.push(entry),
+            Err(skipped) => self.skipped.push(skipped),
+        }
+    }
+}
+
+/// `[{ name, provider, apiKey, baseUrl }, ...]`.
+fn parse_generic(json: &serde_json::Value) -> Result<Parsed, String> {
+    let items = json
+        .as_array()
+        .ok_or_else(|| format!("Expected {}", GENERIC_SHAPE))?;
+    let mut parsed = Parsed::default();
+    for (index, item) in items.iter().enumerate() {
+        if !item.is_object() {
+            parsed.skipped.push((format!("#{}", index + 1), format!("Not an object; expected {}", GENERIC_SHAPE)));
+            continue;
+        }
+        parsed.add(
+            str_field(item, &["name"]),
+            str_field(item, &["provider"]),
+            str_field(item, &["apiKey", "api_key"]),
+            str_field(item, &["baseUrl", "base_url"]),
+        );
+    }
+    Ok(parsed)
+}
+
+/// A string holding JSON, as Cherry Studio persists each store slice.
+fn nested_json(value: &serde_json::Value) -> Option<serde_json::Value> {
+    match value {
+        serde_json::Value::String(s) => serde_json::from_str(s).ok(),
+        other => Some(other.clone()),
+    }
+}
+
+/// Cherry Studio's data backup: `localStorage["persist:cherry-studio"]` is a
+/// JSON string whose `llm` slice is another JSON string with `providers`.
+/// A bare `{ llm: { providers } }` or `{ providers }` is accepted too.
+fn parse_cherry_studio(json: &serde_json::Value) -> Result<Parsed, String> {
+    let persisted = json
+        .get("localStorage")
+        .and_then(|s| s.get("persist:cherry-studio"))
+        .and_then(nested_json)
+        .unwrap_or_else(|| json.clone());
+    let llm = persisted.get("llm").and_then(nested_json).unwrap_or(persisted);
+    let providers = llm
+        .get("providers")
+        .and_then(|p| p.as_array())
+        .ok_or("No llm.providers list found; expected a Cherry Studio data backup")?;
+    let mut parsed = Parsed::default();
+    for provider in providers {
+        // Built-in providers are always listed; only those given a key matter.
+        if str_field(provider, &["apiKey"]).is_empty() {
+            continue;
+        }
+        // Several keys may be listed comma-separated for rotation; take the first.
+        let api_key = str_field(provider, &["apiKey"]).split(',').next().unwrap_or("").trim();
+        parsed.add(
+            str_field(provider, &["name", "id"]),
+            str_field(provider, &["type"]),
+            api_key,
+            str_field(provider, &["apiHost"]),
+        );
+    }
+    Ok(parsed)
+}
+
+/// Parses `content` as `format`: `generic` or `cherry-studio`.
+pub fn parse(content: &str, format: &str) -> Result<Parsed, String> {
+    let json: serde_json::Value = serde_json::from_str(content).map_err(|e| format!("Invalid JSON: {}", e))?;
+    match format {
+        "generic" => parse_generic(&json),
+        "cherry-studio" => parse_cherry_studio(&json),
+        _ => Err(format!(
+            "Unknown import format '{}'; use 'generic' ({}) or 'cherry-studio'",
+            format, GENERIC_SHAPE
+        )),
+    }
+}
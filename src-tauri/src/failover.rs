@@ -0,0 +1,133 @@
+//! Opt-in automatic failover. While `ConfigStore.failover.enabled` is set, the
+//! active config of every type with a fallback chain gets a connection check
+//! each `interval_minutes`. After `failure_threshold` outages in a row the
+//! next reachable config in the chain is activated, and a `config-failover`
+//! event lets the UI tell the user.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+use crate::health::{self, HealthStatus};
+use crate::{activate_for_tool, load_store, state, Config, ConfigStore, ConfigType};
+
+const CONFIG_FAILOVER: &str = "config-failover";
+/// How often the settings are re-read, so enabling or shortening the
+/// interval takes effect without a restart.
+const TICK: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FailoverEvent {
+    pub config_type: ConfigType,
+    pub from_id: String,
+    pub from_name: String,
+    pub to_id: String,
+    pub to_name: String,
+}
+
+/// Consecutive outages of the active config, per type. Starts over when a
+/// different config becomes active.
+type FailureCounts = HashMap<ConfigType, (String, u32)>;
+
+pub fn start(app: AppHandle) {
+    std::thread::spawn(move || {
+        let mut failures = FailureCounts::new();
+        let mut last_run: Option<Instant> = None;
+        loop {
+            std::thread::sleep(TICK);
+            let store = load_store();
+            if !store.failover.enabled {
+                failures.clear();
+                last_run = None;
+                continue;
+            }
+            let interval = Duration::from_secs(u64::from(store.failover.interval_minutes) * 60);
+            if last_run.is_some_and(|t| t.elapsed() < interval) {
+                continue;
+            }
+            last_run = Some(Instant::now());
+            for (config_type, chain) in &store.fallbacks {
+                check_type(&app, &store, config_type, chain, &mut failures);
+            }
+        }
+    });
+}
+
+fn check_type(app: &AppHandle, store: &ConfigStore, config_type: &ConfigType, chain: &[String], failures: &mut FailureCounts) {
+    let Some(active) = store.configs.iter().find(|c| &c.config_type == config_type && c.is_active) else {
+        failures.remove(config_type);
+        return;
+    };
+    let check = health::check_config(active, health::DEFAULT_TIMEOUT);
+    if !check.status.is_outage() {
+        failures.remove(config_type);
+        return;
+    }
+    let count = match failures.get_mut(config_type) {
+        Some((id, count)) if *id == active.id => {
+            *count += 1;
+            *count
+        }
+        _ => {
+            failures.insert(config_type.clone(), (active.id.clone(), 1));
+            1
+        }
+    };
+    eprintln!(
+        "failover: '{}' failed its check ({}/{}): {}",
+        active.name,
+        count,
+        store.failover.failure_threshold,
+        check.error.unwrap_or_default()
+    );
+    if count < store.failover.failure_threshold {
+        return;
+    }
+
+    // The configs after the active one come first, then wrap around.
+    let start = chain.iter().position(|id| *id == active.id).map_or(0, |i| i + 1);
+    let candidates = chain[start..]
+        .iter()
+        .chain(&chain[..start])
+        .filter(|id| **id != active.id)
+        .filter_map(|id| store.configs.iter().find(|c| c.id == *id));
+    for candidate in candidates {
+        if health::check_config(candidate, health::DEFAULT_TIMEOUT).status != HealthStatus::Reachable {
+            continue;
+        }
+        match switch(active, candidate) {
+            Ok(true) => {
+                failures.remove(config_type);
+                let event = FailoverEvent {
+                    config_type: config_type.clone(),
+                    from_id: active.id.clone(),
+                    from_name: active.name.clone(),
+                    to_id: candidate.id.clone(),
+                    to_name: candidate.name.clone(),
+                };
+                if let Err(e) = app.emit(CONFIG_FAILOVER, event) {
+                    eprintln!("failover: failed to emit {}: {}", CONFIG_FAILOVER, e);
+                }
+                return;
+            }
+            Ok(false) => return,
+            Err(e) => eprintln!("failover: failed to activate '{}': {}", candidate.name, e),
+        }
+    }
+    eprintln!("failover: no reachable fallback for '{}'", active.name);
+}
+
+/// Activates `to` unless the user is activating something right now or
+/// already moved off `from` while the checks ran; returns whether it did.
+fn switch(from: &Config, to: &Config) -> Result<bool, String> {
+    let Some(_lock) = state::try_lock() else {
+        return Ok(false);
+    };
+    let still_active = load_store().configs.iter().any(|c| c.id == from.id && c.is_active);
+    if !still_active {
+        return Ok(false);
+    }
+    activate_for_tool(&to.id, false)?;
+    Ok(true)
+}
@@ -0,0 +1,220 @@
+//! Connection test for a saved config: one authenticated `GET` of the
+//! provider's model list at the config's base URL. Cheap enough to run on a
+//! timer, and it tells a dead relay (5xx, timeout) apart from a bad key.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::{codex_azure, codex_base_url, now_millis, Benchmark, Config, ConfigType};
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Shorter than `DEFAULT_TIMEOUT` so one dead relay doesn't hold up a
+/// benchmark of several rounds.
+const BENCHMARK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    /// Answered without a server error. A 404 still counts: some relays
+    /// don't serve the model list but proxy requests fine.
+    Reachable,
+    /// 401 / 403: the endpoint is up but rejected the key.
+    AuthFailed,
+    /// 5xx from the endpoint.
+    ServerError,
+    /// Connection failed or timed out.
+    Unreachable,
+}
+
+impl HealthStatus {
+    /// Whether the endpoint itself is down, as opposed to the key being bad.
+    pub fn is_outage(self) -> bool {
+        matches!(self, HealthStatus::ServerError | HealthStatus::Unreachable)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HealthCheck {
+    pub status: HealthStatus,
+    pub http_status: Option<u16>,
+    /// Time until the response headers arrived.
+    pub latency_ms: u64,
+    pub error: Option<String>,
+    /// Milliseconds since the Unix epoch.
+    #[serde(default)]
+    pub checked_at: u64,
+}
+
+/// The model list endpoint and auth headers for each provider.
+fn probe_request(config: &Config) -> (String, Vec<(&'static str, String)>) {
+    let base = |default: &str| {
+        if config.base_url.is_empty() {
+            default.to_string()
+        } else {
+            config.base_url.clone()
+        }
+    };
+    let bearer = ("Authorization", format!("Bearer {}", config.api_key));
+    match config.config_type {
+        ConfigType::Claude => (
+            format!("{}/v1/models", base("https://api.anthropic.com")),
+            vec![
+                ("x-api-key", config.api_key.clone()),
+                ("anthropic-version", "2023-06-01".to_string()),
+                bearer,
+            ],
+        ),
+        ConfigType::Gemini => (
+            format!("{}/v1beta/models", base("https://generativelanguage.googleapis.com")),
+            vec![("x-goog-api-key", config.api_key.clone())],
+        ),
+        ConfigType::Codex => match codex_azure(config) {
+            Some(azure) => (
+                format!("{}/models?api-version={}", config.base_url, azure.api_version),
+                vec![("api-key", config.api_key.clone())],
+            ),
+            None => (format!("{}/models", codex_base_url(config)), vec![bearer]),
+        },
+        ConfigType::Other(_) => (config.base_url.clone(), vec![bearer]),
+    }
+}
+
+pub fn check_config(config: &Config, timeout: Duration) -> HealthCheck {
+    let (url, headers) = probe_request(config);
+    if url.is_empty() {
+        return HealthCheck {
+            status: HealthStatus::Unreachable,
+            http_status: None,
+            latency_ms: 0,
+            error: Some("Config has no base URL to check".to_string()),
+            checked_at: now_millis(),
+        };
+    }
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(timeout))
+        .http_status_as_error(false)
+        .build()
+        .into();
+    let mut request = agent.get(&url);
+    for (name, value) in headers {
+        request = request.header(name, value);
+    }
+
+    let started = Instant::now();
+    let result = request.call();
+    let latency_ms = started.elapsed().as_millis() as u64;
+    match result {
+        Ok(response) => {
+            let code = response.status().as_u16();
+            let status = match code {
+                401 | 403 => HealthStatus::AuthFailed,
+                500..=599 => HealthStatus::ServerError,
+                _ => HealthStatus::Reachable,
+            };
+            HealthCheck {
+                status,
+                http_status: Some(code),
+                latency_ms,
+                error: (status != HealthStatus::Reachable).then(|| format!("HTTP {}", code)),
+                checked_at: now_millis(),
+            }
+        }
+        Err(e) => HealthCheck {
+            status: HealthStatus::Unreachable,
+            http_status: None,
+            latency_ms,
+            error: Some(e.to_string()),
+            checked_at: now_millis(),
+        },
+    }
+}
+
+static CHECK_ALL_RUNNING: AtomicBool = AtomicBool::new(false);
+static CHECK_ALL_CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// Held for the length of a `check_all_configs` run; only one runs at a time.
+pub struct CheckAllRun(());
+
+impl CheckAllRun {
+    /// `None` if a run is already in progress.
+    pub fn start() -> Option<Self> {
+        CHECK_ALL_RUNNING
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .ok()?;
+        CHECK_ALL_CANCELLED.store(false, Ordering::SeqCst);
+        Some(CheckAllRun(()))
+    }
+
+    pub fn cancelled(&self) -> bool {
+        CHECK_ALL_CANCELLED.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for CheckAllRun {
+    fn drop(&mut self) {
+        CHECK_ALL_RUNNING.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Asks the running `check_all_configs` to stop. Checks already in flight
+/// finish (each is bounded by its timeout); no new ones start. Returns
+/// whether a run was in progress.
+pub fn cancel_check_all() -> bool {
+    CHECK_ALL_CANCELLED.store(true, Ordering::SeqCst);
+    CHECK_ALL_RUNNING.load(Ordering::SeqCst)
+}
+
+/// Runs `f` over `items` on at most `limit` threads, keeping the input order.
+pub fn map_bounded<T: Sync, R: Send>(items: &[T], limit: usize, f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new(items.iter().map(|_| None).collect());
+    std::thread::scope(|scope| {
+        for _ in 0..limit.clamp(1, items.len().max(1)) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(i) else {
+                    break;
+                };
+                let result = f(item);
+                results.lock().unwrap_or_else(|e| e.into_inner())[i] = Some(result);
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap_or_else(|e| e.into_inner())
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// `rounds` sequential checks of one config. Each check opens a fresh
+/// connection, so the latency covers connect + TLS + time to first byte.
+pub fn benchmark(config: &Config, rounds: u8) -> (Benchmark, Option<String>) {
+    let mut latencies = Vec::new();
+    let mut failures = 0u32;
+    let mut auth_ok = false;
+    let mut last_error = None;
+    for _ in 0..rounds {
+        let check = check_config(config, BENCHMARK_TIMEOUT);
+        if check.status != HealthStatus::Unreachable {
+            latencies.push(check.latency_ms);
+        }
+        if check.status == HealthStatus::Reachable {
+            auth_ok = true;
+        } else {
+            failures += 1;
+            last_error = check.error;
+        }
+    }
+    let benchmark = Benchmark {
+        min_latency_ms: latencies.iter().min().copied(),
+        avg_latency_ms: (!latencies.is_empty()).then(|| latencies.iter().sum::<u64>() / latencies.len() as u64),
+        error_rate: f64::from(failures) / f64::from(rounds.max(1)),
+        auth_ok,
+        measured_at: now_millis(),
+    };
+    (benchmark, last_error)
+}
@@ -0,0 +1,265 @@
+//! Audit log of config switches, kept in `history.json` next to
+//! `configs.json`. Entries carry the config id, name and type but never a key.
+//! Past `ConfigStore.history_threshold` entries the log is compacted: the
+//! newest `checkpoint_interval` are kept behind a `Snapshot` entry holding
+//! what replaying the dropped ones gives.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{get_config_path, load_store, write_file_atomic, Config, ConfigType};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryAction {
+    Activate,
+    Deactivate,
+    Update,
+    Delete,
+    /// Written by `compact_event_log`; its config fields are empty.
+    Snapshot,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub action: HistoryAction,
+    pub config_id: String,
+    pub name: String,
+    pub config_type: ConfigType,
+    /// The state derived from every entry up to this one, on a `Snapshot`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub snapshot: Vec<ConfigState>,
+}
+
+/// One config as replaying the history leaves it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConfigState {
+    pub config_id: String,
+    pub name: String,
+    pub config_type: ConfigType,
+    pub active: bool,
+    /// Seconds since the Unix epoch.
+    #[serde(default)]
+    pub last_activated_at: Option<u64>,
+}
+
+fn get_history_path() -> PathBuf {
+    get_config_path().with_file_name("history.json")
+}
+
+/// Oldest entry first. A missing or unreadable file is just an empty history.
+pub fn load_history() -> Vec<HistoryEntry> {
+    let path = get_history_path();
+    let Ok(content) = fs::read_to_string(&path) else {
+        return Vec::new();
+    };
+    serde_json::from_str(&content).unwrap_or_else(|e| {
+        eprintln!("history: failed to parse {}: {}", path.display(), e);
+        Vec::new()
+    })
+}
+
+fn save(entries: &[HistoryEntry]) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(entries).map_err(|e| e.to_string())?;
+    write_file_atomic(&get_history_path(), content.as_bytes())
+}
+
+/// Replays `entries` (oldest first, starting from a `Snapshot` if there is
+/// one) into the configs they mention, ordered by first appearance. Deleted
+/// configs are left out, and activating a config deactivates the others of
+/// its type.
+pub fn replay(entries: &[HistoryEntry]) -> Vec<ConfigState> {
+    let mut states: Vec<ConfigState> = Vec::new();
+    for entry in entries {
+        if entry.action == HistoryAction::Snapshot {
+            states = entry.snapshot.clone();
+            continue;
+        }
+        let index = match states.iter().position(|s| s.config_id == entry.config_id) {
+            Some(index) => index,
+            None => {
+                states.push(ConfigState {
+                    config_id: entry.config_id.clone(),
+                    name: entry.name.clone(),
+                    config_type: entry.config_type.clone(),
+                    active: false,
+                    last_activated_at: None,
+                });
+                states.len() - 1
+            }
+        };
+        states[index].name = entry.name.clone();
+        match entry.action {
+            HistoryAction::Activate => {
+                for state in states.iter_mut().filter(|s| s.config_type == entry.config_type) {
+                    state.active = false;
+                }
+                states[index].active = true;
+                states[index].last_activated_at = Some(entry.timestamp);
+            }
+            HistoryAction::Deactivate => states[index].active = false,
+            HistoryAction::Update | HistoryAction::Snapshot => {}
+            HistoryAction::Delete => {
+                states.remove(index);
+            }
+        }
+    }
+    states
+}
+
+/// When each config was last activated according to the history, by id.
+pub fn last_activated() -> HashMap<String, u64> {
+    replay(&load_history())
+        .into_iter()
+        .filter_map(|s| Some((s.config_id, s.last_activated_at?)))
+        .collect()
+}
+
+/// Keeps the newest `ConfigStore.checkpoint_interval` entries behind a
+/// `Snapshot` of everything before them, and returns how many entries were
+/// removed. A log that is already that short past its snapshot is left alone.
+pub fn compact_event_log() -> Result<usize, String> {
+    let entries = load_history();
+    let keep = load_store().checkpoint_interval;
+    let events = entries.iter().filter(|e| e.action != HistoryAction::Snapshot).count();
+    if events <= keep {
+        return Ok(0);
+    }
+    let split = entries.len() - keep;
+    let snapshot = HistoryEntry {
+        timestamp: entries[split - 1].timestamp,
+        action: HistoryAction::Snapshot,
+        config_id: String::new(),
+        name: String::new(),
+        config_type: ConfigType::default(),
+        snapshot: replay(&entries[..split]),
+    };
+    let compacted: Vec<HistoryEntry> = std::iter::once(snapshot).chain(entries[split..].iter().cloned()).collect();
+    save(&compacted).map_err(|e| format!("history: {}", e))?;
+    Ok(entries.len() - compacted.len())
+}
+
+/// Appends an entry, compacting the log once it's past
+/// `ConfigStore.history_threshold`. Errors are logged rather than returned so
+/// a read-only history file never fails the switch it's recording.
+pub fn record(action: HistoryAction, config: &Config) {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let mut entries = load_history();
+    entries.push(HistoryEntry {
+        timestamp,
+        action,
+        config_id: config.id.clone(),
+        name: config.name.clone(),
+        config_type: config.config_type.clone(),
+        snapshot: Vec::new(),
+    });
+    if let Err(e) = save(&entries) {
+        eprintln!("history: {}", e);
+        return;
+    }
+    if entries.len() > load_store().history_threshold {
+        if let Err(e) = compact_event_log() {
+            eprintln!("{}", e);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::sandbox;
+
+    fn config(id: &str, config_type: ConfigType) -> Config {
+        Config {
+            id: id.to_string(),
+            name: id.to_string(),
+            config_type,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn replay_tracks_the_active_config_per_type() {
+        let entry = |action, id: &str, config_type: ConfigType, timestamp| HistoryEntry {
+            timestamp,
+            action,
+            config_id: id.to_string(),
+            name: id.to_string(),
+            config_type,
+            snapshot: Vec::new(),
+        };
+        let states = replay(&[
+            entry(HistoryAction::Activate, "a", ConfigType::Claude, 1),
+            entry(HistoryAction::Activate, "g", ConfigType::Gemini, 2),
+            entry(HistoryAction::Activate, "b", ConfigType::Claude, 3),
+            entry(HistoryAction::Activate, "c", ConfigType::Claude, 4),
+            entry(HistoryAction::Delete, "c", ConfigType::Claude, 5),
+        ]);
+        let summary: Vec<(&str, bool, Option<u64>)> = states
+            .iter()
+            .map(|s| (s.config_id.as_str(), s.active, s.last_activated_at))
+            .collect();
+        assert_eq!(summary, [("a", false, Some(1)), ("g", true, Some(2)), ("b", false, Some(3))]);
+    }
+
+    #[test]
+    fn compaction_keeps_the_newest_entries_behind_a_snapshot() {
+        let _sandbox = sandbox();
+        let (claude, codex) = (config("a", ConfigType::Claude), config("x", ConfigType::Codex));
+        record(HistoryAction::Activate, &codex);
+        for _ in 0..10 {
+            record(HistoryAction::Activate, &claude);
+            record(HistoryAction::Deactivate, &claude);
+        }
+        let before = replay(&load_history());
+
+        let mut store = load_store();
+        store.checkpoint_interval = 5;
+        crate::save_store(&store).unwrap();
+        assert_eq!(compact_event_log().unwrap(), 15);
+
+        let entries = load_history();
+        assert_eq!(entries.len(), 6);
+        assert_eq!(entries[0].action, HistoryAction::Snapshot);
+        assert_eq!(replay(&entries), before);
+        assert!(replay(&entries).iter().any(|s| s.config_id == "x" && s.active));
+        assert_eq!(compact_event_log().unwrap(), 0);
+        assert_eq!(load_history().len(), 6);
+
+        // Newer events are folded into a fresh snapshot with the old one.
+        for _ in 0..3 {
+            record(HistoryAction::Update, &claude);
+        }
+        assert_eq!(compact_event_log().unwrap(), 3);
+        let entries = load_history();
+        assert_eq!(entries.len(), 6);
+        assert_eq!(entries.iter().filter(|e| e.action == HistoryAction::Snapshot).count(), 1);
+        assert_eq!(replay(&entries), before);
+    }
+
+    #[test]
+    fn record_compacts_past_the_threshold() {
+        let _sandbox = sandbox();
+        let mut store = load_store();
+        store.history_threshold = 10;
+        store.checkpoint_interval = 4;
+        crate::save_store(&store).unwrap();
+
+        let claude = config("a", ConfigType::Claude);
+        for _ in 0..11 {
+            record(HistoryAction::Update, &claude);
+        }
+        let entries = load_history();
+        assert_eq!(entries.len(), 5);
+        assert_eq!(entries[0].action, HistoryAction::Snapshot);
+    }
+}
@@ -1,18 +1,116 @@
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-#[serde(rename_all = "lowercase")]
+mod backups;
+mod budget;
+mod cli;
+mod deeplink;
+mod events;
+mod expiry;
+mod external;
+mod failover;
+mod health;
+mod history;
+mod migrations;
+mod opencode_auth;
+mod paste;
+mod plan;
+mod project;
+mod rotation;
+mod secrets;
+mod state;
+#[cfg(test)]
+mod test_support;
+mod tray;
+mod usage;
+mod watcher;
+mod written;
+
+use history::{HistoryAction, HistoryEntry};
+use plan::{ActivationPlan, Plan};
+use project::ProjectActivation;
+
+/// Serialized as a plain lowercase string. Any name other than the built-in
+/// ones becomes `Other`: a passthrough type whose configs are stored and can
+/// be marked active, but are never written to any tool's files.
+/// Ordered as declared, which is also the order `get_configs` lists types in.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Default, PartialOrd, Ord)]
+#[serde(from = "String", into = "String")]
 pub enum ConfigType {
+    #[default]
     Claude,
     Gemini,
     Codex,
+    Other(String),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl From<String> for ConfigType {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "claude" => ConfigType::Claude,
+            "gemini" => ConfigType::Gemini,
+            "codex" => ConfigType::Codex,
+            _ => ConfigType::Other(s),
+        }
+    }
+}
+
+impl From<ConfigType> for String {
+    fn from(t: ConfigType) -> Self {
+        match t {
+            ConfigType::Claude => "claude".to_string(),
+            ConfigType::Gemini => "gemini".to_string(),
+            ConfigType::Codex => "codex".to_string(),
+            ConfigType::Other(s) => s,
+        }
+    }
+}
+
+fn parse_config_type(s: &str) -> Result<ConfigType, String> {
+    if s.trim().is_empty() {
+        return Err("Invalid config type".to_string());
+    }
+    Ok(ConfigType::from(s.to_string()))
+}
+
+/// Codex-only knobs for the generated config.toml. Every field is optional so
+/// that unset values fall back to the built-in defaults.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct CodexOptions {
+    #[serde(default)]
+    pub provider_name: Option<String>,
+    #[serde(default)]
+    pub reasoning_effort: Option<String>,
+    #[serde(default)]
+    pub wire_api: Option<String>,
+    /// `disable_response_storage` in config.toml; unset means `true`. Turn it
+    /// off to keep responses stored server-side, e.g. for debugging.
+    #[serde(default)]
+    pub disable_response_storage: Option<bool>,
+    /// Azure OpenAI mode, see `AzureOptions`.
+    #[serde(default)]
+    pub azure: Option<AzureOptions>,
+}
+
+/// Azure OpenAI through Codex's `env_key` / `query_params` provider settings.
+/// The config's base URL is the resource endpoint
+/// (`https://<resource>.openai.azure.com/openai`) and the key goes into
+/// `~/.codex/.env` as `AZURE_OPENAI_API_KEY` instead of auth.json.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct AzureOptions {
+    /// Deployment name, written as `model`.
+    pub deployment: String,
+    /// Sent as the `api-version` query parameter, e.g. `2025-04-01-preview`.
+    pub api_version: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
 pub struct Config {
+    #[serde(default)]
     pub id: String,
     pub name: String,
     pub config_type: ConfigType,
@@ -20,18 +118,585 @@ pub struct Config {
     pub base_url: String,
     #[serde(default)]
     pub model: String,
+    /// Active for `ActivationTarget::Tool`, i.e. written to the tool's own
+    /// files. Stores from before targets existed only have this flag.
+    #[serde(default)]
     pub is_active: bool,
+    /// Active for `ActivationTarget::Opencode`: this config's key is the one in
+    /// its type's opencode.json provider block.
+    #[serde(default)]
+    pub opencode_active: bool,
+    #[serde(default)]
+    pub options: ConfigOptions,
+    /// Came from a team config file via `import_shared_configs`. Shared
+    /// configs can be activated but not edited or deleted locally.
+    #[serde(default)]
+    pub is_shared: bool,
+    /// Free-form labels ("work", "relay", ...) for filtering long lists.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Milliseconds since the Unix epoch. `None` for configs saved before
+    /// these were tracked; they fill in on the next update / activation.
+    #[serde(default)]
+    pub created_at: Option<u64>,
+    #[serde(default)]
+    pub updated_at: Option<u64>,
+    #[serde(default)]
+    pub last_activated_at: Option<u64>,
+    #[serde(default)]
+    pub activation_count: u64,
+    /// Result of the last `benchmark_configs` run that included this config.
+    #[serde(default)]
+    pub last_benchmark: Option<Benchmark>,
+    /// Result of the last `check_all_configs` run that reached this config.
+    #[serde(default)]
+    pub last_health: Option<health::HealthCheck>,
+    /// Result of the last `fetch_usage` for this config.
+    #[serde(default)]
+    pub last_usage: Option<usage::Usage>,
+    /// Spend noted by hand via `set_usage_note`, for providers `fetch_usage`
+    /// can't query.
+    #[serde(default)]
+    pub usage_note: Option<String>,
+    /// Position within its type, set by `reorder_configs`. Gaps are fine;
+    /// only the relative order matters.
+    #[serde(default)]
+    pub sort_index: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Benchmark {
+    /// `None` when no round got a response at all.
+    pub min_latency_ms: Option<u64>,
+    pub avg_latency_ms: Option<u64>,
+    /// Share of rounds that didn't come back as `reachable`, 0.0 to 1.0.
+    pub error_rate: f64,
+    /// At least one round was answered without rejecting the key.
+    pub auth_ok: bool,
+    /// Milliseconds since the Unix epoch.
+    pub measured_at: u64,
+}
+
+/// Where an activation is written. At most one config per type is active for
+/// each target, so e.g. one Claude config can drive Claude Code while another
+/// is the one exported to OpenCode.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ActivationTarget {
+    /// The tool's own files: Claude settings.json, Gemini .env, ~/.codex.
+    #[default]
+    Tool,
+    Opencode,
+}
+
+fn active_targets(config: &Config) -> Vec<ActivationTarget> {
+    let mut targets = Vec::new();
+    if config.is_active {
+        targets.push(ActivationTarget::Tool);
+    }
+    if config.opencode_active {
+        targets.push(ActivationTarget::Opencode);
+    }
+    targets
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Resets the bookkeeping fields for a config entering the store as a new
+/// entry (added, copied or pasted).
+fn mark_created(config: &mut Config) {
+    config.created_at = Some(now_millis());
+    config.updated_at = None;
+    config.last_activated_at = None;
+    config.activation_count = 0;
+    config.last_benchmark = None;
+    config.last_health = None;
+    config.last_usage = None;
+}
+
+/// Optional per-config settings beyond key/url/model. `add_config` and
+/// `update_config` take these as a single `options` object so new settings
+/// don't each need another command parameter.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct ConfigOptions {
+    #[serde(default)]
+    pub codex: Option<CodexOptions>,
+    /// Claude only: `Some(false)` writes `ANTHROPIC_STREAMING=0`, `Some(true)`
+    /// writes `1`, `None` leaves the variable unset.
+    #[serde(default)]
+    pub streaming_enabled: Option<bool>,
+    /// `Some(true)` writes `DO_NOT_TRACK=1` plus the tool's own opt-out var
+    /// (see `telemetry_env_vars`); otherwise they're left unset.
+    #[serde(default)]
+    pub telemetry_disabled: Option<bool>,
+    /// Request timeout written to the tool's timeout var (see
+    /// `timeout_env_var`), 1 to 300 seconds.
+    #[serde(default)]
+    pub timeout_seconds: Option<u32>,
+    /// How often the tool itself retries a failed request, 0 to
+    /// `MAX_RETRIES`, written to its retry var (see `max_retries_env_var`).
+    #[serde(default)]
+    pub max_retries: Option<u8>,
+    /// Claude only: the model for background / quick tasks, written as
+    /// `ANTHROPIC_SMALL_FAST_MODEL` next to `ANTHROPIC_MODEL` (`Config.model`).
+    #[serde(default)]
+    pub small_fast_model: Option<String>,
+    /// Claude only: extended thinking budget in tokens, written as
+    /// `ANTHROPIC_THINKING_BUDGET` and, through OpenCode, as the provider's
+    /// `x-thinking-budget` header.
+    #[serde(default)]
+    pub model_thinking_budget: Option<u32>,
+    /// API version to pin, written to the tool's version var (see
+    /// `api_version_env_var`).
+    #[serde(default)]
+    pub provider_version: Option<String>,
+    /// `(name, value)` of a version header the provider requires, e.g.
+    /// `("anthropic-version", "2023-06-01")`. Added to the OpenCode provider's
+    /// `options.headers`, and for Claude written as
+    /// `ANTHROPIC_API_VERSION_HEADER` (`name: value`).
+    #[serde(default)]
+    pub api_version_header: Option<(String, String)>,
+    /// Client certificate (PEM) for proxies that require mTLS, written to the
+    /// tool's client certificate var (see `client_cert_env_vars`).
+    #[serde(default)]
+    pub http_client_cert_path: Option<String>,
+    /// Private key for `http_client_cert_path`, if not in the same file.
+    #[serde(default)]
+    pub http_client_key_path: Option<String>,
+    /// Extra env vars written after everything else, for relays that expect
+    /// e.g. `ANTHROPIC_API_KEY` or `API_TIMEOUT_MS`.
+    #[serde(default)]
+    pub env_overrides: Vec<EnvVar>,
+    /// Write only `env_overrides`, skipping the built-in key/url/model vars.
+    #[serde(default)]
+    pub replace_builtin_env: bool,
+    /// Gemini only: put the API key in `~/.gemini/settings.json`
+    /// (`security.auth.apiKey`) instead of `.env`, for machines where env
+    /// files aren't picked up. Base URL, model and extra vars stay in `.env`.
+    #[serde(default)]
+    pub gemini_settings_file: bool,
+    /// Gemini only: OAuth2 scopes, written comma-separated as
+    /// `GOOGLE_OAUTH_SCOPES` for Google auth that asks for non-default ones
+    /// (e.g. Vertex AI through a service account).
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    /// A notes file (runbook, billing info, ...) opened by `open_config_notes`.
+    #[serde(default)]
+    pub notes_file_path: Option<String>,
+    /// When the key expires, as `YYYY-MM-DD` or an RFC 3339 timestamp (see
+    /// `expiry::parse_expiry`). Only used for reminders; nothing is deactivated.
+    #[serde(default)]
+    pub expires_at: Option<String>,
+    /// Daily spend in US dollars above which activating warns (see
+    /// `budget.rs`). Never blocks.
+    #[serde(default)]
+    pub cost_budget_daily_usd: Option<f64>,
+    /// Free text on how to rotate the key (where to get a new one, who to ask).
+    #[serde(default)]
+    pub rotation_note: Option<String>,
+    /// POSTed to after each activation, see `events::post_webhook`.
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EnvVar {
+    pub key: String,
+    pub value: String,
+}
+
+/// The smallest `budget_tokens` the API accepts for extended thinking.
+const MIN_THINKING_BUDGET: u32 = 1024;
+/// Upper bound for `ConfigOptions.max_retries`.
+const MAX_RETRIES: u8 = 10;
+
+fn validate_config_options(config_type: &ConfigType, options: &ConfigOptions) -> Result<(), String> {
+    if let Some(codex) = &options.codex {
+        validate_codex_options(codex)?;
+    }
+    if !options.env_overrides.is_empty() && config_type == &ConfigType::Codex {
+        return Err("Env overrides are not supported for Codex configs".to_string());
+    }
+    if options.telemetry_disabled == Some(true) && telemetry_env_vars(config_type).is_empty() {
+        return Err("Telemetry opt-out is not supported for this config type".to_string());
+    }
+    if let Some(timeout) = options.timeout_seconds {
+        if timeout_env_var(config_type).is_none() {
+            return Err("Request timeout is not supported for this config type".to_string());
+        }
+        if !(1..=300).contains(&timeout) {
+            return Err("Timeout must be between 1 and 300 seconds".to_string());
+        }
+    }
+    if let Some(retries) = options.max_retries {
+        if max_retries_env_var(config_type).is_none() {
+            return Err("max_retries is not supported for this config type".to_string());
+        }
+        if retries > MAX_RETRIES {
+            return Err(format!("max_retries must be at most {}", MAX_RETRIES));
+        }
+    }
+    if let Some(model) = &options.small_fast_model {
+        if config_type != &ConfigType::Claude {
+            return Err("small_fast_model only applies to Claude configs".to_string());
+        }
+        if model.trim().is_empty() {
+            return Err("small_fast_model must not be empty".to_string());
+        }
+    }
+    if let Some(budget) = options.model_thinking_budget {
+        if config_type != &ConfigType::Claude {
+            return Err("model_thinking_budget only applies to Claude configs".to_string());
+        }
+        if budget < MIN_THINKING_BUDGET {
+            return Err(format!("Thinking budget must be at least {} tokens", MIN_THINKING_BUDGET));
+        }
+    }
+    if let Some(version) = &options.provider_version {
+        if api_version_env_var(config_type).is_none() {
+            return Err("API version pinning is not supported for this config type".to_string());
+        }
+        if version.trim().is_empty() || version.contains(char::is_whitespace) {
+            return Err(format!("Invalid API version '{}'", version));
+        }
+    }
+    if let Some((name, value)) = &options.api_version_header {
+        if matches!(config_type, ConfigType::Other(_)) {
+            return Err("API version headers are not supported for this config type".to_string());
+        }
+        let valid_name = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid_name {
+            return Err(format!("Invalid header name '{}'", name));
+        }
+        if value.trim().is_empty() || value.contains(['\r', '\n']) {
+            return Err(format!("Invalid value for header '{}'", name));
+        }
+    }
+    if options.http_client_cert_path.is_some() || options.http_client_key_path.is_some() {
+        if client_cert_env_vars(config_type).is_none() {
+            return Err("Client certificates are not supported for this config type".to_string());
+        }
+        if options.http_client_cert_path.is_none() {
+            return Err("http_client_key_path needs http_client_cert_path".to_string());
+        }
+    }
+    for path in [&options.http_client_cert_path, &options.http_client_key_path].into_iter().flatten() {
+        if !Path::new(path).is_file() {
+            return Err(format!("Client certificate file '{}' does not exist", path));
+        }
+    }
+    for var in &options.env_overrides {
+        let valid = !var.key.is_empty()
+            && !var.key.starts_with(|c: char| c.is_ascii_digit())
+            && var.key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(format!("Invalid env var name '{}'", var.key));
+        }
+    }
+    if options.replace_builtin_env && options.env_overrides.is_empty() {
+        return Err("replace_builtin_env needs at least one env override".to_string());
+    }
+    if options.gemini_settings_file && config_type != &ConfigType::Gemini {
+        return Err("gemini_settings_file only applies to Gemini configs".to_string());
+    }
+    if !options.scopes.is_empty() && config_type != &ConfigType::Gemini {
+        return Err("scopes only apply to Gemini configs".to_string());
+    }
+    for scope in &options.scopes {
+        // Commas separate the scopes in the env var.
+        if scope.is_empty() || scope.contains(char::is_whitespace) || scope.contains(',') {
+            return Err(format!("Invalid OAuth scope '{}'", scope));
+        }
+    }
+    if let Some(expires_at) = &options.expires_at {
+        expiry::parse_expiry(expires_at)?;
+    }
+    if let Some(budget) = options.cost_budget_daily_usd {
+        if !budget.is_finite() || budget <= 0.0 {
+            return Err("Daily budget must be a positive number of dollars".to_string());
+        }
+    }
+    if let Some(url) = &options.webhook_url {
+        if !(url.starts_with("http://") || url.starts_with("https://")) {
+            return Err(format!("Webhook URL '{}' must start with http:// or https://", url));
+        }
+    }
+    Ok(())
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConfigStore {
+    /// Schema version, see `migrations.rs`. Missing means version 0.
+    #[serde(default)]
+    pub version: u32,
     pub configs: Vec<Config>,
+    /// Names of the env vars beyond key/url/model (see `config_extra_env`,
+    /// plus an Azure Codex key in `~/.codex/.env`) currently written for each
+    /// type. Only these are removed on the next activation or clear, so
+    /// variables the user set by hand are left alone; tracked separately from
+    /// the configs so they can be removed even after the config that set them
+    /// was edited or deleted.
+    #[serde(default)]
+    pub applied_env: HashMap<ConfigType, Vec<String>>,
+    /// Configs activated into a project directory, at most one per project
+    /// and type. Independent of the user-wide `is_active` flag.
+    #[serde(default)]
+    pub project_activations: Vec<ProjectActivation>,
+    #[serde(default)]
+    pub secret_backend: SecretBackend,
+    #[serde(default)]
+    pub profiles: Vec<Profile>,
+    /// Config ids to fail over to, in order, per type. See `failover.rs`.
+    #[serde(default)]
+    pub fallbacks: HashMap<ConfigType, Vec<String>>,
+    #[serde(default)]
+    pub failover: FailoverSettings,
+    /// Key rotation ring per type. See `rotation.rs`.
+    #[serde(default)]
+    pub rotations: HashMap<ConfigType, rotation::RotationPolicy>,
+    /// How many days ahead `configs-expiring` warns about expiring keys.
+    #[serde(default = "default_expiry_warning_days")]
+    pub expiry_warning_days: u32,
+    /// How many backups of configs.json `backups.rs` keeps; 0 turns them off.
+    #[serde(default = "default_backup_retention")]
+    pub backup_retention: usize,
+    /// History length past which `history::record` compacts it.
+    #[serde(default = "default_history_threshold")]
+    pub history_threshold: usize,
+    /// How many of the newest history entries compaction keeps.
+    #[serde(default = "default_checkpoint_interval")]
+    pub checkpoint_interval: usize,
+    #[serde(default)]
+    pub settings: Settings,
+    /// Set when configs.json exists but can't be used as-is (e.g. it was
+    /// written by a newer app). `save_store` refuses to overwrite it then.
+    #[serde(skip)]
+    pub load_error: Option<String>,
+}
+
+/// App-wide preferences, as opposed to per-config `ConfigOptions`.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct Settings {
+    /// Show a desktop notification when a config is switched. The events
+    /// themselves (`events.rs`) are always emitted.
+    #[serde(default)]
+    pub notifications_enabled: bool,
+    /// Hide the tray icon (`tray.rs`).
+    #[serde(default)]
+    pub tray_disabled: bool,
+    /// Overrides for where the tool files live, for portable installs and
+    /// non-standard layouts. `~`, `$VAR` / `${VAR}` and `%VAR%` are expanded
+    /// (see `expand_path`); unset means the usual location.
+    #[serde(default)]
+    pub codex_dir: Option<String>,
+    #[serde(default)]
+    pub opencode_config_path: Option<String>,
+    #[serde(default)]
+    pub claude_settings_path: Option<String>,
+    /// Put OpenCode keys in its auth store (`opencode_auth.rs`) and leave
+    /// opencode.json's `apiKey` fields empty.
+    #[serde(default)]
+    pub opencode_auth_store: bool,
+}
+
+/// Just the settings from configs.json, for the path getters. Unlike
+/// `load_store` this skips migrations and keyring lookups, and any problem
+/// simply means default settings.
+fn load_settings() -> Settings {
+    fs::read_to_string(get_config_path())
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|mut value| value.get_mut("settings").map(serde_json::Value::take))
+        .and_then(|settings| serde_json::from_value(settings).ok())
+        .unwrap_or_default()
+}
+
+/// Expands a leading `~` and `$VAR`, `${VAR}` or `%VAR%` references. An
+/// unset variable is an error rather than an empty string, which would
+/// silently turn e.g. `$CODEX_HOME/x` into `/x`.
+fn expand_path(path: &str) -> Result<PathBuf, String> {
+    let var = |name: &str| {
+        std::env::var(name).map_err(|_| format!("Environment variable '{}' in path '{}' is not set", name, path))
+    };
+    let mut out = String::new();
+    let mut rest = path.trim();
+    if rest == "~" || rest.starts_with("~/") || rest.starts_with("~\\") {
+        out.push_str(&get_user_home().to_string_lossy());
+        rest = &rest[1..];
+    }
+    while let Some(i) = rest.find(['$', '%']) {
+        out.push_str(&rest[..i]);
+        let after = &rest[i + 1..];
+        let (name, remaining) = if rest[i..].starts_with('%') {
+            match after.find('%') {
+                Some(end) if end > 0 => (&after[..end], &after[end + 1..]),
+                _ => ("", after),
+            }
+        } else if let Some(braced) = after.strip_prefix('{') {
+            let end = braced.find('}').ok_or_else(|| format!("Unclosed '${{' in path '{}'", path))?;
+            (&braced[..end], &braced[end + 1..])
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..end], &after[end..])
+        };
+        if name.is_empty() {
+            // A lone `$` or `%` is just a character.
+            out.push_str(&rest[i..i + 1]);
+            rest = after;
+        } else {
+            out.push_str(&var(name)?);
+            rest = remaining;
+        }
+    }
+    out.push_str(rest);
+    Ok(PathBuf::from(out))
+}
+
+/// A path setting resolved for use, falling back to `default` when it's
+/// unset or no longer expands (logged, since `update_settings` checked it).
+fn settings_path(setting: Option<&str>, default: impl FnOnce() -> PathBuf) -> PathBuf {
+    match setting.map(expand_path) {
+        Some(Ok(path)) => path,
+        Some(Err(e)) => {
+            eprintln!("settings: {}, using the default location", e);
+            default()
+        }
+        None => default(),
+    }
+}
+
+/// Each path setting must expand to an absolute path whose parent directory
+/// exists or can be created.
+fn validate_settings(settings: &Settings) -> Result<(), String> {
+    let paths = [
+        ("codex_dir", &settings.codex_dir),
+        ("opencode_config_path", &settings.opencode_config_path),
+        ("claude_settings_path", &settings.claude_settings_path),
+    ];
+    for (field, value) in paths {
+        let Some(value) = value else {
+            continue;
+        };
+        let path = expand_path(value)?;
+        if !path.is_absolute() {
+            return Err(format!("{} must be an absolute path, got '{}'", field, value));
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("{}: cannot create {}: {}", field, parent.display(), e))?;
+        }
+    }
+    Ok(())
+}
+
+/// A named set of configs switched together, at most one per type.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Profile {
+    pub id: String,
+    pub name: String,
+    /// Config id to activate for each type.
+    pub entries: HashMap<ConfigType, String>,
+    /// Also write the profile's Claude/Gemini/Codex configs into opencode.json.
+    #[serde(default)]
+    pub opencode: bool,
+}
+
+fn default_expiry_warning_days() -> u32 {
+    14
+}
+
+fn default_backup_retention() -> usize {
+    20
+}
+
+/// When the background failover checks run. Off until the user enables it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FailoverSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_failover_interval")]
+    pub interval_minutes: u32,
+    /// Consecutive failed checks before switching.
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+}
+
+fn default_failover_interval() -> u32 {
+    5
+}
+
+fn default_failure_threshold() -> u32 {
+    3
+}
+
+impl Default for FailoverSettings {
+    fn default() -> Self {
+        FailoverSettings {
+            enabled: false,
+            interval_minutes: default_failover_interval(),
+            failure_threshold: default_failure_threshold(),
+        }
+    }
+}
+
+/// Where API keys are kept. `Keyring` is opted into with
+/// `migrate_secrets_to_keyring`; see `secrets.rs`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretBackend {
+    #[default]
+    File,
+    Keyring,
+}
+
+impl Default for ConfigStore {
+    fn default() -> Self {
+        ConfigStore {
+            version: migrations::CURRENT_VERSION,
+            configs: Vec::new(),
+            applied_env: HashMap::new(),
+            project_activations: Vec::new(),
+            secret_backend: SecretBackend::default(),
+            profiles: Vec::new(),
+            fallbacks: HashMap::new(),
+            failover: FailoverSettings::default(),
+            rotations: HashMap::new(),
+            expiry_warning_days: default_expiry_warning_days(),
+            backup_retention: default_backup_retention(),
+            history_threshold: default_history_threshold(),
+            checkpoint_interval: default_checkpoint_interval(),
+            settings: Settings::default(),
+            load_error: None,
+        }
+    }
+}
+
+fn default_history_threshold() -> usize {
+    1000
+}
+
+fn default_checkpoint_interval() -> usize {
+    200
 }
 
+/// `CLAUDE_CONFIG_MANAGER_CONFIG_DIR` overrides the directory, e.g. for CI
+/// or to keep a separate store while testing.
 fn get_config_path() -> PathBuf {
-    let config_dir = dirs::config_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join("claude-config-manager");
+    let config_dir = std::env::var_os("CLAUDE_CONFIG_MANAGER_CONFIG_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            dirs::config_dir()
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join("claude-config-manager")
+        });
     fs::create_dir_all(&config_dir).ok();
     config_dir.join("configs.json")
 }
@@ -40,8 +705,30 @@ fn get_user_home() -> PathBuf {
     dirs::home_dir().unwrap_or_else(|| PathBuf::from("."))
 }
 
+/// The store as last saved. Served from memory (`state.rs`) unless
+/// configs.json changed on disk since. Callers that go on to save should use
+/// `load_store_locked` instead.
 fn load_store() -> ConfigStore {
     let path = get_config_path();
+    let stamp = state::stamp(&path);
+    if let Some(store) = state::cached(stamp) {
+        return store;
+    }
+    let store = read_store(&path);
+    state::remember(stamp, &store);
+    store
+}
+
+/// `load_store` for a read-modify-write. Keep the lock until after
+/// `save_store` and any tool file writes, so concurrent commands can't
+/// interleave.
+fn load_store_locked() -> (state::StoreLock, ConfigStore) {
+    let lock = state::lock();
+    (lock, load_store())
+}
+
+fn read_store(path: &Path) -> ConfigStore {
+    let path = path.to_path_buf();
     if !path.exists() {
         return ConfigStore::default();
     }
@@ -52,30 +739,181 @@ fn load_store() -> ConfigStore {
             return ConfigStore::default();
         }
     };
+    // A filesystem bug can leave NUL bytes in the file, which JSON allows
+    // nowhere; drop them rather than the whole store.
+    let nul_bytes = content.matches('\0').count();
+    let content = if nul_bytes > 0 {
+        eprintln!("load_store: removed {} NUL bytes from {}", nul_bytes, path.display());
+        content.replace('\0', "")
+    } else {
+        content
+    };
     if content.trim().is_empty() {
         return ConfigStore::default();
     }
-    match serde_json::from_str(&content) {
-        Ok(store) => store,
+    let mut value: serde_json::Value = match serde_json::from_str(&content) {
+        Ok(value) => value,
+        Err(e) => return back_up_unreadable_store(&path, e),
+    };
+    let migrated = match migrations::migrate(&mut value) {
+        Ok(migrated) => migrated,
         Err(e) => {
-            // Don't silently overwrite the user's data on next save: back up the
-            // unreadable file so it can be recovered, then start with an empty store.
-            eprintln!("load_store: failed to parse {}: {}", path.display(), e);
-            let backup = path.with_extension("json.broken");
-            if let Err(be) = fs::copy(&path, &backup) {
-                eprintln!("load_store: backup to {} failed: {}", backup.display(), be);
-            } else {
-                eprintln!("load_store: backed up unreadable file to {}", backup.display());
+            eprintln!("load_store: {}", e);
+            return ConfigStore {
+                load_error: Some(e),
+                ..ConfigStore::default()
+            };
+        }
+    };
+    let mut store: ConfigStore = match serde_json::from_value(value) {
+        Ok(store) => store,
+        Err(e) => return back_up_unreadable_store(&path, e),
+    };
+    if migrated {
+        // Keep only the first pre-migration copy; later migrations start
+        // from an already-migrated file.
+        let backup = path.with_extension("json.pre-migration");
+        if !backup.exists() {
+            if let Err(e) = fs::copy(&path, &backup) {
+                eprintln!("load_store: backup to {} failed: {}", backup.display(), e);
+            }
+        }
+        if let Err(e) = save_store(&store) {
+            eprintln!("load_store: failed to write migrated store: {}", e);
+        }
+    }
+    // An unreadable keyring leaves the reference in place; `save_store` writes
+    // it back untouched and `require_api_key` refuses to apply it.
+    for config in &mut store.configs {
+        if secrets::is_reference(&config.api_key) {
+            match secrets::resolve(&config.api_key) {
+                Ok(key) => config.api_key = key,
+                Err(e) => eprintln!("load_store: {}", e),
             }
-            ConfigStore::default()
         }
     }
+    store
+}
+
+/// Don't silently overwrite the user's data on next save: back up the
+/// unreadable file so it can be recovered, then start with an empty store.
+fn back_up_unreadable_store(path: &Path, error: serde_json::Error) -> ConfigStore {
+    eprintln!("load_store: failed to parse {}: {}", path.display(), error);
+    let backup = path.with_extension("json.broken");
+    if let Err(be) = fs::copy(path, &backup) {
+        eprintln!("load_store: backup to {} failed: {}", backup.display(), be);
+    } else {
+        eprintln!("load_store: backed up unreadable file to {}", backup.display());
+    }
+    ConfigStore::default()
 }
 
 fn save_store(store: &ConfigStore) -> Result<(), String> {
+    if let Some(e) = &store.load_error {
+        return Err(e.clone());
+    }
     let path = get_config_path();
-    let content = serde_json::to_string_pretty(store).map_err(|e| e.to_string())?;
-    fs::write(path, content).map_err(|e| e.to_string())
+    let content = if store.secret_backend == SecretBackend::Keyring {
+        let mut on_disk = store.clone();
+        for config in &mut on_disk.configs {
+            if config.api_key.is_empty() || secrets::is_reference(&config.api_key) {
+                continue;
+            }
+            match secrets::store(&config.id, &config.api_key) {
+                Ok(reference) => config.api_key = reference,
+                Err(e) => eprintln!("save_store: {}; keeping the key in configs.json", e),
+            }
+        }
+        serde_json::to_string_pretty(&on_disk)
+    } else {
+        serde_json::to_string_pretty(store)
+    }
+    .map_err(|e| e.to_string())?;
+    write_file_atomic(&path, content.as_bytes())?;
+    state::remember(state::stamp(&path), store);
+    backups::record(content.as_bytes(), store.backup_retention);
+    watcher::saved(content.as_bytes());
+    Ok(())
+}
+
+/// Follows `path` if it is a symlink (e.g. configs.json managed by stow), so
+/// writes go to the real file instead of replacing the link. A dangling link
+/// resolves to its target, which is then created.
+fn resolve_symlink(path: &Path) -> PathBuf {
+    let is_symlink = fs::symlink_metadata(path)
+        .map(|m| m.file_type().is_symlink())
+        .unwrap_or(false);
+    if !is_symlink {
+        return path.to_path_buf();
+    }
+    if let Ok(real) = fs::canonicalize(path) {
+        return real;
+    }
+    match fs::read_link(path) {
+        Ok(target) => path.parent().unwrap_or(Path::new(".")).join(target),
+        Err(e) => {
+            eprintln!("resolve_symlink: failed to read link {}: {}", path.display(), e);
+            path.to_path_buf()
+        }
+    }
+}
+
+/// Writes to a sibling `<name>.pending` file, flushes it, then renames it over
+/// `path`, so a crash or full disk mid-write leaves the old file intact. A
+/// symlinked `path` is resolved first so the link itself survives, and an
+/// existing file's permissions carry over (auth files are often 0600).
+/// A read-only mount, which permission bits don't reveal, gets its own
+/// message.
+fn write_file_atomic(path: &Path, content: &[u8]) -> Result<(), String> {
+    let path = &resolve_symlink(path);
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".pending");
+    let pending = path.with_file_name(name);
+
+    let written = fs::File::create(&pending).and_then(|mut file| {
+        if let Ok(metadata) = fs::metadata(path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+        file.write_all(content)?;
+        file.sync_all()
+    });
+    if let Err(e) = written.and_then(|_| replace_file(&pending, path)) {
+        let _ = fs::remove_file(&pending);
+        if e.kind() == std::io::ErrorKind::ReadOnlyFilesystem {
+            let dir = path.parent().unwrap_or(Path::new("."));
+            return Err(format!("Failed to write {}: {} is read-only", path.display(), dir.display()));
+        }
+        return Err(format!("Failed to write {}: {}", path.display(), e));
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn replace_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    fs::rename(from, to)
+}
+
+/// Windows has no atomic replace-over-existing rename: MoveFileExW with
+/// MOVEFILE_REPLACE_EXISTING can still be observed half-done if power is lost
+/// at the wrong moment. MOVEFILE_WRITE_THROUGH at least doesn't return until
+/// the move is on disk, which is as close as the platform gets.
+#[cfg(windows)]
+fn replace_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::{
+        MoveFileExW, MOVEFILE_REPLACE_EXISTING, MOVEFILE_WRITE_THROUGH,
+    };
+
+    let wide = |p: &Path| p.as_os_str().encode_wide().chain(Some(0)).collect::<Vec<u16>>();
+    let (from, to) = (wide(from), wide(to));
+    // SAFETY: both buffers are NUL-terminated and outlive the call.
+    let ok = unsafe {
+        MoveFileExW(from.as_ptr(), to.as_ptr(), MOVEFILE_REPLACE_EXISTING | MOVEFILE_WRITE_THROUGH)
+    };
+    if ok == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
 }
 
 fn read_json_object(path: &Path) -> Result<serde_json::Value, String> {
@@ -84,33 +922,240 @@ fn read_json_object(path: &Path) -> Result<serde_json::Value, String> {
     }
     let content = fs::read_to_string(path)
         .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
-    if content.trim().is_empty() {
-        return Ok(serde_json::json!({}));
+    parse_json_object(path, Some(&content))
+}
+
+/// `read_json_object` for content already in hand, e.g. from a `Plan`.
+fn parse_json_object(path: &Path, content: Option<&str>) -> Result<serde_json::Value, String> {
+    match content {
+        Some(content) if !content.trim().is_empty() => serde_json::from_str(content)
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e)),
+        _ => Ok(serde_json::json!({})),
     }
-    serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+fn to_json_pretty(value: &serde_json::Value) -> Result<String, String> {
+    serde_json::to_string_pretty(value).map_err(|e| e.to_string())
 }
 
 fn write_json_pretty(path: &Path, value: &serde_json::Value) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    write_config_file_atomic(path, &content)
+}
+
+/// How every tool config file is written: creates missing parent
+/// directories, then `write_file_atomic`, and records the write for
+/// `written::modified`.
+fn write_config_file_atomic(path: &Path, content: &str) -> Result<(), String> {
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)
             .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
     }
-    let content = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
-    fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    write_file_atomic(path, content.as_bytes())?;
+    written::record(path, Some(content.as_bytes()));
+    Ok(())
 }
 
 fn get_claude_settings_path() -> PathBuf {
-    get_user_home().join(".claude").join("settings.json")
+    settings_path(load_settings().claude_settings_path.as_deref(), || {
+        get_user_home().join(".claude").join("settings.json")
+    })
 }
 
 fn get_gemini_env_path() -> PathBuf {
     get_user_home().join(".gemini").join(".env")
 }
 
-fn update_claude_env(api_key: Option<&str>, base_url: Option<&str>, model: Option<&str>) -> Result<(), String> {
+fn get_gemini_settings_path() -> PathBuf {
+    get_user_home().join(".gemini").join("settings.json")
+}
+
+/// OpenCode looks under `$XDG_CONFIG_HOME`, falling back to `~/.config` on
+/// every platform. Not `dirs::config_dir()`, which would point macOS and
+/// Windows at `Application Support` / `%APPDATA%` where OpenCode never looks.
+fn get_opencode_config_path() -> PathBuf {
+    settings_path(load_settings().opencode_config_path.as_deref(), || {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .unwrap_or_else(|| get_user_home().join(".config"))
+            .join("opencode")
+            .join("opencode.json")
+    })
+}
+
+/// A provider's key: its `apiKey` option, or else its auth store entry.
+fn opencode_api_key<'a>(options: Option<&'a serde_json::Value>, auth: &'a serde_json::Value, provider: &str) -> &'a str {
+    options
+        .and_then(|o| o.get("apiKey"))
+        .and_then(|v| v.as_str())
+        .filter(|k| !k.is_empty())
+        .or_else(|| opencode_auth::key(auth, provider))
+        .unwrap_or("")
+}
+
+/// Sets or removes one header in an OpenCode provider's `options.headers`,
+/// dropping `headers` once it's empty.
+fn set_opencode_header(
+    options: &mut serde_json::Map<String, serde_json::Value>,
+    name: &str,
+    value: Option<String>,
+) -> Result<(), String> {
+    let headers = match (&value, options.get_mut("headers")) {
+        (None, None) => return Ok(()),
+        (_, Some(headers)) => headers,
+        (Some(_), None) => options.entry("headers".to_string()).or_insert_with(|| serde_json::json!({})),
+    };
+    let headers = headers.as_object_mut().ok_or("'options.headers' field is not an object")?;
+    match value {
+        Some(value) => {
+            headers.insert(name.to_string(), serde_json::Value::String(value));
+        }
+        None => {
+            headers.shift_remove(name);
+            if headers.is_empty() {
+                options.shift_remove("headers");
+            }
+        }
+    }
+    Ok(())
+}
+
+const THINKING_BUDGET_HEADER: &str = "x-thinking-budget";
+
+/// The version header the type's current OpenCode config added, so it can be
+/// removed when another config (or none) takes the provider block over.
+fn opencode_version_header(store: &ConfigStore, config_type: &ConfigType) -> Option<String> {
+    store
+        .configs
+        .iter()
+        .find(|c| &c.config_type == config_type && c.opencode_active)
+        .and_then(|c| c.options.api_version_header.as_ref())
+        .map(|(name, _)| name.clone())
+}
+
+/// Codex is configured through auth.json/config.toml rather than env vars,
+/// so it has nowhere to put an opt-out.
+fn telemetry_env_vars(config_type: &ConfigType) -> &'static [&'static str] {
+    match config_type {
+        ConfigType::Claude => &["DO_NOT_TRACK", "ANTHROPIC_TELEMETRY_DISABLED"],
+        ConfigType::Gemini => &["DO_NOT_TRACK"],
+        ConfigType::Codex | ConfigType::Other(_) => &[],
+    }
+}
+
+/// Codex would read `OPENAI_TIMEOUT`, but like telemetry it has no env file
+/// we manage to put it in.
+fn timeout_env_var(config_type: &ConfigType) -> Option<&'static str> {
+    match config_type {
+        ConfigType::Claude => Some("ANTHROPIC_TIMEOUT"),
+        _ => None,
+    }
+}
+
+/// Where `max_retries` goes. Codex gets it through `~/.codex/.env` (see
+/// `update_codex_env`) rather than the env block the others share; Gemini CLI
+/// has no such setting.
+fn max_retries_env_var(config_type: &ConfigType) -> Option<&'static str> {
+    match config_type {
+        ConfigType::Claude => Some("ANTHROPIC_MAX_RETRIES"),
+        ConfigType::Codex => Some(CODEX_MAX_RETRIES_VAR),
+        _ => None,
+    }
+}
+
+/// Where `provider_version` goes. Codex has no env file we manage; Azure-style
+/// versions belong in its config.toml instead.
+fn api_version_env_var(config_type: &ConfigType) -> Option<&'static str> {
+    match config_type {
+        ConfigType::Claude => Some("ANTHROPIC_API_VERSION"),
+        _ => None,
+    }
+}
+
+/// Where `api_version_header` goes as `name: value`. Gemini and Codex only
+/// get it through OpenCode.
+fn api_version_header_env_var(config_type: &ConfigType) -> Option<&'static str> {
+    match config_type {
+        ConfigType::Claude => Some("ANTHROPIC_API_VERSION_HEADER"),
+        _ => None,
+    }
+}
+
+/// Where the mTLS certificate and key paths go. Claude Code has its own
+/// variables; Gemini CLI and Codex read none (the generic `SSL_CERT_FILE`
+/// names a CA bundle, not a client certificate).
+fn client_cert_env_vars(config_type: &ConfigType) -> Option<(&'static str, &'static str)> {
+    match config_type {
+        ConfigType::Claude => Some(("CLAUDE_CODE_CLIENT_CERT", "CLAUDE_CODE_CLIENT_KEY")),
+        _ => None,
+    }
+}
+
+/// The optional env vars `config` sets, derived from its `options`, followed
+/// by its user-defined `env_overrides` (which therefore win on conflicts).
+fn config_extra_env(config: &Config) -> Vec<(String, String)> {
+    let mut vars = Vec::new();
+    let options = &config.options;
+    if config.config_type == ConfigType::Claude {
+        if let Some(streaming) = options.streaming_enabled {
+            vars.push(("ANTHROPIC_STREAMING".to_string(), if streaming { "1" } else { "0" }.to_string()));
+        }
+        if let Some(model) = &options.small_fast_model {
+            vars.push(("ANTHROPIC_SMALL_FAST_MODEL".to_string(), model.clone()));
+        }
+        if let Some(budget) = options.model_thinking_budget {
+            vars.push(("ANTHROPIC_THINKING_BUDGET".to_string(), budget.to_string()));
+        }
+    }
+    if config.config_type == ConfigType::Gemini && !options.scopes.is_empty() {
+        vars.push(("GOOGLE_OAUTH_SCOPES".to_string(), options.scopes.join(",")));
+    }
+    if options.telemetry_disabled == Some(true) {
+        for key in telemetry_env_vars(&config.config_type) {
+            vars.push((key.to_string(), "1".to_string()));
+        }
+    }
+    if let (Some(timeout), Some(key)) = (options.timeout_seconds, timeout_env_var(&config.config_type)) {
+        vars.push((key.to_string(), timeout.to_string()));
+    }
+    if let (Some(retries), Some(key)) = (options.max_retries, max_retries_env_var(&config.config_type)) {
+        vars.push((key.to_string(), retries.to_string()));
+    }
+    if let (Some(version), Some(key)) = (&options.provider_version, api_version_env_var(&config.config_type)) {
+        vars.push((key.to_string(), version.clone()));
+    }
+    if let (Some((name, value)), Some(key)) =
+        (&options.api_version_header, api_version_header_env_var(&config.config_type))
+    {
+        vars.push((key.to_string(), format!("{}: {}", name, value)));
+    }
+    if let Some((cert_var, key_var)) = client_cert_env_vars(&config.config_type) {
+        if let Some(cert) = &options.http_client_cert_path {
+            vars.push((cert_var.to_string(), cert.clone()));
+        }
+        if let Some(key) = &options.http_client_key_path {
+            vars.push((key_var.to_string(), key.clone()));
+        }
+    }
+    for var in &options.env_overrides {
+        vars.push((var.key.clone(), var.value.clone()));
+    }
+    vars
+}
+
+/// Writes the Claude env block. `stale` lists extra variables from a previous
+/// activation to drop first; `extra` is written last.
+fn update_claude_env(
+    plan: &mut Plan,
+    api_key: Option<&str>,
+    base_url: Option<&str>,
+    model: Option<&str>,
+    extra: &[(String, String)],
+    stale: &[String],
+) -> Result<(), String> {
     let path = get_claude_settings_path();
-    let mut json = read_json_object(&path)?;
+    let mut json = parse_json_object(&path, plan.read(&path)?.as_deref())?;
 
     let obj = json
         .as_object_mut()
@@ -131,7 +1176,7 @@ fn update_claude_env(api_key: Option<&str>, base_url: Option<&str>, model: Optio
             );
         }
         None => {
-            env_obj.remove("ANTHROPIC_AUTH_TOKEN");
+            env_obj.shift_remove("ANTHROPIC_AUTH_TOKEN");
         }
     }
 
@@ -143,7 +1188,7 @@ fn update_claude_env(api_key: Option<&str>, base_url: Option<&str>, model: Optio
             );
         }
         _ => {
-            env_obj.remove("ANTHROPIC_BASE_URL");
+            env_obj.shift_remove("ANTHROPIC_BASE_URL");
         }
     }
 
@@ -155,26 +1200,35 @@ fn update_claude_env(api_key: Option<&str>, base_url: Option<&str>, model: Optio
             );
         }
         _ => {
-            env_obj.remove("ANTHROPIC_MODEL");
+            env_obj.shift_remove("ANTHROPIC_MODEL");
         }
     }
 
     // Always strip ANTHROPIC_API_KEY: it conflicts with ANTHROPIC_AUTH_TOKEN
     // when activating, and must be gone for OAuth fallback when clearing.
-    env_obj.remove("ANTHROPIC_API_KEY");
+    env_obj.shift_remove("ANTHROPIC_API_KEY");
+
+    for key in stale {
+        env_obj.shift_remove(key);
+    }
+    for (key, value) in extra {
+        env_obj.insert(key.clone(), serde_json::Value::String(value.clone()));
+    }
 
-    write_json_pretty(&path, &json)
+    plan.write(&path, to_json_pretty(&json)?);
+    Ok(())
 }
 
-fn update_gemini_env(api_key: Option<&str>, base_url: Option<&str>, model: Option<&str>) -> Result<(), String> {
+fn update_gemini_env(
+    plan: &mut Plan,
+    api_key: Option<&str>,
+    base_url: Option<&str>,
+    model: Option<&str>,
+    extra: &[(String, String)],
+    stale: &[String],
+) -> Result<(), String> {
     let path = get_gemini_env_path();
-
-    let existing = if path.exists() {
-        fs::read_to_string(&path)
-            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
-    } else {
-        String::new()
-    };
+    let existing = plan.read(&path)?.unwrap_or_default();
 
     let target_keys = ["GEMINI_API_KEY", "GOOGLE_GEMINI_BASE_URL", "GEMINI_MODEL"];
     let mut lines: Vec<String> = existing
@@ -186,6 +1240,8 @@ fn update_gemini_env(api_key: Option<&str>, base_url: Option<&str>, model: Optio
             }
             let key = trimmed.split('=').next().unwrap_or("").trim();
             !target_keys.contains(&key)
+                && !stale.iter().any(|s| s == key)
+                && !extra.iter().any(|(k, _)| k == key)
         })
         .map(|s| s.to_string())
         .collect();
@@ -203,254 +1259,3296 @@ fn update_gemini_env(api_key: Option<&str>, base_url: Option<&str>, model: Optio
             lines.push(format!("GEMINI_MODEL={}", v));
         }
     }
+    for (key, value) in extra {
+        lines.push(format!("{}={}", key, value));
+    }
 
     let only_blank = lines.iter().all(|l| l.trim().is_empty());
     if only_blank {
-        if path.exists() {
-            fs::remove_file(&path)
-                .map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+        if plan.exists(&path) {
+            plan.remove(&path);
         }
         return Ok(());
     }
 
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent)
-            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
-    }
     let mut content = lines.join("\n");
     if !content.ends_with('\n') {
         content.push('\n');
     }
-    fs::write(&path, content)
-        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    plan.write(&path, content);
+    Ok(())
 }
 
-fn apply_claude_config(config: &Config) -> Result<(), String> {
-    update_claude_env(Some(&config.api_key), Some(&config.base_url), Some(&config.model))
+/// The built-in key/url/model values for env-based types, or all `None` when
+/// the config replaces them with its own overrides.
+fn builtin_env_values(config: &Config) -> [Option<&str>; 3] {
+    if config.options.replace_builtin_env {
+        [None, None, None]
+    } else {
+        [Some(&config.api_key), Some(&config.base_url), Some(&config.model)]
+    }
 }
 
-fn apply_gemini_config(config: &Config) -> Result<(), String> {
-    update_gemini_env(Some(&config.api_key), Some(&config.base_url), Some(&config.model))
+fn apply_claude_config(plan: &mut Plan, config: &Config, stale: &[String]) -> Result<(), String> {
+    let [api_key, base_url, model] = builtin_env_values(config);
+    update_claude_env(plan, api_key, base_url, model, &config_extra_env(config), stale)
 }
 
-fn apply_codex_config(config: &Config) -> Result<(), String> {
-    let home = get_user_home();
-    let codex_dir = home.join(".codex");
-
-    // Create .codex directory if it doesn't exist
-    fs::create_dir_all(&codex_dir).map_err(|e| format!("Failed to create .codex directory: {}", e))?;
+fn apply_gemini_config(plan: &mut Plan, config: &Config, stale: &[String]) -> Result<(), String> {
+    let [api_key, base_url, model] = builtin_env_values(config);
+    if config.options.gemini_settings_file {
+        update_gemini_env(plan, None, base_url, model, &config_extra_env(config), stale)?;
+        update_gemini_settings(plan, api_key)
+    } else {
+        update_gemini_env(plan, api_key, base_url, model, &config_extra_env(config), stale)?;
+        update_gemini_settings(plan, None)
+    }
+}
 
-    // Write auth.json - use OPENAI_API_KEY as the key name
-    let auth_path = codex_dir.join("auth.json");
-    let auth_content = serde_json::json!({
-        "OPENAI_API_KEY": config.api_key
-    });
-    fs::write(&auth_path, serde_json::to_string_pretty(&auth_content).unwrap())
-        .map_err(|e| format!("Failed to write auth.json: {}", e))?;
+const GEMINI_API_KEY_AUTH: &str = "gemini-api-key";
 
-    // Write config.toml
-    let config_path = codex_dir.join("config.toml");
-    let base_url = if config.base_url.is_empty() {
-        "https://api.openai.com/v1".to_string()
-    } else {
-        config.base_url.clone()
-    };
-    let model = if config.model.is_empty() {
-        "gpt-5.2-codex".to_string()
-    } else {
-        config.model.clone()
+/// Sets (or with `None` removes) `security.auth.apiKey` in the Gemini CLI's
+/// settings.json, along with `selectedType = "gemini-api-key"` so the CLI
+/// uses it. Everything else (`theme`, `mcpServers`, ...) is left alone, and
+/// the file isn't rewritten when nothing changes.
+fn update_gemini_settings(plan: &mut Plan, api_key: Option<&str>) -> Result<(), String> {
+    let path = get_gemini_settings_path();
+    let original = match plan.read(&path).and_then(|content| parse_json_object(&path, content.as_deref())) {
+        Ok(json) => json,
+        // Users on the default .env mode shouldn't be blocked by a settings
+        // file we can't parse (e.g. one with comments) and never wrote to.
+        Err(e) if api_key.is_none() => {
+            eprintln!("update_gemini_settings: {}", e);
+            return Ok(());
+        }
+        Err(e) => return Err(e),
     };
+    let mut json = original.clone();
+    let root = json
+        .as_object_mut()
+        .ok_or_else(|| format!("{} is not a JSON object", path.display()))?;
 
-    let config_content = format!(
-r#"model_provider = "fox"
-model = "{}"
-model_reasoning_effort = "medium"
-disable_response_storage = true
+    match api_key {
+        Some(key) => {
+            let security = root
+                .entry("security".to_string())
+                .or_insert_with(|| serde_json::json!({}))
+                .as_object_mut()
+                .ok_or("'security' in Gemini settings.json is not an object")?;
+            let auth = security
+                .entry("auth".to_string())
+                .or_insert_with(|| serde_json::json!({}))
+                .as_object_mut()
+                .ok_or("'security.auth' in Gemini settings.json is not an object")?;
+            auth.insert("apiKey".to_string(), serde_json::json!(key));
+            auth.insert("selectedType".to_string(), serde_json::json!(GEMINI_API_KEY_AUTH));
+        }
+        None => {
+            let Some(security) = root.get_mut("security").and_then(|s| s.as_object_mut()) else {
+                return Ok(());
+            };
+            if let Some(auth) = security.get_mut("auth").and_then(|a| a.as_object_mut()) {
+                if auth.shift_remove("apiKey").is_some()
+                    && auth.get("selectedType").and_then(|t| t.as_str()) == Some(GEMINI_API_KEY_AUTH)
+                {
+                    auth.shift_remove("selectedType");
+                }
+                if auth.is_empty() {
+                    security.shift_remove("auth");
+                }
+            }
+            if security.is_empty() {
+                root.shift_remove("security");
+            }
+        }
+    }
 
-[model_providers.fox]
-name = "fox"
-base_url = "{}"
-wire_api = "responses"
-requires_openai_auth = true
-"#, model, base_url);
+    if json != original {
+        plan.write(&path, to_json_pretty(&json)?);
+    }
+    Ok(())
+}
 
-    fs::write(&config_path, config_content)
-        .map_err(|e| format!("Failed to write config.toml: {}", e))?;
+const CODEX_TOML_MARKER: &str = "# Managed by Claude Config Manager";
 
-    Ok(())
+/// `Settings.codex_dir`, else `$CODEX_HOME` like Codex itself, else `~/.codex`.
+fn get_codex_dir() -> PathBuf {
+    settings_path(load_settings().codex_dir.as_deref(), || {
+        std::env::var_os("CODEX_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| get_user_home().join(".codex"))
+    })
 }
 
-fn apply_config(config: &Config) -> Result<(), String> {
-    match config.config_type {
-        ConfigType::Claude => apply_claude_config(config),
-        ConfigType::Gemini => apply_gemini_config(config),
-        ConfigType::Codex => apply_codex_config(config),
+fn codex_backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".ccm-backup");
+    path.with_file_name(name)
+}
+
+/// Whether `content` looks like a Codex file this app generated, as opposed to
+/// one the user (or `codex login`) wrote. Only user files are worth backing up.
+fn is_app_written_codex_file(path: &Path, content: &str, known_keys: &[String]) -> bool {
+    if path.extension().is_some_and(|e| e == "toml") {
+        return content.starts_with(CODEX_TOML_MARKER);
     }
+    let Ok(serde_json::Value::Object(obj)) = serde_json::from_str::<serde_json::Value>(content) else {
+        return false;
+    };
+    obj.len() == 1
+        && obj
+            .get("OPENAI_API_KEY")
+            .and_then(|v| v.as_str())
+            .is_some_and(|k| known_keys.iter().any(|known| known == k))
 }
 
-fn clear_claude_config() -> Result<(), String> {
-    let path = get_claude_settings_path();
-    if !path.exists() {
+/// Copies a user-owned Codex file aside before we overwrite it. An existing
+/// backup is never replaced: it holds the original file, and whatever is on
+/// disk now is most likely our own output from a previous activation.
+fn backup_codex_file(plan: &mut Plan, path: &Path, known_keys: &[String]) -> Result<(), String> {
+    let backup = codex_backup_path(path);
+    if plan.exists(&backup) {
         return Ok(());
     }
-    update_claude_env(None, None, None)
+    let Some(content) = plan.read(path)? else {
+        return Ok(());
+    };
+    if !is_app_written_codex_file(path, &content, known_keys) {
+        plan.write(&backup, content);
+    }
+    Ok(())
+}
+
+/// Moves each `*.ccm-backup` back over its original. Returns whether any
+/// backup was found.
+fn restore_codex_files() -> Result<bool, String> {
+    let codex_dir = get_codex_dir();
+    let mut plan = Plan::default();
+    let mut restored = false;
+    for file in ["auth.json", "config.toml"] {
+        restored |= restore_codex_file(&mut plan, &codex_dir.join(file))?;
+    }
+    plan.execute()?;
+    Ok(restored)
 }
 
-fn clear_gemini_config() -> Result<(), String> {
-    update_gemini_env(None, None, None)
+fn restore_codex_file(plan: &mut Plan, path: &Path) -> Result<bool, String> {
+    let backup = codex_backup_path(path);
+    let Some(original) = plan.read(&backup)? else {
+        return Ok(false);
+    };
+    let current = plan.read(path)?;
+    let merged = current
+        .filter(|_| path.file_name().is_some_and(|n| n == "config.toml"))
+        .and_then(|current| carry_over_codex_notice(&current, &original));
+    plan.write(path, merged.unwrap_or(original));
+    plan.remove(&backup);
+    Ok(true)
 }
 
-fn clear_codex_config() -> Result<(), String> {
-    let home = get_user_home();
-    let codex_dir = home.join(".codex");
+/// Codex records acknowledged notices under `[notice]` in config.toml. Ones
+/// acknowledged while our config was active would be lost by restoring the
+/// backup as-is, so the current `[notice]` table replaces the original's.
+/// `None` when there's nothing to carry over or either file doesn't parse.
+fn carry_over_codex_notice(current: &str, original: &str) -> Option<String> {
+    let current: toml_edit::DocumentMut = current.parse().ok()?;
+    let mut original: toml_edit::DocumentMut = original.parse().ok()?;
+    let notice = current.get("notice")?.clone();
+    original["notice"] = notice;
+    Some(original.to_string())
+}
 
-    // Remove auth.json
+fn apply_codex_config(plan: &mut Plan, config: &Config, store: &ConfigStore) -> Result<(), String> {
+    let codex_dir = get_codex_dir();
     let auth_path = codex_dir.join("auth.json");
-    if auth_path.exists() {
-        fs::remove_file(&auth_path).ok();
+    let config_path = codex_dir.join("config.toml");
+    let azure = codex_azure(config);
+    if azure.is_some() && config.base_url.is_empty() {
+        return Err("Azure configs need the resource endpoint as base URL".to_string());
     }
 
-    // Remove config.toml
-    let config_path = codex_dir.join("config.toml");
-    if config_path.exists() {
-        fs::remove_file(&config_path).ok();
+    // An unparseable existing config.toml fails the plan, so nothing
+    // (including auth.json) gets written.
+    let config_content = match plan.read(&config_path)? {
+        Some(existing) => merge_codex_toml(&existing, config)
+            .map_err(|e| format!("{} is not valid TOML, fix or remove it first: {}", config_path.display(), e))?,
+        None => generate_codex_toml(config),
+    };
+
+    let known_keys: Vec<String> = store
+        .configs
+        .iter()
+        .filter(|c| c.config_type == ConfigType::Codex)
+        .map(|c| c.api_key.clone())
+        .collect();
+    backup_codex_file(plan, &auth_path, &known_keys)?;
+    backup_codex_file(plan, &config_path, &known_keys)?;
+
+    // Azure reads the key from the env var named by `env_key`; anything else
+    // from auth.json, using OPENAI_API_KEY as the key name.
+    let mut env = Vec::new();
+    if let Some(retries) = config.options.max_retries {
+        env.push((CODEX_MAX_RETRIES_VAR, retries.to_string()));
+    }
+    let stale = store.applied_env.get(&ConfigType::Codex).cloned().unwrap_or_default();
+    if azure.is_some() {
+        env.push((CODEX_AZURE_KEY_VAR, config.api_key.clone()));
+        update_codex_env(plan, &env, &stale)?;
+        // A key from an earlier non-Azure activation would stay live for
+        // any other provider, so auth.json goes back to the user's own.
+        if !restore_codex_file(plan, &auth_path)? && plan.exists(&auth_path) {
+            plan.remove(&auth_path);
+        }
+    } else {
+        update_codex_env(plan, &env, &stale)?;
+        let auth_content = serde_json::json!({
+            "OPENAI_API_KEY": config.api_key
+        });
+        plan.write(&auth_path, to_json_pretty(&auth_content)?);
     }
 
+    plan.write(&config_path, config_content);
     Ok(())
 }
 
-fn clear_config(config_type: &ConfigType) -> Result<(), String> {
-    match config_type {
-        ConfigType::Claude => clear_claude_config(),
-        ConfigType::Gemini => clear_gemini_config(),
-        ConfigType::Codex => clear_codex_config(),
+const CODEX_DEFAULT_PROVIDER: &str = "fox";
+const CODEX_AZURE_PROVIDER: &str = "azure";
+const CODEX_AZURE_KEY_VAR: &str = "AZURE_OPENAI_API_KEY";
+const CODEX_MAX_RETRIES_VAR: &str = "OPENAI_MAX_RETRIES";
+/// The `~/.codex/.env` lines `update_codex_env` may write.
+const CODEX_ENV_VARS: [&str; 2] = [CODEX_AZURE_KEY_VAR, CODEX_MAX_RETRIES_VAR];
+const CODEX_DEFAULT_MODEL: &str = "gpt-5.2-codex";
+const CODEX_DEFAULT_REASONING_EFFORT: &str = "medium";
+const CODEX_DEFAULT_WIRE_API: &str = "responses";
+
+fn codex_base_url(config: &Config) -> String {
+    if config.base_url.is_empty() {
+        "https://api.openai.com/v1".to_string()
+    } else {
+        config.base_url.clone()
     }
 }
 
-#[tauri::command]
-fn get_configs() -> Vec<Config> {
-    load_store().configs
+fn codex_azure(config: &Config) -> Option<&AzureOptions> {
+    config.options.codex.as_ref()?.azure.as_ref()
 }
 
-#[tauri::command]
-fn add_config(name: String, config_type: String, api_key: String, base_url: String, model: String) -> Result<Config, String> {
-    let mut store = load_store();
-
-    let config_type_enum = match config_type.as_str() {
-        "claude" => ConfigType::Claude,
-        "gemini" => ConfigType::Gemini,
-        "codex" => ConfigType::Codex,
-        _ => return Err("Invalid config type".to_string()),
+fn codex_provider_name(config: &Config) -> &str {
+    let default = if codex_azure(config).is_some() {
+        CODEX_AZURE_PROVIDER
+    } else {
+        CODEX_DEFAULT_PROVIDER
     };
+    config
+        .options
+        .codex
+        .as_ref()
+        .and_then(|o| o.provider_name.as_deref())
+        .filter(|n| !n.is_empty())
+        .unwrap_or(default)
+}
 
-    let config = Config {
-        id: Uuid::new_v4().to_string(),
-        name,
-        config_type: config_type_enum,
-        api_key,
-        base_url,
-        model,
-        is_active: false,
-    };
-    store.configs.push(config.clone());
-    save_store(&store)?;
-    Ok(config)
+/// The `model` to write, if any: the deployment in Azure mode, since Azure
+/// routes by deployment name, otherwise the config's model.
+fn codex_model(config: &Config) -> Option<&str> {
+    match codex_azure(config) {
+        Some(azure) => Some(azure.deployment.as_str()),
+        None => Some(config.model.as_str()).filter(|m| !m.is_empty()),
+    }
 }
 
-#[tauri::command]
-fn update_config(
-    id: String,
-    name: String,
-    api_key: String,
-    base_url: String,
-    model: String,
-) -> Result<(), String> {
-    let mut store = load_store();
-    if let Some(config) = store.configs.iter_mut().find(|c| c.id == id) {
-        config.name = name;
-        config.api_key = api_key;
-        config.base_url = base_url;
-        config.model = model;
+/// Writes `vars` into `~/.codex/.env`, which Codex loads at startup. Drops
+/// the `stale` variables a previous activation wrote (`store.applied_env`),
+/// leaving any other lines alone, so e.g. an `OPENAI_MAX_RETRIES` or
+/// `AZURE_OPENAI_API_KEY` the user set by hand survives. The file is removed
+/// once nothing else is left in it.
+fn update_codex_env(plan: &mut Plan, vars: &[(&str, String)], stale: &[String]) -> Result<(), String> {
+    let path = get_codex_dir().join(".env");
+    let existing = match plan.read(&path)? {
+        Some(content) => content,
+        None if vars.is_empty() => return Ok(()),
+        None => String::new(),
+    };
+    let mut lines: Vec<String> = existing
+        .lines()
+        .filter(|line| {
+            let key = line.trim_start().trim_start_matches("export ").split('=').next().unwrap_or("").trim();
+            !stale.iter().any(|s| s == key) && !vars.iter().any(|(k, _)| *k == key)
+        })
+        .map(str::to_string)
+        .collect();
+    for (key, value) in vars {
+        lines.push(format!("{}={}", key, value));
+    }
 
-        // If this config is active, re-apply it
-        if config.is_active {
-            let config_clone = config.clone();
-            save_store(&store)?;
-            apply_config(&config_clone)?;
-            return Ok(());
-        }
+    if lines.iter().all(|l| l.trim().is_empty()) {
+        plan.remove(&path);
+        return Ok(());
     }
-    save_store(&store)
+    let mut content = lines.join("\n");
+    content.push('\n');
+    plan.write(&path, content);
+    Ok(())
 }
 
-#[tauri::command]
-fn delete_config(id: String) -> Result<(), String> {
-    let mut store = load_store();
-    let config_to_delete = store.configs.iter().find(|c| c.id == id).cloned();
-
-    if let Some(config) = config_to_delete {
-        if config.is_active {
-            clear_config(&config.config_type)?;
+fn validate_codex_options(options: &CodexOptions) -> Result<(), String> {
+    if let Some(name) = &options.provider_name {
+        // The name becomes a bare TOML key in `[model_providers.<name>]`.
+        if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(format!(
+                "Invalid Codex provider name '{}': use letters, digits, '-' or '_'",
+                name
+            ));
+        }
+    }
+    if let Some(azure) = &options.azure {
+        if azure.deployment.trim().is_empty() {
+            return Err("Azure configs need a deployment name".to_string());
+        }
+        if azure.api_version.trim().is_empty() || azure.api_version.contains(char::is_whitespace) {
+            return Err(format!("Invalid Azure API version '{}'", azure.api_version));
         }
     }
+    Ok(())
+}
 
-    store.configs.retain(|c| c.id != id);
-    save_store(&store)
+/// Minimal config.toml used when the user has none yet. The marker line tells
+/// `backup_codex_file` that this file is ours and needn't be preserved.
+fn generate_codex_toml(config: &Config) -> String {
+    let model = codex_model(config).unwrap_or(CODEX_DEFAULT_MODEL);
+    let options = config.options.codex.clone().unwrap_or_default();
+    let provider = codex_provider_name(config);
+    let auth = match &options.azure {
+        Some(azure) => format!(
+            "env_key = \"{}\"\nquery_params = {{ api-version = \"{}\" }}",
+            CODEX_AZURE_KEY_VAR, azure.api_version
+        ),
+        None => "requires_openai_auth = true".to_string(),
+    };
+
+    format!(
+r#"{}
+model_provider = "{}"
+model = "{}"
+model_reasoning_effort = "{}"
+disable_response_storage = {}
+
+[model_providers.{}]
+name = "{}"
+base_url = "{}"
+wire_api = "{}"
+{}
+"#,
+        CODEX_TOML_MARKER,
+        provider,
+        model,
+        options.reasoning_effort.as_deref().unwrap_or(CODEX_DEFAULT_REASONING_EFFORT),
+        options.disable_response_storage.unwrap_or(true),
+        provider,
+        provider,
+        codex_base_url(config),
+        options.wire_api.as_deref().unwrap_or(CODEX_DEFAULT_WIRE_API),
+        auth,
+    )
 }
 
-#[tauri::command]
-fn activate_config(id: String) -> Result<(), String> {
-    let mut store = load_store();
+/// Updates only the keys we own in an existing config.toml: `model_provider`,
+/// the provider table's `base_url` and auth settings, and `model` / reasoning
+/// effort / wire API / response storage when the config sets them.
+/// Everything else (`mcp_servers`, `profiles`, `[notice]`, formatting,
+/// comments) is kept, except a model migration that would replace the
+/// config's model.
+fn merge_codex_toml(existing: &str, config: &Config) -> Result<String, String> {
+    use toml_edit::{value, DocumentMut, InlineTable, Item, Table};
 
-    // Find the config to activate
-    let config_to_activate = store
-        .configs
-        .iter()
-        .find(|c| c.id == id)
-        .cloned()
-        .ok_or("Config not found")?;
+    let mut doc: DocumentMut = existing.parse().map_err(|e: toml_edit::TomlError| e.to_string())?;
+    let options = config.options.codex.clone().unwrap_or_default();
+    let provider_name = codex_provider_name(config);
 
-    // Deactivate only configs of the same type
-    for config in &mut store.configs {
-        if config.config_type == config_to_activate.config_type {
-            config.is_active = false;
+    doc["model_provider"] = value(provider_name);
+    if let Some(model) = codex_model(config) {
+        doc["model"] = value(model);
+        // `[notice.model_migrations]` maps a model to the one Codex migrated
+        // the user to; an entry for ours would switch it to something else.
+        if let Some(migrations) = doc
+            .get_mut("notice")
+            .and_then(|n| n.get_mut("model_migrations"))
+            .and_then(|m| m.as_table_like_mut())
+        {
+            if migrations.get(model).and_then(|v| v.as_str()).is_some_and(|to| to != model) {
+                migrations.remove(model);
+            }
         }
     }
+    if let Some(effort) = &options.reasoning_effort {
+        doc["model_reasoning_effort"] = value(effort.as_str());
+    }
+    if let Some(disable) = options.disable_response_storage {
+        doc["disable_response_storage"] = value(disable);
+    }
 
-    // Activate the selected config
-    if let Some(config) = store.configs.iter_mut().find(|c| c.id == id) {
-        config.is_active = true;
+    let providers = doc
+        .entry("model_providers")
+        .or_insert_with(|| {
+            let mut t = Table::new();
+            t.set_implicit(true);
+            Item::Table(t)
+        })
+        .as_table_like_mut()
+        .ok_or("'model_providers' is not a table")?;
+    let provider = providers
+        .entry(provider_name)
+        .or_insert_with(|| {
+            let mut t = Table::new();
+            t["name"] = value(provider_name);
+            t["wire_api"] = value(CODEX_DEFAULT_WIRE_API);
+            t["requires_openai_auth"] = value(true);
+            Item::Table(t)
+        })
+        .as_table_like_mut()
+        .ok_or_else(|| format!("'model_providers.{}' is not a table", provider_name))?;
+    provider.insert("base_url", value(codex_base_url(config)));
+    if let Some(wire_api) = &options.wire_api {
+        provider.insert("wire_api", value(wire_api.as_str()));
+    }
+    match &options.azure {
+        Some(azure) => {
+            let mut query = InlineTable::new();
+            query.insert("api-version", azure.api_version.as_str().into());
+            provider.insert("env_key", value(CODEX_AZURE_KEY_VAR));
+            provider.insert("query_params", value(query));
+            provider.remove("requires_openai_auth");
+        }
+        // Undo an earlier Azure activation that used the same provider name.
+        None if provider.get("env_key").and_then(|v| v.as_str()) == Some(CODEX_AZURE_KEY_VAR) => {
+            provider.remove("env_key");
+            provider.remove("query_params");
+            provider.insert("requires_openai_auth", value(true));
+        }
+        None => {}
     }
 
-    save_store(&store)?;
-    apply_config(&config_to_activate)?;
+    Ok(doc.to_string())
+}
 
-    Ok(())
+/// Every file `apply_config` may create, change or remove for a type,
+/// including the Codex backups it makes.
+fn tool_files(config_type: &ConfigType) -> Vec<PathBuf> {
+    match config_type {
+        ConfigType::Claude => vec![get_claude_settings_path()],
+        ConfigType::Gemini => vec![get_gemini_env_path(), get_gemini_settings_path()],
+        ConfigType::Codex => {
+            let codex_dir = get_codex_dir();
+            ["auth.json", "config.toml"]
+                .into_iter()
+                .flat_map(|file| {
+                    let path = codex_dir.join(file);
+                    [codex_backup_path(&path), path]
+                })
+                .chain([codex_dir.join(".env")])
+                .collect()
+        }
+        ConfigType::Other(_) => Vec::new(),
+    }
 }
 
-#[tauri::command]
-fn deactivate_config(id: String) -> Result<(), String> {
-    let mut store = load_store();
+/// The contents of a set of files (or their absence) at one point in time,
+/// used to undo a partially failed apply.
+struct FileSnapshot {
+    files: Vec<(PathBuf, Option<Vec<u8>>)>,
+}
 
-    if let Some(config) = store.configs.iter_mut().find(|c| c.id == id) {
-        if config.is_active {
-            config.is_active = false;
-            let config_type = config.config_type.clone();
-            save_store(&store)?;
-            clear_config(&config_type)?;
+impl FileSnapshot {
+    fn capture(paths: &[PathBuf]) -> Self {
+        FileSnapshot {
+            files: paths.iter().map(|p| (p.clone(), fs::read(p).ok())).collect(),
         }
     }
 
-    Ok(())
+    /// Best effort: puts every file back, logging the ones that can't be.
+    fn restore(&self) {
+        for (path, content) in &self.files {
+            let result = match content {
+                Some(content) => fs::write(path, content),
+                None if path.exists() => fs::remove_file(path),
+                None => Ok(()),
+            };
+            if result.is_ok() {
+                written::record(path, content.as_deref());
+            }
+            if let Err(e) = result {
+                eprintln!("rollback: failed to restore {}: {}", path.display(), e);
+            }
+        }
+    }
 }
 
-fn get_opencode_template() -> &'static str {
-    r#"{
-  "$schema": "https://opencode.ai/config.json",
-  "tui": {
-    "scroll_speed": 3,
-    "scroll_acceleration": {
-      "enabled": true
+/// Errors if the config's key is still a keyring reference, i.e. the
+/// keyring couldn't be read when the store was loaded.
+fn require_api_key(config: &Config) -> Result<(), String> {
+    if secrets::is_reference(&config.api_key) {
+        return Err(format!(
+            "The key for '{}' is in the system keyring, which is unavailable",
+            config.name
+        ));
+    }
+    Ok(())
+}
+
+/// Every file write `apply_config` would make for `config`, without making
+/// any of them.
+fn plan_apply(config: &Config, store: &ConfigStore) -> Result<Plan, String> {
+    require_api_key(config)?;
+    let stale = store.applied_env.get(&config.config_type).cloned().unwrap_or_default();
+    let mut plan = Plan::default();
+    match config.config_type {
+        ConfigType::Claude => apply_claude_config(&mut plan, config, &stale)?,
+        ConfigType::Gemini => apply_gemini_config(&mut plan, config, &stale)?,
+        ConfigType::Codex => apply_codex_config(&mut plan, config, store)?,
+        ConfigType::Other(_) => {}
+    }
+    Ok(plan)
+}
+
+/// Writes `config` into its target tool and records which override variables
+/// it set in `store.applied_env`. The caller is responsible for saving `store`.
+/// Unless `force`, refuses to overwrite a file edited by hand since we last
+/// wrote it (see `Plan::check_external_edits`).
+fn apply_config(config: &Config, store: &mut ConfigStore, force: bool) -> Result<(), String> {
+    let plan = plan_apply(config, store)?;
+    if let ConfigType::Other(ref name) = config.config_type {
+        eprintln!("apply_config: '{}' is a passthrough type, nothing to write", name);
+        return Ok(());
+    }
+    if !force {
+        plan.check_external_edits(&stored_keys(store))?;
+    }
+    plan.execute()?;
+    let mut written: Vec<String> = Vec::new();
+    if codex_azure(config).is_some() {
+        written.push(CODEX_AZURE_KEY_VAR.to_string());
+    }
+    for (key, _) in config_extra_env(config) {
+        if !written.contains(&key) {
+            written.push(key);
+        }
+    }
+    if written.is_empty() {
+        store.applied_env.remove(&config.config_type);
+    } else {
+        store.applied_env.insert(config.config_type.clone(), written);
+    }
+    Ok(())
+}
+
+/// The `clear_*` helpers return what they actually removed: variable names
+/// for Claude and Gemini, file paths for Codex.
+fn clear_claude_config(plan: &mut Plan, stale: &[String]) -> Result<Vec<String>, String> {
+    let path = get_claude_settings_path();
+    if !plan.exists(&path) {
+        return Ok(Vec::new());
+    }
+    let before = plan.read(&path)?;
+    update_claude_env(plan, None, None, None, &[], stale)?;
+    Ok(plan::removed_vars(&path, before.as_deref(), plan.read(&path)?.as_deref()))
+}
+
+fn clear_gemini_config(plan: &mut Plan, stale: &[String]) -> Result<Vec<String>, String> {
+    let (env_path, settings_path) = (get_gemini_env_path(), get_gemini_settings_path());
+    let (env_before, settings_before) = (plan.read(&env_path)?, plan.read(&settings_path)?);
+    update_gemini_env(plan, None, None, None, &[], stale)?;
+    update_gemini_settings(plan, None)?;
+    let mut cleared = plan::removed_vars(&env_path, env_before.as_deref(), plan.read(&env_path)?.as_deref());
+    if plan.read(&settings_path)? != settings_before {
+        cleared.push("security.auth.apiKey".to_string());
+    }
+    Ok(cleared)
+}
+
+fn clear_codex_config(plan: &mut Plan, stale: &[String]) -> Result<Vec<String>, String> {
+    clear_codex_files(plan, false, false, stale)
+}
+
+fn clear_codex_files(
+    plan: &mut Plan,
+    keep_auth: bool,
+    keep_toml: bool,
+    stale: &[String],
+) -> Result<Vec<String>, String> {
+    let codex_dir = get_codex_dir();
+    let files = [codex_dir.join("auth.json"), codex_dir.join("config.toml"), codex_dir.join(".env")];
+    let before = files.iter().map(|path| plan.read(path)).collect::<Result<Vec<_>, _>>()?;
+
+    // Files we backed up go back to the user's original; files that didn't
+    // exist before we first wrote them are simply removed.
+    for (file, keep) in [("auth.json", keep_auth), ("config.toml", keep_toml)] {
+        if keep {
+            continue;
+        }
+        let path = codex_dir.join(file);
+        if !restore_codex_file(plan, &path)? && plan.exists(&path) {
+            plan.remove(&path);
+        }
+    }
+    // An Azure config's key (and any retry count) lives in .env rather
+    // than auth.json.
+    if !keep_auth {
+        update_codex_env(plan, &[], stale)?;
+    }
+
+    let mut cleared = Vec::new();
+    for (path, before) in files.iter().zip(before) {
+        if before.is_some() && plan.read(path)? != before {
+            cleared.push(path.display().to_string());
+        }
+    }
+    Ok(cleared)
+}
+
+/// Logs the keys we're about to clear that are already gone, a sign that the
+/// store's active state and the tool's files drifted apart.
+fn log_unset_keys(config_type: &ConfigType, stale: &[String]) {
+    let (Some([(_, key_var), ..]), Ok(values)) = (managed_keys(config_type), read_applied_values(config_type)) else {
+        return;
+    };
+    for key in std::iter::once(key_var).chain(stale.iter().map(String::as_str)) {
+        if !values.contains_key(key) {
+            tracing::debug!("Attempted to clear '{key}' but it was not set");
+        }
+    }
+}
+
+/// Removes our keys from the target tool, including any override variables
+/// recorded in `store.applied_env`, and returns what was removed (see
+/// `clear_claude_config`). The caller is responsible for saving `store`.
+fn clear_config(config_type: &ConfigType, store: &mut ConfigStore) -> Result<Vec<String>, String> {
+    let stale = store.applied_env.get(config_type).cloned().unwrap_or_default();
+    log_unset_keys(config_type, &stale);
+    let (plan, cleared) = plan_clear(config_type, store)?;
+    plan.execute()?;
+    store.applied_env.remove(config_type);
+    Ok(cleared)
+}
+
+/// Every file change `clear_config` would make, without making any of them,
+/// and what it would remove.
+fn plan_clear(config_type: &ConfigType, store: &ConfigStore) -> Result<(Plan, Vec<String>), String> {
+    let stale = store.applied_env.get(config_type).cloned().unwrap_or_default();
+    let mut plan = Plan::default();
+    let cleared = match config_type {
+        ConfigType::Claude => clear_claude_config(&mut plan, &stale)?,
+        ConfigType::Gemini => clear_gemini_config(&mut plan, &stale)?,
+        ConfigType::Codex => clear_codex_config(&mut plan, &stale)?,
+        ConfigType::Other(_) => Vec::new(),
+    };
+    Ok((plan, cleared))
+}
+
+/// Parses `KEY=value` lines, skipping blanks and comments. Tolerates an
+/// `export ` prefix and surrounding quotes so hand-edited files still read back.
+fn parse_dotenv(content: &str) -> Vec<(String, String)> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                return None;
+            }
+            let trimmed = trimmed.strip_prefix("export ").unwrap_or(trimmed);
+            let (key, value) = trimmed.split_once('=')?;
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+                .unwrap_or(value);
+            Some((key.trim().to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// What `apply_config` should leave behind for `config`, as key → value.
+/// `None` means the key must be absent; keys we don't control (e.g. a Codex
+/// model preserved from the user's own config.toml) are simply not listed.
+fn expected_applied_values(config: &Config) -> Vec<(String, Option<String>)> {
+    let non_empty = |s: &str| (!s.is_empty()).then(|| s.to_string());
+    let Some([(_, key_var), (_, url_var), (_, model_var)]) = managed_keys(&config.config_type) else {
+        return Vec::new();
+    };
+    match config.config_type {
+        ConfigType::Claude | ConfigType::Gemini => {
+            let [api_key, base_url, model] = builtin_env_values(config);
+            let mut values = vec![
+                (key_var.to_string(), api_key.map(str::to_string)),
+                (url_var.to_string(), base_url.and_then(non_empty)),
+                (model_var.to_string(), model.and_then(non_empty)),
+            ];
+            // Overrides come last in `config_extra_env`, so later entries win.
+            for (key, value) in config_extra_env(config) {
+                values.retain(|(k, _)| k != &key);
+                values.push((key, Some(value)));
+            }
+            values
+        }
+        ConfigType::Codex => {
+            let provider = codex_provider_name(config);
+            let key_var = if codex_azure(config).is_some() {
+                CODEX_AZURE_KEY_VAR
+            } else {
+                key_var
+            };
+            let mut values = vec![
+                (key_var.to_string(), Some(config.api_key.clone())),
+                ("model_provider".to_string(), Some(provider.to_string())),
+                (format!("model_providers.{}.base_url", provider), Some(codex_base_url(config))),
+            ];
+            if let Some(model) = codex_model(config) {
+                values.push((model_var.to_string(), Some(model.to_string())));
+            }
+            if let Some(retries) = config.options.max_retries {
+                values.push((CODEX_MAX_RETRIES_VAR.to_string(), Some(retries.to_string())));
+            }
+            values
+        }
+        ConfigType::Other(_) => Vec::new(),
+    }
+}
+
+/// Reads back the live values of every key `expected_applied_values` may
+/// mention for this type. Missing files simply yield no entries.
+fn read_applied_values(config_type: &ConfigType) -> Result<HashMap<String, String>, String> {
+    let mut values = HashMap::new();
+    match config_type {
+        ConfigType::Claude => {
+            let json = read_json_object(&get_claude_settings_path())?;
+            if let Some(env) = json.get("env").and_then(|e| e.as_object()) {
+                for (k, v) in env {
+                    if let Some(s) = v.as_str() {
+                        values.insert(k.clone(), s.to_string());
+                    }
+                }
+            }
+        }
+        ConfigType::Gemini => {
+            let path = get_gemini_env_path();
+            if path.exists() {
+                let content = fs::read_to_string(&path)
+                    .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+                values.extend(parse_dotenv(&content));
+            }
+            // Written there instead of .env with `gemini_settings_file`.
+            let settings = read_json_object(&get_gemini_settings_path()).unwrap_or_default();
+            if let Some(key) = settings.pointer("/security/auth/apiKey").and_then(|v| v.as_str()) {
+                values.entry("GEMINI_API_KEY".to_string()).or_insert_with(|| key.to_string());
+            }
+        }
+        ConfigType::Codex => {
+            let codex_dir = get_codex_dir();
+            let auth = read_json_object(&codex_dir.join("auth.json"))?;
+            if let Some(key) = auth.get("OPENAI_API_KEY").and_then(|v| v.as_str()) {
+                values.insert("OPENAI_API_KEY".to_string(), key.to_string());
+            }
+            if let Ok(content) = fs::read_to_string(codex_dir.join(".env")) {
+                values.extend(
+                    parse_dotenv(&content)
+                        .into_iter()
+                        .filter(|(k, _)| CODEX_ENV_VARS.contains(&k.as_str())),
+                );
+            }
+            let toml_path = codex_dir.join("config.toml");
+            if toml_path.exists() {
+                let content = fs::read_to_string(&toml_path)
+                    .map_err(|e| format!("Failed to read {}: {}", toml_path.display(), e))?;
+                let doc: toml_edit::DocumentMut = content
+                    .parse()
+                    .map_err(|e| format!("Failed to parse {}: {}", toml_path.display(), e))?;
+                for key in ["model_provider", "model"] {
+                    if let Some(v) = doc.get(key).and_then(|v| v.as_str()) {
+                        values.insert(key.to_string(), v.to_string());
+                    }
+                }
+                if let Some(providers) = doc.get("model_providers").and_then(|p| p.as_table_like()) {
+                    for (name, provider) in providers.iter() {
+                        if let Some(url) = provider.get("base_url").and_then(|v| v.as_str()) {
+                            values.insert(format!("model_providers.{}.base_url", name), url.to_string());
+                        }
+                    }
+                }
+            }
+        }
+        ConfigType::Other(_) => {}
+    }
+    Ok(values)
+}
+
+/// Keys whose live value doesn't match what `config` should have applied.
+fn find_mismatched_keys(config: &Config) -> Result<Vec<String>, String> {
+    let actual = read_applied_values(&config.config_type)?;
+    Ok(expected_applied_values(config)
+        .into_iter()
+        .filter(|(key, expected)| actual.get(key) != expected.as_ref())
+        .map(|(key, _)| key)
+        .collect())
+}
+
+/// Where each field of a config ends up in the target tool. Claude and Gemini
+/// use env vars; Codex has no env vars of its own, so its entries name the
+/// auth.json / config.toml keys instead.
+fn managed_keys(config_type: &ConfigType) -> Option<[(&'static str, &'static str); 3]> {
+    Some(match config_type {
+        ConfigType::Claude => [
+            ("primary_key", "ANTHROPIC_AUTH_TOKEN"),
+            ("base_url_key", "ANTHROPIC_BASE_URL"),
+            ("model_key", "ANTHROPIC_MODEL"),
+        ],
+        ConfigType::Gemini => [
+            ("primary_key", "GEMINI_API_KEY"),
+            ("base_url_key", "GOOGLE_GEMINI_BASE_URL"),
+            ("model_key", "GEMINI_MODEL"),
+        ],
+        ConfigType::Codex => [
+            ("primary_key", "OPENAI_API_KEY"),
+            ("base_url_key", "model_providers.fox.base_url"),
+            ("model_key", "model"),
+        ],
+        ConfigType::Other(_) => return None,
+    })
+}
+
+/// First 7 + last 4 chars, or `****` for keys too short to show any of.
+fn mask_api_key(key: &str) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    if chars.len() < 10 {
+        return "****".to_string();
+    }
+    let head: String = chars[..7].iter().collect();
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}...{}", head, tail)
+}
+
+/// What `get_configs` sends to the webview: the config with `api_key`
+/// replaced by its mask. The real key is only handed out by `reveal_api_key`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigView {
+    #[serde(flatten)]
+    pub config: Config,
+    pub key_set: bool,
+    pub active_targets: Vec<ActivationTarget>,
+    /// `options.expires_at` has passed.
+    pub expired: bool,
+}
+
+fn expires_at_millis(config: &Config) -> Option<u64> {
+    config
+        .options
+        .expires_at
+        .as_deref()
+        .and_then(|value| expiry::parse_expiry(value).ok())
+}
+
+impl From<Config> for ConfigView {
+    fn from(mut config: Config) -> Self {
+        let key_set = !config.api_key.is_empty();
+        config.api_key = if key_set { mask_api_key(&config.api_key) } else { String::new() };
+        ConfigView {
+            expired: expires_at_millis(&config).is_some_and(|at| at <= now_millis()),
+            active_targets: active_targets(&config),
+            config,
+            key_set,
+        }
+    }
+}
+
+/// Opens a file with its default app, or a folder in the file manager.
+fn open_path(path: &Path) -> Result<(), String> {
+    let opener = if cfg!(windows) {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    std::process::Command::new(opener)
+        .arg(path)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to run {}: {}", opener, e))
+}
+
+/// Configs whose key expires within `within_days` (or already has), soonest
+/// first.
+fn expiring_configs(within_days: u32) -> Vec<ConfigView> {
+    let deadline = now_millis() + u64::from(within_days) * 24 * 60 * 60 * 1000;
+    let mut configs: Vec<(u64, Config)> = load_store()
+        .configs
+        .into_iter()
+        .filter_map(|c| expires_at_millis(&c).filter(|at| *at <= deadline).map(|at| (at, c)))
+        .collect();
+    configs.sort_by_key(|(at, _)| *at);
+    configs.into_iter().map(|(_, c)| ConfigView::from(c)).collect()
+}
+
+#[tauri::command]
+fn list_backups() -> Vec<backups::BackupInfo> {
+    backups::list()
+}
+
+#[tauri::command]
+fn restore_backup(filename: String) -> Result<(), String> {
+    backups::restore(&filename, load_store().backup_retention)
+}
+
+#[tauri::command]
+fn set_backup_retention(count: usize) -> Result<(), String> {
+    let (_lock, mut store) = load_store_locked();
+    store.backup_retention = count;
+    save_store(&store)
+}
+
+#[tauri::command]
+fn get_expiring_configs(within_days: u32) -> Vec<ConfigView> {
+    expiring_configs(within_days)
+}
+
+/// Configs last activated (or, never activated, created) more than
+/// `max_age_days` ago, oldest first, as candidates for a new key. Configs
+/// with neither timestamp (saved before they were tracked) are left out.
+#[tauri::command]
+fn get_configs_needing_rotation(max_age_days: u64) -> Vec<ConfigView> {
+    let cutoff = now_millis().saturating_sub(max_age_days.saturating_mul(24 * 60 * 60 * 1000));
+    let mut configs: Vec<(u64, Config)> = load_store()
+        .configs
+        .into_iter()
+        .filter_map(|c| c.last_activated_at.or(c.created_at).filter(|at| *at < cutoff).map(|at| (at, c)))
+        .collect();
+    configs.sort_by_key(|(at, _)| *at);
+    configs.into_iter().map(|(_, c)| ConfigView::from(c)).collect()
+}
+
+/// Configs that were never activated, in display order.
+#[tauri::command]
+fn get_never_used_configs() -> Vec<ConfigView> {
+    sorted_configs(load_store().configs)
+        .into_iter()
+        .filter(|c| c.last_activated_at.is_none() && c.activation_count == 0)
+        .map(ConfigView::from)
+        .collect()
+}
+
+#[tauri::command]
+fn get_expiry_warning_days() -> u32 {
+    load_store().expiry_warning_days
+}
+
+#[tauri::command]
+fn set_expiry_warning_days(days: u32) -> Result<(), String> {
+    let (_lock, mut store) = load_store_locked();
+    store.expiry_warning_days = days;
+    save_store(&store)
+}
+
+/// Shows the folder holding configs.json in the OS file manager.
+#[tauri::command]
+fn open_config_directory() -> Result<(), String> {
+    let path = get_config_path();
+    let dir = path.parent().ok_or("Config path has no parent directory")?;
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    open_path(dir)
+}
+
+/// Opens the config's `notes_file_path` in the default editor.
+#[tauri::command]
+fn open_config_notes(id: String) -> Result<(), String> {
+    let config = load_store()
+        .configs
+        .into_iter()
+        .find(|c| c.id == id)
+        .ok_or("Config not found")?;
+    let path = config.options.notes_file_path.ok_or("Config has no notes file")?;
+    let path = Path::new(&path);
+    if !path.is_file() {
+        return Err(format!("Notes file {} does not exist", path.display()));
+    }
+    open_path(path)
+}
+
+/// Errors when configs.json couldn't be loaded as-is, e.g. it was written by
+/// a newer version of the app; every save fails with the same message then.
+#[tauri::command]
+fn check_store() -> Result<(), String> {
+    match load_store().load_error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// The order configs are listed in everywhere: by type, then `sort_index`,
+/// then name.
+fn sorted_configs(mut configs: Vec<Config>) -> Vec<Config> {
+    configs.sort_by(|a, b| {
+        (&a.config_type, a.sort_index, &a.name).cmp(&(&b.config_type, b.sort_index, &b.name))
+    });
+    configs
+}
+
+/// Puts a new config at the end of its type's list.
+fn next_sort_index(store: &ConfigStore, config_type: &ConfigType) -> i32 {
+    store
+        .configs
+        .iter()
+        .filter(|c| &c.config_type == config_type)
+        .map(|c| c.sort_index + 1)
+        .max()
+        .unwrap_or(0)
+}
+
+#[tauri::command]
+fn get_configs() -> Vec<ConfigView> {
+    sorted_configs(load_store().configs).into_iter().map(ConfigView::from).collect()
+}
+
+/// Every config whose name matches `name` ignoring case, optionally of one
+/// type. A list, since names don't have to be unique.
+#[tauri::command]
+fn get_config_by_name(name: String, config_type: Option<String>) -> Result<Vec<ConfigView>, String> {
+    let config_type = config_type.as_deref().map(parse_config_type).transpose()?;
+    let name = name.trim().to_lowercase();
+    Ok(sorted_configs(load_store().configs)
+        .into_iter()
+        .filter(|c| c.name.to_lowercase() == name)
+        .filter(|c| config_type.as_ref().is_none_or(|t| &c.config_type == t))
+        .map(ConfigView::from)
+        .collect())
+}
+
+/// `ordered_ids` must list every stored config exactly once; each type's
+/// configs are renumbered in the order they appear.
+#[tauri::command]
+fn reorder_configs(ordered_ids: Vec<String>) -> Result<(), String> {
+    let (_lock, mut store) = load_store_locked();
+    let mut seen = HashSet::new();
+    for id in &ordered_ids {
+        if !seen.insert(id.as_str()) {
+            return Err(format!("Config '{}' is listed more than once", id));
+        }
+        if !store.configs.iter().any(|c| &c.id == id) {
+            return Err(format!("Config '{}' not found", id));
+        }
+    }
+    if let Some(missing) = store.configs.iter().find(|c| !seen.contains(c.id.as_str())) {
+        return Err(format!("Config '{}' is missing from the new order", missing.name));
+    }
+
+    let mut next: HashMap<ConfigType, i32> = HashMap::new();
+    for id in &ordered_ids {
+        let config = store.configs.iter_mut().find(|c| &c.id == id).unwrap();
+        let index = next.entry(config.config_type.clone()).or_insert(0);
+        config.sort_index = *index;
+        *index += 1;
+    }
+    save_store(&store)
+}
+
+/// Stores URLs without trailing slashes so "https://x/" and "https://x"
+/// behave the same once written to the tool files.
+fn normalize_base_url(base_url: &str) -> String {
+    base_url.trim().trim_end_matches('/').to_string()
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Blocks `add_config` / `update_config`.
+    Error,
+    /// Shown to the user but saved anyway.
+    Warning,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationIssue {
+    pub field: String,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Accepts `http(s)://host[...]` with a non-empty host and no whitespace.
+fn is_http_url(url: &str) -> bool {
+    let Some(rest) = url.strip_prefix("https://").or_else(|| url.strip_prefix("http://")) else {
+        return false;
+    };
+    let host = rest.split(['/', '?', '#']).next().unwrap_or("");
+    !host.is_empty() && !url.contains(char::is_whitespace)
+}
+
+/// Prefix official keys start with. Skipped when a base URL is set, since
+/// relays hand out keys in their own format.
+fn expected_key_prefix(config_type: &ConfigType) -> Option<&'static str> {
+    match config_type {
+        ConfigType::Claude => Some("sk-ant-"),
+        ConfigType::Gemini => Some("AIza"),
+        ConfigType::Codex => Some("sk-"),
+        ConfigType::Other(_) => None,
+    }
+}
+
+/// Checks the user-entered fields of a config. Expects `base_url` already
+/// normalized by `normalize_base_url`.
+fn validate_config_fields(
+    config_type: &ConfigType,
+    name: &str,
+    api_key: &str,
+    base_url: &str,
+    options: &ConfigOptions,
+) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    let mut issue = |field: &str, severity: Severity, message: String| {
+        issues.push(ValidationIssue {
+            field: field.to_string(),
+            severity,
+            message,
+        })
+    };
+    if name.trim().is_empty() {
+        issue("name", Severity::Error, "Name must not be empty".to_string());
+    }
+    // With `replace_builtin_env` the key comes from the env overrides instead.
+    if api_key.trim().is_empty() && !options.replace_builtin_env {
+        issue("api_key", Severity::Error, "API key must not be empty".to_string());
+    } else if api_key != api_key.trim() {
+        issue("api_key", Severity::Error, "API key has leading or trailing whitespace".to_string());
+    } else if let Some(prefix) = expected_key_prefix(config_type) {
+        if base_url.is_empty() && !api_key.is_empty() && !api_key.starts_with(prefix) {
+            issue(
+                "api_key",
+                Severity::Warning,
+                format!("{} keys usually start with '{}'", String::from(config_type.clone()), prefix),
+            );
+        }
+    }
+    if !base_url.is_empty() && !is_http_url(base_url) {
+        issue("base_url", Severity::Error, format!("'{}' is not an http(s) URL", base_url));
+    }
+    if let Some(path) = &options.notes_file_path {
+        if !Path::new(path).is_file() {
+            issue("notes_file_path", Severity::Warning, format!("'{}' does not exist", path));
+        }
+    }
+    issues
+}
+
+const WEAK_KEY_WARNING: &str = "API key looks like a password";
+
+/// A key `weak_key_reason` flagged. Only needs confirming: `add_config`
+/// takes it with `allow_weak_key`.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct WeakKeyWarning {
+    pub reason: String,
+}
+
+/// Why `add_config` didn't add a config, tagged by `kind` so the frontend can
+/// ask to confirm a weak key or a duplicate without parsing the message.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AddConfigError {
+    WeakKey(WeakKeyWarning),
+    /// Same type, key and base URL as `existing`; `force` adds it anyway.
+    Duplicate { existing: String },
+    Invalid { message: String },
+}
+
+impl From<String> for AddConfigError {
+    fn from(message: String) -> Self {
+        AddConfigError::Invalid { message }
+    }
+}
+
+impl std::fmt::Display for AddConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AddConfigError::WeakKey(warning) => write!(f, "{} ({})", WEAK_KEY_WARNING, warning.reason),
+            AddConfigError::Duplicate { existing } => write!(f, "Config already exists as '{}'", existing),
+            AddConfigError::Invalid { message } => f.write_str(message),
+        }
+    }
+}
+
+/// Why `api_key` looks more like a pasted password than an API key, if it
+/// does. Real keys are long single tokens of ASCII characters.
+fn weak_key_reason(api_key: &str) -> Option<&'static str> {
+    if api_key.is_empty() {
+        None
+    } else if api_key.contains(char::is_whitespace) {
+        Some("contains spaces")
+    } else if !api_key.is_ascii() {
+        Some("contains non-ASCII characters")
+    } else if api_key.chars().count() < 20 {
+        Some("shorter than 20 characters")
+    } else {
+        None
+    }
+}
+
+/// Fails with every hard error, one per line as `field: message`.
+fn ensure_valid(issues: &[ValidationIssue]) -> Result<(), String> {
+    let errors: Vec<String> = issues
+        .iter()
+        .filter(|i| i.severity == Severity::Error)
+        .map(|i| format!("{}: {}", i.field, i.message))
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
+/// Lets the form show per-field errors and warnings before saving. With `id`,
+/// a key equal to that config's mask counts as its stored key, as in
+/// `update_config`.
+#[tauri::command]
+fn validate_config(
+    config_type: String,
+    name: String,
+    api_key: String,
+    base_url: String,
+    options: Option<ConfigOptions>,
+    id: Option<String>,
+) -> Result<Vec<ValidationIssue>, String> {
+    let config_type = parse_config_type(&config_type)?;
+    let stored = id.and_then(|id| load_store().configs.into_iter().find(|c| c.id == id));
+    let api_key = match stored {
+        Some(config) if api_key == mask_api_key(&config.api_key) => config.api_key,
+        _ => api_key,
+    };
+    Ok(validate_config_fields(
+        &config_type,
+        &name,
+        &api_key,
+        &normalize_base_url(&base_url),
+        &options.unwrap_or_default(),
+    ))
+}
+
+/// Trims tags, drops empty ones and duplicates, keeping the first spelling.
+fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim();
+        if !tag.is_empty() && !normalized.iter().any(|t| t == tag) {
+            normalized.push(tag.to_string());
+        }
+    }
+    normalized
+}
+
+/// Filters by case-insensitive name substring, type, and tags (a config must
+/// carry every tag given). `sort` is "name", "type" or "last_used"; anything
+/// else keeps the stored order.
+#[tauri::command]
+fn search_configs(
+    query: Option<String>,
+    config_type: Option<String>,
+    tags: Vec<String>,
+    sort: Option<String>,
+) -> Result<Vec<ConfigView>, String> {
+    let config_type = config_type.as_deref().map(parse_config_type).transpose()?;
+    let query = query.map(|q| q.trim().to_lowercase()).filter(|q| !q.is_empty());
+    let tags = normalize_tags(tags);
+
+    let mut matches: Vec<Config> = load_store()
+        .configs
+        .into_iter()
+        .filter(|c| config_type.as_ref().is_none_or(|t| &c.config_type == t))
+        .filter(|c| query.as_ref().is_none_or(|q| c.name.to_lowercase().contains(q)))
+        .filter(|c| tags.iter().all(|t| c.tags.contains(t)))
+        .collect();
+
+    match sort.as_deref() {
+        Some("name") => matches.sort_by_key(|c| c.name.to_lowercase()),
+        Some("type") => matches.sort_by_key(|c| (String::from(c.config_type.clone()), c.name.to_lowercase())),
+        Some("last_used") => {
+            let last_used = history::last_activated();
+            // Most recent first; never-activated configs go last.
+            matches.sort_by_key(|c| std::cmp::Reverse(last_used.get(&c.id).copied().unwrap_or(0)));
+        }
+        _ => {}
+    }
+
+    Ok(matches.into_iter().map(ConfigView::from).collect())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: usize,
+}
+
+/// Distinct tags across all configs with how many configs carry each,
+/// sorted by tag.
+#[tauri::command]
+fn list_tags() -> Vec<TagCount> {
+    let mut counts: std::collections::BTreeMap<String, usize> = std::collections::BTreeMap::new();
+    for config in load_store().configs {
+        for tag in config.tags {
+            *counts.entry(tag).or_default() += 1;
+        }
+    }
+    counts.into_iter().map(|(tag, count)| TagCount { tag, count }).collect()
+}
+
+#[tauri::command]
+fn reveal_api_key(id: String) -> Result<String, String> {
+    let config = load_store()
+        .configs
+        .into_iter()
+        .find(|c| c.id == id)
+        .ok_or("Config not found")?;
+    require_api_key(&config)?;
+    Ok(config.api_key)
+}
+
+#[tauri::command]
+fn get_env_vars_for_config_type(config_type: String) -> Result<HashMap<String, String>, String> {
+    let config_type = parse_config_type(&config_type)?;
+    Ok(managed_keys(&config_type)
+        .unwrap_or_default()
+        .iter()
+        .map(|(role, key)| (role.to_string(), key.to_string()))
+        .collect())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EnvFormat {
+    Dotenv,
+    Posix,
+    Powershell,
+}
+
+impl EnvFormat {
+    fn parse(s: &str) -> Result<Self, String> {
+        match s.to_lowercase().as_str() {
+            "dotenv" | "env" => Ok(EnvFormat::Dotenv),
+            "posix" | "sh" | "bash" => Ok(EnvFormat::Posix),
+            "powershell" | "ps1" => Ok(EnvFormat::Powershell),
+            _ => Err(format!("Unknown format '{}' (expected dotenv, posix or powershell)", s)),
+        }
+    }
+
+    /// One assignment, quoted so the value comes through literally.
+    fn line(self, key: &str, value: &str) -> String {
+        match self {
+            // Single quotes are literal in dotenv loaders but can't hold a
+            // quote or newline; those fall back to escaped double quotes.
+            EnvFormat::Dotenv if !value.contains(['\'', '\n', '\r']) => format!("{}='{}'", key, value),
+            EnvFormat::Dotenv => {
+                let escaped = value
+                    .replace('\\', "\\\\")
+                    .replace('"', "\\\"")
+                    .replace('$', "\\$")
+                    .replace('\n', "\\n")
+                    .replace('\r', "\\r");
+                format!("{}=\"{}\"", key, escaped)
+            }
+            EnvFormat::Posix => format!("export {}='{}'", key, value.replace('\'', "'\\''")),
+            EnvFormat::Powershell => format!("$env:{} = '{}'", key, value.replace('\'', "''")),
+        }
+    }
+}
+
+/// Codex's entries in `expected_applied_values` include config.toml keys;
+/// only real environment variable names belong in a snippet.
+fn is_env_var_name(key: &str) -> bool {
+    !key.is_empty()
+        && !key.starts_with(|c: char| c.is_ascii_digit())
+        && key.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// The variables the active configs (optionally only `config_type`'s), or
+/// the configs in `ids`, would set, as a dotenv file or a POSIX / PowerShell
+/// script for people who don't run the app.
+#[tauri::command]
+fn export_env_snippet(config_type: Option<String>, format: String, ids: Option<Vec<String>>) -> Result<String, String> {
+    let format = EnvFormat::parse(&format)?;
+    let config_type = config_type.as_deref().map(parse_config_type).transpose()?;
+    let store = load_store();
+    let configs: Vec<&Config> = match &ids {
+        Some(ids) => ids
+            .iter()
+            .map(|id| {
+                store
+                    .configs
+                    .iter()
+                    .find(|c| &c.id == id)
+                    .ok_or_else(|| format!("Config with id '{}' not found", id))
+            })
+            .collect::<Result<_, _>>()?,
+        None => store.configs.iter().filter(|c| c.is_active).collect(),
+    };
+
+    let mut lines = Vec::new();
+    for config in configs {
+        if config_type.as_ref().is_some_and(|t| t != &config.config_type) {
+            continue;
+        }
+        require_api_key(config)?;
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        lines.push(format!("# {}: {}", String::from(config.config_type.clone()), config.name));
+        for (key, value) in expected_applied_values(config) {
+            if let Some(value) = value.filter(|_| is_env_var_name(&key)) {
+                lines.push(format.line(&key, &value));
+            }
+        }
+    }
+    if lines.is_empty() {
+        return Err("No config to export".to_string());
+    }
+    lines.push(String::new());
+    Ok(lines.join("\n"))
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+fn add_config(
+    name: String,
+    config_type: String,
+    api_key: String,
+    base_url: String,
+    model: String,
+    options: Option<ConfigOptions>,
+    force: Option<bool>,
+    tags: Option<Vec<String>>,
+    allow_weak_key: Option<bool>,
+) -> Result<Config, AddConfigError> {
+    let (_lock, mut store) = load_store_locked();
+
+    let config_type_enum = parse_config_type(&config_type)?;
+    let name = name.trim().to_string();
+    let base_url = normalize_base_url(&base_url);
+    let options = options.unwrap_or_default();
+    validate_config_options(&config_type_enum, &options)?;
+    ensure_valid(&validate_config_fields(&config_type_enum, &name, &api_key, &base_url, &options))?;
+
+    if let Some(reason) = weak_key_reason(&api_key).filter(|_| !allow_weak_key.unwrap_or(false)) {
+        return Err(AddConfigError::WeakKey(WeakKeyWarning {
+            reason: reason.to_string(),
+        }));
+    }
+    let duplicate = find_duplicate_config(&store, &config_type_enum, &api_key, &base_url);
+    if let Some(existing) = duplicate.filter(|_| !force.unwrap_or(false)) {
+        return Err(AddConfigError::Duplicate {
+            existing: existing.name.clone(),
+        });
+    }
+
+    let sort_index = next_sort_index(&store, &config_type_enum);
+    let config = Config {
+        id: Uuid::new_v4().to_string(),
+        name,
+        config_type: config_type_enum,
+        api_key,
+        base_url,
+        model,
+        is_active: false,
+        options,
+        is_shared: false,
+        tags: normalize_tags(tags.unwrap_or_default()),
+        created_at: Some(now_millis()),
+        sort_index,
+        ..Default::default()
+    };
+    store.configs.push(config.clone());
+    save_store(&store)?;
+    Ok(config)
+}
+
+/// `options` and `tags` replace the stored values wholesale when given;
+/// omitting them leaves them untouched.
+#[tauri::command]
+fn update_config(
+    id: String,
+    name: String,
+    api_key: String,
+    base_url: String,
+    model: String,
+    options: Option<ConfigOptions>,
+    tags: Option<Vec<String>>,
+) -> Result<(), String> {
+    let (_lock, mut store) = load_store_locked();
+    let Some(config) = store.configs.iter_mut().find(|c| c.id == id) else {
+        return Ok(());
+    };
+    if config.is_shared {
+        return Err("Cannot modify a shared config".to_string());
+    }
+    if let Some(options) = &options {
+        validate_config_options(&config.config_type, options)?;
+    }
+    let before = config.clone();
+    config.name = name.trim().to_string();
+    // The edit form is filled from `get_configs`, so an untouched key field
+    // comes back as the mask.
+    if api_key != mask_api_key(&config.api_key) {
+        config.api_key = api_key;
+    }
+    config.base_url = normalize_base_url(&base_url);
+    config.model = model;
+    if let Some(options) = options {
+        config.options = options;
+    }
+    if let Some(tags) = tags {
+        config.tags = normalize_tags(tags);
+    }
+    ensure_valid(&validate_config_fields(
+        &config.config_type,
+        &config.name,
+        &config.api_key,
+        &config.base_url,
+        &config.options,
+    ))?;
+    // Saving the form unchanged shouldn't make a stale key look fresh.
+    if *config == before {
+        return Ok(());
+    }
+    config.updated_at = Some(now_millis());
+    let updated = config.clone();
+
+    // If this config is active, re-apply it
+    if updated.is_active {
+        save_store(&store)?;
+        apply_config(&updated, &mut store, false)?;
+        events::emit(events::CONFIG_ACTIVATED, &updated);
+    }
+    for activation in store.project_activations.iter_mut().filter(|a| a.config_id == id) {
+        *activation = project::apply(&updated, &activation.project_dir, &activation.written_keys)?;
+    }
+    save_store(&store)?;
+    history::record(HistoryAction::Update, &updated);
+    Ok(())
+}
+
+/// Copies a config under a fresh id, named "Foo (copy)" or "Foo (copy N)"
+/// if that's taken, and places it right after the original.
+#[tauri::command]
+fn duplicate_config(id: String) -> Result<Config, String> {
+    let (_lock, mut store) = load_store_locked();
+    let index = store
+        .configs
+        .iter()
+        .position(|c| c.id == id)
+        .ok_or("Config not found")?;
+
+    let mut copy = store.configs[index].clone();
+    let name_taken = |name: &str| store.configs.iter().any(|c| c.name == name);
+    let mut name = format!("{} (copy)", copy.name);
+    let mut n = 2;
+    while name_taken(&name) {
+        name = format!("{} (copy {})", copy.name, n);
+        n += 1;
+    }
+    copy.id = Uuid::new_v4().to_string();
+    copy.name = name;
+    copy.is_active = false;
+    copy.opencode_active = false;
+    // Copying is how a shared config gets a locally editable version.
+    copy.is_shared = false;
+    copy.sort_index = next_sort_index(&store, &copy.config_type);
+    mark_created(&mut copy);
+
+    store.configs.insert(index + 1, copy.clone());
+    save_store(&store)?;
+    Ok(copy)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyResult {
+    pub all_ok: bool,
+    pub failed_vars: Vec<String>,
+}
+
+/// Re-reads the target tool's files after activation to confirm the config
+/// really landed there.
+#[tauri::command]
+fn verify_apply_success(id: String) -> Result<VerifyResult, String> {
+    let store = load_store();
+    let config = store
+        .configs
+        .iter()
+        .find(|c| c.id == id)
+        .ok_or("Config not found")?;
+    let failed_vars = find_mismatched_keys(config)?;
+    Ok(VerifyResult {
+        all_ok: failed_vars.is_empty(),
+        failed_vars,
+    })
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncState {
+    InSync,
+    /// Some values differ from the config; see `ConfigStatus::drifted`.
+    Drifted,
+    /// None of the config's values are present (or the file is unreadable).
+    Missing,
+}
+
+/// One value that differs. Secrets are masked like `get_configs` keys.
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldDrift {
+    pub key: String,
+    pub expected: Option<String>,
+    pub actual: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigStatus {
+    pub id: String,
+    pub name: String,
+    pub config_type: ConfigType,
+    pub state: SyncState,
+    pub drifted: Vec<FieldDrift>,
+}
+
+fn mask_secret_value(key: &str, value: &str) -> String {
+    let upper = key.to_ascii_uppercase();
+    if ["KEY", "TOKEN", "SECRET"].iter().any(|s| upper.contains(s)) {
+        mask_api_key(value)
+    } else {
+        value.to_string()
+    }
+}
+
+fn config_status(config: &Config) -> ConfigStatus {
+    let (state, drifted) = match read_applied_values(&config.config_type) {
+        Err(e) => {
+            eprintln!("config_status: {}", e);
+            (SyncState::Missing, Vec::new())
+        }
+        Ok(actual) => {
+            let expected = expected_applied_values(config);
+            let none_present = expected
+                .iter()
+                .filter(|(_, value)| value.is_some())
+                .all(|(key, _)| !actual.contains_key(key));
+            let drifted: Vec<FieldDrift> = expected
+                .into_iter()
+                .filter(|(key, value)| actual.get(key) != value.as_ref())
+                .map(|(key, value)| FieldDrift {
+                    expected: value.map(|v| mask_secret_value(&key, &v)),
+                    actual: actual.get(&key).map(|v| mask_secret_value(&key, v)),
+                    key,
+                })
+                .collect();
+            let state = if drifted.is_empty() {
+                SyncState::InSync
+            } else if none_present {
+                SyncState::Missing
+            } else {
+                SyncState::Drifted
+            };
+            (state, drifted)
+        }
+    };
+    ConfigStatus {
+        id: config.id.clone(),
+        name: config.name.clone(),
+        config_type: config.config_type.clone(),
+        state,
+        drifted,
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigWithStatus {
+    pub config: ConfigView,
+    pub has_issues: bool,
+    /// All problems found, one per line.
+    pub issue_description: Option<String>,
+}
+
+/// Problems with a stored config: a key the keyring couldn't supply, fields
+/// that no longer pass `validate_config_fields`, and for active configs any
+/// drift between the config and the tool's files.
+fn config_issues(config: &Config) -> Vec<String> {
+    let mut issues = Vec::new();
+    if let Err(e) = require_api_key(config) {
+        issues.push(e);
+    } else {
+        for issue in validate_config_fields(
+            &config.config_type,
+            &config.name,
+            &config.api_key,
+            &config.base_url,
+            &config.options,
+        ) {
+            issues.push(format!("{}: {}", issue.field, issue.message));
+        }
+    }
+    if config.is_active {
+        let status = config_status(config);
+        match status.state {
+            SyncState::InSync => {}
+            SyncState::Missing => issues.push("Not present in the tool's config files".to_string()),
+            SyncState::Drifted => {
+                let keys: Vec<String> = status.drifted.into_iter().map(|d| d.key).collect();
+                issues.push(format!("Differs from the tool's config files: {}", keys.join(", ")));
+            }
+        }
+    }
+    issues
+}
+
+/// `get_configs` plus a health check per config, for badges in the list.
+#[tauri::command]
+fn get_configs_with_status() -> Vec<ConfigWithStatus> {
+    sorted_configs(load_store().configs)
+        .into_iter()
+        .map(|config| {
+            let issues = config_issues(&config);
+            ConfigWithStatus {
+                config: ConfigView::from(config),
+                has_issues: !issues.is_empty(),
+                issue_description: (!issues.is_empty()).then(|| issues.join("\n")),
+            }
+        })
+        .collect()
+}
+
+const BENCHMARK_CONCURRENCY: usize = 4;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchmarkResult {
+    pub id: String,
+    pub name: String,
+    #[serde(flatten)]
+    pub benchmark: Benchmark,
+    /// The last failed round's error, if any failed.
+    pub error: Option<String>,
+}
+
+/// Measures every saved config of a type over `rounds` requests each, a few
+/// configs at a time, and keeps the results in `Config.last_benchmark`.
+/// Sorted fastest first; configs that never answered come last.
+#[tauri::command]
+async fn benchmark_configs(config_type: String, rounds: u8) -> Result<Vec<BenchmarkResult>, String> {
+    let config_type = parse_config_type(&config_type)?;
+    if !(1..=10).contains(&rounds) {
+        return Err("rounds must be between 1 and 10".to_string());
+    }
+    let configs: Vec<Config> = load_store()
+        .configs
+        .into_iter()
+        .filter(|c| c.config_type == config_type && require_api_key(c).is_ok())
+        .collect();
+    let mut results = tauri::async_runtime::spawn_blocking(move || {
+        health::map_bounded(&configs, BENCHMARK_CONCURRENCY, |config| {
+            let (benchmark, error) = health::benchmark(config, rounds);
+            BenchmarkResult {
+                id: config.id.clone(),
+                name: config.name.clone(),
+                benchmark,
+                error,
+            }
+        })
+    })
+    .await
+    .map_err(|e| format!("Benchmark failed: {}", e))?;
+    results.sort_by_key(|r| r.benchmark.avg_latency_ms.unwrap_or(u64::MAX));
+
+    let (_lock, mut store) = load_store_locked();
+    for result in &results {
+        if let Some(config) = store.configs.iter_mut().find(|c| c.id == result.id) {
+            config.last_benchmark = Some(result.benchmark.clone());
+        }
+    }
+    save_store(&store)?;
+    Ok(results)
+}
+
+const HEALTH_CHECK_CONCURRENCY: usize = 5;
+const HEALTH_CHECK_PROGRESS: &str = "health-check-progress";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthCheckResult {
+    pub id: String,
+    pub name: String,
+    #[serde(flatten)]
+    pub check: health::HealthCheck,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthSummary {
+    /// Configs checked before the run ended, in store order.
+    pub results: Vec<HealthCheckResult>,
+    /// Stopped early by `cancel_health_check`.
+    pub cancelled: bool,
+}
+
+/// Checks every saved config with a key, a few at a time. Each result is
+/// sent as a `health-check-progress` event (a `HealthCheckResult`) as soon as
+/// it arrives, and kept in `Config.last_health`.
+#[tauri::command]
+async fn check_all_configs(app: tauri::AppHandle) -> Result<HealthSummary, String> {
+    let run = health::CheckAllRun::start().ok_or("A health check is already running")?;
+    let configs: Vec<Config> = load_store()
+        .configs
+        .into_iter()
+        .filter(|c| require_api_key(c).is_ok())
+        .collect();
+    let (results, run) = tauri::async_runtime::spawn_blocking(move || {
+        let results = health::map_bounded(&configs, HEALTH_CHECK_CONCURRENCY, |config| {
+            if run.cancelled() {
+                return None;
+            }
+            let result = HealthCheckResult {
+                id: config.id.clone(),
+                name: config.name.clone(),
+                check: health::check_config(config, health::DEFAULT_TIMEOUT),
+            };
+            if let Err(e) = tauri::Emitter::emit(&app, HEALTH_CHECK_PROGRESS, &result) {
+                eprintln!("health: failed to emit {}: {}", HEALTH_CHECK_PROGRESS, e);
+            }
+            Some(result)
+        });
+        (results.into_iter().flatten().collect::<Vec<_>>(), run)
+    })
+    .await
+    .map_err(|e| format!("Health check failed: {}", e))?;
+
+    let (_lock, mut store) = load_store_locked();
+    for result in &results {
+        if let Some(config) = store.configs.iter_mut().find(|c| c.id == result.id) {
+            config.last_health = Some(result.check.clone());
+        }
+    }
+    save_store(&store)?;
+    Ok(HealthSummary {
+        results,
+        cancelled: run.cancelled(),
+    })
+}
+
+/// Stops a running `check_all_configs`; see `health::cancel_check_all`.
+#[tauri::command]
+fn cancel_health_check() -> bool {
+    health::cancel_check_all()
+}
+
+/// Queries this month's spend for a config's key and keeps the result in
+/// `Config.last_usage`. A provider or key that can't report usage gives a
+/// non-`ok` status, not an error.
+#[tauri::command]
+async fn fetch_usage(id: String) -> Result<usage::Usage, String> {
+    let config = load_store()
+        .configs
+        .into_iter()
+        .find(|c| c.id == id)
+        .ok_or("Config not found")?;
+    require_api_key(&config)?;
+    let usage = tauri::async_runtime::spawn_blocking(move || usage::fetch(&config))
+        .await
+        .map_err(|e| format!("Usage check failed: {}", e))?;
+
+    let (_lock, mut store) = load_store_locked();
+    if let Some(config) = store.configs.iter_mut().find(|c| c.id == id) {
+        config.last_usage = Some(usage.clone());
+    }
+    save_store(&store)?;
+    Ok(usage)
+}
+
+/// Sets the manual usage note; blank clears it.
+#[tauri::command]
+fn set_usage_note(id: String, text: String) -> Result<(), String> {
+    let (_lock, mut store) = load_store_locked();
+    let config = store.configs.iter_mut().find(|c| c.id == id).ok_or("Config not found")?;
+    let text = text.trim();
+    config.usage_note = (!text.is_empty()).then(|| text.to_string());
+    save_store(&store)
+}
+
+/// Checks every active config against the target tool's files, to catch
+/// edits made by hand or by another tool since activation.
+#[tauri::command]
+fn verify_active_configs() -> Vec<ConfigStatus> {
+    load_store()
+        .configs
+        .iter()
+        .filter(|c| c.is_active)
+        .map(config_status)
+        .collect()
+}
+
+/// Re-applies an active config over whatever drifted, returning the status
+/// read back afterwards.
+#[tauri::command]
+fn repair(id: String) -> Result<ConfigStatus, String> {
+    let (_lock, mut store) = load_store_locked();
+    let config = store
+        .configs
+        .iter()
+        .find(|c| c.id == id)
+        .cloned()
+        .ok_or("Config not found")?;
+    if !config.is_active {
+        return Err("Only an active config can be repaired".to_string());
+    }
+    apply_config(&config, &mut store, false)?;
+    save_store(&store)?;
+    Ok(config_status(&config))
+}
+
+/// The variables `get_applied_state` reports on.
+const STATE_VARIABLES: [(ConfigType, &str); 4] = [
+    (ConfigType::Claude, "ANTHROPIC_AUTH_TOKEN"),
+    (ConfigType::Claude, "ANTHROPIC_BASE_URL"),
+    (ConfigType::Gemini, "GEMINI_API_KEY"),
+    (ConfigType::Gemini, "GOOGLE_GEMINI_BASE_URL"),
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AppliedVariable {
+    pub name: String,
+    pub config_type: ConfigType,
+    pub present: bool,
+    pub masked_value: Option<String>,
+    /// A stored config that would write this value, if any.
+    pub matched_config_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AppliedCodexState {
+    pub auth_file_exists: bool,
+    pub config_file_exists: bool,
+    /// A stored Codex config that auth.json and config.toml fully match.
+    pub matched_config_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AppliedOpenCodeProvider {
+    pub provider: String,
+    pub config_type: ConfigType,
+    pub key_set: bool,
+    pub masked_key: String,
+    pub matched_config_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AppliedState {
+    pub variables: Vec<AppliedVariable>,
+    pub codex: AppliedCodexState,
+    pub opencode_providers: Vec<AppliedOpenCodeProvider>,
+}
+
+/// Live values for one tool, empty (with a log line) when its files can't be read.
+fn read_applied_values_or_empty(config_type: &ConfigType) -> HashMap<String, String> {
+    read_applied_values(config_type).unwrap_or_else(|e| {
+        eprintln!("get_applied_state: {}", e);
+        HashMap::new()
+    })
+}
+
+/// A stored config of `config_type` that `matches`, preferring the active one.
+fn matching_config_id(
+    store: &ConfigStore,
+    config_type: &ConfigType,
+    matches: impl Fn(&Config) -> bool,
+) -> Option<String> {
+    store
+        .configs
+        .iter()
+        .filter(|c| &c.config_type == config_type && matches(c))
+        .max_by_key(|c| c.is_active)
+        .map(|c| c.id.clone())
+}
+
+/// What the tools will actually pick up right now, whoever wrote it, and
+/// which stored config (if any) it came from. Values set outside the app show
+/// up with no match, which is how drift becomes visible.
+#[tauri::command]
+fn get_applied_state() -> AppliedState {
+    let store = load_store();
+    let mut live: HashMap<ConfigType, HashMap<String, String>> = HashMap::new();
+
+    let variables = STATE_VARIABLES
+        .iter()
+        .map(|(config_type, name)| {
+            let values = live
+                .entry(config_type.clone())
+                .or_insert_with(|| read_applied_values_or_empty(config_type));
+            let value = values.get(*name);
+            AppliedVariable {
+                name: name.to_string(),
+                config_type: config_type.clone(),
+                present: value.is_some(),
+                masked_value: value.map(|v| mask_secret_value(name, v)),
+                matched_config_id: value.and_then(|value| {
+                    matching_config_id(&store, config_type, |c| {
+                        expected_applied_values(c)
+                            .iter()
+                            .any(|(k, v)| k == name && v.as_ref() == Some(value))
+                    })
+                }),
+            }
+        })
+        .collect();
+
+    let codex_dir = get_codex_dir();
+    let auth_file_exists = codex_dir.join("auth.json").exists();
+    let codex_values = read_applied_values_or_empty(&ConfigType::Codex);
+    let codex = AppliedCodexState {
+        auth_file_exists,
+        config_file_exists: codex_dir.join("config.toml").exists(),
+        matched_config_id: if auth_file_exists {
+            matching_config_id(&store, &ConfigType::Codex, |c| {
+                expected_applied_values(c)
+                    .iter()
+                    .all(|(k, v)| codex_values.get(k) == v.as_ref())
+            })
+        } else {
+            None
+        },
+    };
+
+    let opencode = read_json_object(&get_opencode_config_path()).unwrap_or_else(|e| {
+        eprintln!("get_applied_state: {}", e);
+        serde_json::json!({})
+    });
+    let auth = opencode_auth::read();
+    let opencode_providers = OPENCODE_PROVIDER_TYPES
+        .iter()
+        .filter_map(|(provider, config_type)| {
+            let options = opencode.get("provider")?.get(*provider)?.get("options");
+            let api_key = opencode_api_key(options, &auth, provider);
+            Some(AppliedOpenCodeProvider {
+                provider: provider.to_string(),
+                config_type: config_type.clone(),
+                key_set: !api_key.is_empty(),
+                masked_key: if api_key.is_empty() { String::new() } else { mask_api_key(api_key) },
+                matched_config_id: if api_key.is_empty() {
+                    None
+                } else {
+                    matching_config_id(&store, config_type, |c| c.api_key == api_key)
+                },
+            })
+        })
+        .collect();
+
+    AppliedState { variables, codex, opencode_providers }
+}
+
+#[tauri::command]
+fn import_config_from_json_string(json: String) -> Result<Config, String> {
+    let mut config: Config = serde_json::from_str(json.trim())
+        .map_err(|e| format!("Invalid config JSON: {}", e))?;
+    // A pasted config may come from another machine or be a copy of one we
+    // already have, so never trust its id or activation state.
+    config.id = Uuid::new_v4().to_string();
+    config.is_active = false;
+    config.opencode_active = false;
+    config.is_shared = false;
+    mark_created(&mut config);
+    validate_config_options(&config.config_type, &config.options)?;
+
+    let (_lock, mut store) = load_store_locked();
+    config.sort_index = next_sort_index(&store, &config.config_type);
+    store.configs.push(config.clone());
+    save_store(&store)?;
+    Ok(config)
+}
+
+#[derive(Deserialize)]
+struct SharedConfigFile {
+    configs: Vec<Config>,
+}
+
+/// Loads a team config file (the same `{"configs": [...]}` shape as
+/// configs.json) as shared configs. Entries keep the file's ids, so importing
+/// a newer copy of the file updates them in place and re-applies any that
+/// are active.
+#[tauri::command]
+fn import_shared_configs(path: String) -> Result<Vec<Config>, String> {
+    let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let file: SharedConfigFile =
+        serde_json::from_str(&content).map_err(|e| format!("Invalid team config file: {}", e))?;
+
+    let (_lock, mut store) = load_store_locked();
+    let mut imported = Vec::new();
+    let mut reapply = Vec::new();
+    for mut config in file.configs {
+        if config.id.is_empty() {
+            return Err(format!("Shared config '{}' has no id", config.name));
+        }
+        validate_config_options(&config.config_type, &config.options)?;
+        config.is_shared = true;
+
+        match store.configs.iter_mut().find(|c| c.id == config.id) {
+            Some(existing) if !existing.is_shared => {
+                return Err(format!("Config id '{}' is already used by a local config", config.id));
+            }
+            Some(existing) => {
+                // Usage is local; the team file doesn't know about it.
+                config.is_active = existing.is_active;
+                config.opencode_active = existing.opencode_active;
+                config.created_at = existing.created_at;
+                config.last_activated_at = existing.last_activated_at;
+                config.activation_count = existing.activation_count;
+                config.sort_index = existing.sort_index;
+                config.updated_at = existing.updated_at;
+                if config != *existing {
+                    config.updated_at = Some(now_millis());
+                }
+                *existing = config.clone();
+                if config.is_active {
+                    reapply.push(config.clone());
+                }
+            }
+            None => {
+                config.is_active = false;
+                config.opencode_active = false;
+                config.sort_index = next_sort_index(&store, &config.config_type);
+                mark_created(&mut config);
+                store.configs.push(config.clone());
+            }
+        }
+        imported.push(config);
+    }
+
+    save_store(&store)?;
+    for config in &reapply {
+        apply_config(config, &mut store, false)?;
+    }
+    save_store(&store)?;
+    Ok(imported)
+}
+
+#[tauri::command]
+fn delete_config(id: String) -> Result<(), String> {
+    let (_lock, mut store) = load_store_locked();
+    let config_to_delete = store.configs.iter().find(|c| c.id == id).cloned();
+
+    if let Some(config) = &config_to_delete {
+        if config.is_shared {
+            return Err("Cannot modify a shared config".to_string());
+        }
+        if let Some(profile) = store.profiles.iter().find(|p| p.entries.values().any(|c| c == &id)) {
+            return Err(format!("Config is used by profile '{}'", profile.name));
+        }
+        if config.is_active {
+            clear_config(&config.config_type, &mut store)?;
+        }
+        for activation in store.project_activations.iter().filter(|a| a.config_id == id) {
+            project::clear(activation)?;
+        }
+    }
+
+    store.configs.retain(|c| c.id != id);
+    store.project_activations.retain(|a| a.config_id != id);
+    for chain in store.fallbacks.values_mut() {
+        chain.retain(|c| c != &id);
+    }
+    store.fallbacks.retain(|_, chain| !chain.is_empty());
+    for policy in store.rotations.values_mut() {
+        policy.config_ids.retain(|c| c != &id);
+    }
+    store.rotations.retain(|_, policy| !policy.config_ids.is_empty());
+    save_store(&store)?;
+    if store.secret_backend == SecretBackend::Keyring {
+        if let Err(e) = secrets::delete(&id) {
+            eprintln!("delete_config: {}", e);
+        }
+    }
+    if let Some(config) = &config_to_delete {
+        history::record(HistoryAction::Delete, config);
+        events::emit_deleted(config);
+    }
+    budget::forget(&id);
+    Ok(())
+}
+
+/// `target` defaults to the tool's own files. Holds the store lock for the
+/// whole activation, so a background failover never switches configs in the
+/// middle of one the user started. Pauses the type's rotation policy if the
+/// config isn't in its ring.
+#[tauri::command]
+fn activate_config(id: String, target: Option<ActivationTarget>) -> Result<ActivateResult, String> {
+    activate_with(&id, target, false)
+}
+
+/// `activate_config` that overwrites tool files even if they were edited
+/// outside the app, for when the user confirmed after a
+/// `written::EXTERNALLY_MODIFIED` error.
+#[tauri::command]
+fn force_apply_config(id: String, target: Option<ActivationTarget>) -> Result<ActivateResult, String> {
+    activate_with(&id, target, true)
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ActivateResult {
+    /// Set when the config is over its daily budget; it was activated anyway.
+    pub budget_warning: Option<budget::BudgetWarning>,
+}
+
+fn activate_with(id: &str, target: Option<ActivationTarget>, force: bool) -> Result<ActivateResult, String> {
+    let _lock = state::lock();
+    if target == Some(ActivationTarget::Opencode) {
+        activate_config_for_opencode(id, force)?;
+    } else {
+        activate_for_tool(id, force)?;
+    }
+    let (_lock, mut store) = load_store_locked();
+    if target != Some(ActivationTarget::Opencode) && rotation::pause_if_outside(&mut store, id) {
+        save_store(&store)?;
+    }
+    Ok(ActivateResult {
+        budget_warning: store.configs.iter().find(|c| c.id == id).and_then(budget::check),
+    })
+}
+
+/// Adds to today's spend for a config, for a hook that reports what each
+/// request cost (see `budget.rs`). Returns the new daily total.
+#[tauri::command]
+fn record_usage_cost(id: String, cost_usd: f64) -> Result<f64, String> {
+    if !load_store().configs.iter().any(|c| c.id == id) {
+        return Err("Config not found".to_string());
+    }
+    budget::record(&id, cost_usd)
+}
+
+/// `activate_config` for the tool target.
+fn activate_for_tool(id: &str, force: bool) -> Result<(), String> {
+    let (_lock, mut store) = load_store_locked();
+
+    // Find the config to activate
+    let config_to_activate = store
+        .configs
+        .iter()
+        .find(|c| c.id == id)
+        .cloned()
+        .ok_or("Config not found")?;
+
+    // Apply first and flip the flags only once that worked, so a failed
+    // write leaves both the tool files and the store as they were.
+    let snapshot = FileSnapshot::capture(&tool_files(&config_to_activate.config_type));
+    if let Err(e) = apply_config(&config_to_activate, &mut store, force) {
+        snapshot.restore();
+        return Err(e);
+    }
+
+    // Deactivate only configs of the same type
+    for config in &mut store.configs {
+        if config.config_type == config_to_activate.config_type {
+            config.is_active = false;
+        }
+    }
+
+    // Activate the selected config
+    if let Some(config) = store.configs.iter_mut().find(|c| c.id == id) {
+        config.is_active = true;
+        config.last_activated_at = Some(now_millis());
+        config.activation_count += 1;
+    }
+
+    if let Err(e) = save_store(&store) {
+        snapshot.restore();
+        return Err(e);
+    }
+    history::record(HistoryAction::Activate, &config_to_activate);
+    events::emit(events::CONFIG_ACTIVATED, &config_to_activate);
+    events::post_webhook(&config_to_activate);
+    Ok(())
+}
+
+/// Writes the config into its type's default provider block in opencode.json,
+/// leaving the other blocks and the tool's own files alone.
+fn activate_config_for_opencode(id: &str, force: bool) -> Result<(), String> {
+    let config = load_store()
+        .configs
+        .into_iter()
+        .find(|c| c.id == id)
+        .ok_or("Config not found")?;
+    let slot_id = |config_type: ConfigType| (config.config_type == config_type).then(|| config.id.clone());
+    let (claude_id, gemini_id, codex_id) =
+        (slot_id(ConfigType::Claude), slot_id(ConfigType::Gemini), slot_id(ConfigType::Codex));
+    if claude_id.is_none() && gemini_id.is_none() && codex_id.is_none() {
+        return Err(format!(
+            "OpenCode has no provider block for {} configs",
+            String::from(config.config_type)
+        ));
+    }
+    apply_opencode_config(claude_id, gemini_id, codex_id, None, None, None, None, None, Some(force), None)?;
+    history::record(HistoryAction::Activate, &config);
+    Ok(())
+}
+
+/// Moves the `opencode_active` flag of each type whose provider block was
+/// just written or cleared to `config_id` (or to none).
+fn set_opencode_active(store: &mut ConfigStore, config_type: &ConfigType, config_id: Option<&str>) {
+    for config in &mut store.configs {
+        if &config.config_type == config_type {
+            config.opencode_active = Some(config.id.as_str()) == config_id;
+        }
+    }
+}
+
+/// Every stored key, for `Plan::describe` to mask.
+fn stored_keys(store: &ConfigStore) -> Vec<String> {
+    store.configs.iter().map(|c| c.api_key.clone()).collect()
+}
+
+/// What `activate_config` would change in the tool's files, from the same
+/// plan activation executes. Nothing is written.
+#[tauri::command]
+fn preview_activation(id: String) -> Result<ActivationPlan, String> {
+    let store = load_store();
+    let config = store.configs.iter().find(|c| c.id == id).ok_or("Config not found")?;
+    Ok(plan_apply(config, &store)?.describe(&stored_keys(&store)))
+}
+
+/// What `deactivate_config` would change; empty if the config isn't active.
+#[tauri::command]
+fn preview_deactivation(id: String) -> Result<ActivationPlan, String> {
+    let store = load_store();
+    let config = store.configs.iter().find(|c| c.id == id).ok_or("Config not found")?;
+    let plan = if config.is_active {
+        plan_clear(&config.config_type, &store)?.0
+    } else {
+        Plan::default()
+    };
+    Ok(plan.describe(&stored_keys(&store)))
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DeactivateOptions {
+    /// Codex only: remove `auth.json` but leave `config.toml` (model,
+    /// provider settings) in place. Ignored for other types.
+    #[serde(default)]
+    pub preserve_tool_config: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DeactivateResult {
+    /// What was actually removed: variable names for Claude and Gemini, file
+    /// paths for Codex. Empty if the config wasn't active.
+    pub cleared: Vec<String>,
+}
+
+/// `target` defaults to the tool's own files.
+#[tauri::command]
+fn deactivate_config(id: String, target: Option<ActivationTarget>) -> Result<DeactivateResult, String> {
+    if target != Some(ActivationTarget::Opencode) {
+        return deactivate_config_with_options(id, DeactivateOptions::default());
+    }
+    let store = load_store();
+    let Some(config) = store.configs.iter().find(|c| c.id == id && c.opencode_active) else {
+        return Ok(DeactivateResult::default());
+    };
+    let provider = OPENCODE_PROVIDER_TYPES
+        .iter()
+        .find(|(_, config_type)| config_type == &config.config_type)
+        .map(|(provider, _)| provider.to_string())
+        .ok_or("OpenCode has no provider block for this config type")?;
+    let deactivated = config.clone();
+    clear_opencode_config(vec![provider.clone()], None)?;
+    history::record(HistoryAction::Deactivate, &deactivated);
+    events::emit(events::CONFIG_DEACTIVATED, &deactivated);
+    Ok(DeactivateResult {
+        cleared: vec![format!("provider.{}", provider)],
+    })
+}
+
+#[tauri::command]
+fn deactivate_config_with_options(id: String, options: DeactivateOptions) -> Result<DeactivateResult, String> {
+    let (_lock, mut store) = load_store_locked();
+    let mut result = DeactivateResult::default();
+
+    if let Some(config) = store.configs.iter_mut().find(|c| c.id == id) {
+        if config.is_active {
+            config.is_active = false;
+            let deactivated = config.clone();
+            save_store(&store)?;
+            if options.preserve_tool_config && deactivated.config_type == ConfigType::Codex {
+                let stale = store.applied_env.get(&ConfigType::Codex).cloned().unwrap_or_default();
+                let mut plan = Plan::default();
+                result.cleared = clear_codex_files(&mut plan, false, true, &stale)?;
+                plan.execute()?;
+                store.applied_env.remove(&ConfigType::Codex);
+            } else {
+                result.cleared = clear_config(&deactivated.config_type, &mut store)?;
+            }
+            save_store(&store)?;
+            history::record(HistoryAction::Deactivate, &deactivated);
+            events::emit(events::CONFIG_DEACTIVATED, &deactivated);
+        }
+    }
+
+    Ok(result)
+}
+
+/// Newest entries first.
+#[tauri::command]
+fn get_history(limit: usize) -> Vec<HistoryEntry> {
+    history::load_history().into_iter().rev().take(limit).collect()
+}
+
+/// Compacts the history now rather than at `history_threshold`; returns how
+/// many entries were removed.
+#[tauri::command]
+fn compact_event_log() -> Result<usize, String> {
+    let _lock = state::lock();
+    history::compact_event_log()
+}
+
+/// `checkpoint_interval` entries are kept whenever the history grows past
+/// `threshold`.
+#[tauri::command]
+fn set_history_limits(threshold: usize, checkpoint_interval: usize) -> Result<(), String> {
+    if checkpoint_interval == 0 || checkpoint_interval > threshold {
+        return Err("The checkpoint interval must be between 1 and the threshold".to_string());
+    }
+    let (_lock, mut store) = load_store_locked();
+    store.history_threshold = threshold;
+    store.checkpoint_interval = checkpoint_interval;
+    save_store(&store)
+}
+
+/// Every entry must name an existing config of the matching type.
+fn validate_profile_entries(store: &ConfigStore, entries: &HashMap<ConfigType, String>) -> Result<(), String> {
+    for (config_type, config_id) in entries {
+        let config = store
+            .configs
+            .iter()
+            .find(|c| &c.id == config_id)
+            .ok_or_else(|| format!("Config '{}' not found", config_id))?;
+        if &config.config_type != config_type {
+            return Err(format!(
+                "Config '{}' is a {} config, not {}",
+                config.name,
+                String::from(config.config_type.clone()),
+                String::from(config_type.clone())
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn list_profiles() -> Vec<Profile> {
+    load_store().profiles
+}
+
+#[tauri::command]
+fn create_profile(name: String, entries: HashMap<ConfigType, String>, opencode: bool) -> Result<Profile, String> {
+    let (_lock, mut store) = load_store_locked();
+    validate_profile_entries(&store, &entries)?;
+    let profile = Profile {
+        id: Uuid::new_v4().to_string(),
+        name,
+        entries,
+        opencode,
+    };
+    store.profiles.push(profile.clone());
+    save_store(&store)?;
+    Ok(profile)
+}
+
+#[tauri::command]
+fn update_profile(id: String, name: String, entries: HashMap<ConfigType, String>, opencode: bool) -> Result<(), String> {
+    let (_lock, mut store) = load_store_locked();
+    validate_profile_entries(&store, &entries)?;
+    let profile = store
+        .profiles
+        .iter_mut()
+        .find(|p| p.id == id)
+        .ok_or("Profile not found")?;
+    profile.name = name;
+    profile.entries = entries;
+    profile.opencode = opencode;
+    save_store(&store)
+}
+
+#[tauri::command]
+fn delete_profile(id: String) -> Result<(), String> {
+    let (_lock, mut store) = load_store_locked();
+    store.profiles.retain(|p| p.id != id);
+    save_store(&store)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileEntryResult {
+    pub config_type: ConfigType,
+    pub config_id: String,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileActivationResult {
+    pub entries: Vec<ProfileEntryResult>,
+    /// `None` when the profile doesn't touch OpenCode or it was applied.
+    pub opencode_error: Option<String>,
+}
+
+/// Activates each entry in turn (Claude, Gemini, Codex, then custom types),
+/// carrying on past failures so one bad entry doesn't leave the rest
+/// unswitched. Check `error` on each entry for the outcome.
+#[tauri::command]
+fn activate_profile(id: String) -> Result<ProfileActivationResult, String> {
+    let store = load_store();
+    let profile = store
+        .profiles
+        .into_iter()
+        .find(|p| p.id == id)
+        .ok_or("Profile not found")?;
+
+    let rank = |t: &ConfigType| match t {
+        ConfigType::Claude => 0,
+        ConfigType::Gemini => 1,
+        ConfigType::Codex => 2,
+        ConfigType::Other(_) => 3,
+    };
+    let mut entries: Vec<(ConfigType, String)> = profile.entries.into_iter().collect();
+    entries.sort_by_key(|(t, _)| (rank(t), String::from(t.clone())));
+
+    let results: Vec<ProfileEntryResult> = entries
+        .into_iter()
+        .map(|(config_type, config_id)| ProfileEntryResult {
+            error: activate_config(config_id.clone(), None).err(),
+            config_type,
+            config_id,
+        })
+        .collect();
+
+    let opencode_error = if profile.opencode {
+        let id_for = |t: ConfigType| {
+            results
+                .iter()
+                .find(|r| r.config_type == t && r.error.is_none())
+                .map(|r| r.config_id.clone())
+        };
+        apply_opencode_config(
+            id_for(ConfigType::Claude),
+            id_for(ConfigType::Gemini),
+            id_for(ConfigType::Codex),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .err()
+    } else {
+        None
+    };
+
+    Ok(ProfileActivationResult {
+        entries: results,
+        opencode_error,
+    })
+}
+
+/// The failover chain of every type that has one.
+#[tauri::command]
+fn get_fallback_chains() -> HashMap<ConfigType, Vec<String>> {
+    load_store().fallbacks
+}
+
+/// Replaces the failover order for a type; an empty list removes it.
+#[tauri::command]
+fn set_fallback_chain(config_type: String, config_ids: Vec<String>) -> Result<(), String> {
+    let config_type = parse_config_type(&config_type)?;
+    let (_lock, mut store) = load_store_locked();
+    let mut chain: Vec<String> = Vec::new();
+    for id in config_ids {
+        let config = store
+            .configs
+            .iter()
+            .find(|c| c.id == id)
+            .ok_or_else(|| format!("Config with id '{}' not found", id))?;
+        if config.config_type != config_type {
+            return Err(format!("'{}' is not a {} config", config.name, String::from(config_type)));
+        }
+        if !chain.contains(&id) {
+            chain.push(id);
+        }
+    }
+    if chain.is_empty() {
+        store.fallbacks.remove(&config_type);
+    } else {
+        store.fallbacks.insert(config_type, chain);
+    }
+    save_store(&store)
+}
+
+#[tauri::command]
+fn get_rotation_policy(config_type: String) -> Result<Option<rotation::RotationPolicy>, String> {
+    let config_type = parse_config_type(&config_type)?;
+    Ok(load_store().rotations.remove(&config_type))
+}
+
+/// Replaces the rotation policy for a type, which also resumes a paused one;
+/// an empty ring removes it. `last_rotated_at` is kept from the old policy,
+/// or starts now, so the first timed rotation is one interval away.
+#[tauri::command]
+fn set_rotation_policy(config_type: String, mut policy: rotation::RotationPolicy) -> Result<(), String> {
+    let config_type = parse_config_type(&config_type)?;
+    if policy.interval == rotation::RotationInterval::Hours(0) {
+        return Err("Rotation interval must be at least 1 hour".to_string());
+    }
+    let (_lock, mut store) = load_store_locked();
+    let mut ring: Vec<String> = Vec::new();
+    for id in policy.config_ids {
+        let config = store
+            .configs
+            .iter()
+            .find(|c| c.id == id)
+            .ok_or_else(|| format!("Config with id '{}' not found", id))?;
+        if config.config_type != config_type {
+            return Err(format!("'{}' is not a {} config", config.name, String::from(config_type)));
+        }
+        if !ring.contains(&id) {
+            ring.push(id);
+        }
+    }
+    if ring.is_empty() {
+        store.rotations.remove(&config_type);
+        return save_store(&store);
+    }
+    if policy.enabled && ring.len() < 2 {
+        return Err("A rotation needs at least two configs".to_string());
+    }
+    policy.config_ids = ring;
+    policy.paused = false;
+    policy.last_rotated_at = store
+        .rotations
+        .get(&config_type)
+        .and_then(|p| p.last_rotated_at)
+        .or_else(|| Some(now_millis()));
+    store.rotations.insert(config_type, policy);
+    save_store(&store)
+}
+
+/// Activates the next reachable config in the type's ring right away,
+/// whether or not the policy is enabled, and returns it.
+#[tauri::command]
+async fn rotate_now(app: tauri::AppHandle, config_type: String) -> Result<Config, String> {
+    let config_type = parse_config_type(&config_type)?;
+    let event = tauri::async_runtime::spawn_blocking(move || rotation::rotate(&config_type, true))
+        .await
+        .map_err(|e| format!("Rotation failed: {}", e))??
+        .ok_or("The active config changed while rotating; try again")?;
+    let config = load_store()
+        .configs
+        .into_iter()
+        .find(|c| c.id == event.to_id)
+        .ok_or("Config not found")?;
+    rotation::emit(&app, event);
+    Ok(config)
+}
+
+#[tauri::command]
+fn get_settings() -> Settings {
+    load_store().settings
+}
+
+/// Blank path settings are stored as unset.
+#[tauri::command]
+fn update_settings(mut settings: Settings) -> Result<(), String> {
+    for path in [
+        &mut settings.codex_dir,
+        &mut settings.opencode_config_path,
+        &mut settings.claude_settings_path,
+    ] {
+        *path = path.take().map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+    }
+    validate_settings(&settings)?;
+    let (_lock, mut store) = load_store_locked();
+    let tray_disabled = settings.tray_disabled;
+    store.settings = settings;
+    save_store(&store)?;
+    tray::set_disabled(tray_disabled);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_failover_settings() -> FailoverSettings {
+    load_store().failover
+}
+
+#[tauri::command]
+fn set_failover_settings(settings: FailoverSettings) -> Result<(), String> {
+    if settings.interval_minutes == 0 {
+        return Err("interval_minutes must be at least 1".to_string());
+    }
+    if settings.failure_threshold == 0 {
+        return Err("failure_threshold must be at least 1".to_string());
+    }
+    let (_lock, mut store) = load_store_locked();
+    store.failover = settings;
+    save_store(&store)
+}
+
+/// Moves every plaintext key into the OS keyring and switches the store to
+/// keep only `keyring:<id>` references. Fails without changing anything if
+/// the keyring can't be written. Returns how many keys were moved.
+#[tauri::command]
+fn migrate_secrets_to_keyring() -> Result<usize, String> {
+    let (_lock, mut store) = load_store_locked();
+    let mut moved = 0;
+    for config in &store.configs {
+        if config.api_key.is_empty() || secrets::is_reference(&config.api_key) {
+            continue;
+        }
+        secrets::store(&config.id, &config.api_key)?;
+        moved += 1;
+    }
+    store.secret_backend = SecretBackend::Keyring;
+    save_store(&store)?;
+    Ok(moved)
+}
+
+/// Switches back to the most recently activated config of this type other
+/// than the one active now, skipping configs that have since been deleted.
+#[tauri::command]
+fn reactivate_previous(config_type: String) -> Result<Config, String> {
+    let config_type = parse_config_type(&config_type)?;
+    let store = load_store();
+    let current = store
+        .configs
+        .iter()
+        .find(|c| c.config_type == config_type && c.is_active)
+        .map(|c| c.id.clone());
+
+    let previous = history::load_history()
+        .into_iter()
+        .rev()
+        .filter(|e| e.action == HistoryAction::Activate && e.config_type == config_type)
+        .filter(|e| Some(&e.config_id) != current.as_ref())
+        .find_map(|e| store.configs.iter().find(|c| c.id == e.config_id).cloned())
+        .ok_or("No previous config in history")?;
+
+    activate_config(previous.id.clone(), None)?;
+    Ok(previous)
+}
+
+/// Writes the config into `project_dir` instead of the user-wide tool files.
+/// Replaces any config of the same type already activated for that project.
+#[tauri::command]
+fn activate_config_for_project(id: String, project_dir: String) -> Result<ProjectActivation, String> {
+    let (_lock, mut store) = load_store_locked();
+    let config = store
+        .configs
+        .iter()
+        .find(|c| c.id == id)
+        .cloned()
+        .ok_or("Config not found")?;
+
+    let previous = store
+        .project_activations
+        .iter()
+        .position(|a| a.project_dir == project_dir && a.config_type == config.config_type)
+        .map(|i| store.project_activations.remove(i));
+    let stale = previous.map(|a| a.written_keys).unwrap_or_default();
+
+    let activation = project::apply(&config, &project_dir, &stale)?;
+    store.project_activations.push(activation.clone());
+    save_store(&store)?;
+    Ok(activation)
+}
+
+/// Removes exactly the keys written by `activate_config_for_project`.
+#[tauri::command]
+fn deactivate_config_for_project(id: String, project_dir: String) -> Result<(), String> {
+    let (_lock, mut store) = load_store_locked();
+    let index = store
+        .project_activations
+        .iter()
+        .position(|a| a.config_id == id && a.project_dir == project_dir)
+        .ok_or("Config is not active for this project")?;
+    project::clear(&store.project_activations[index])?;
+    store.project_activations.remove(index);
+    save_store(&store)
+}
+
+#[tauri::command]
+fn get_project_activations() -> Vec<ProjectActivation> {
+    load_store().project_activations
+}
+
+/// A credential set found in a tool's own files. Only the masked key leaves
+/// the backend; `import_detected` re-reads the source by `id`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetectedConfig {
+    pub id: String,
+    pub source: String,
+    pub config_type: ConfigType,
+    pub masked_key: String,
+    pub base_url: String,
+    pub model: String,
+    #[serde(default)]
+    pub already_imported: bool,
+}
+
+/// Creates a config for each key variable in a `.env` file, e.g. one Claude
+/// config for `ANTHROPIC_AUTH_TOKEN` and one Gemini config for
+/// `GEMINI_API_KEY`, each with its type's base URL and model variables (see
+/// `paste::parse_env_file`). Keys already stored are skipped and nothing is
+/// activated.
+#[tauri::command]
+fn import_from_env_file(path: String) -> Result<Vec<Config>, String> {
+    let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let found = paste::parse_env_file(&content);
+    if found.is_empty() {
+        return Err(format!("No API key variables recognized in {}", path));
+    }
+    let file_name = Path::new(&path)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.clone());
+
+    let (_lock, mut store) = load_store_locked();
+    let mut imported = Vec::new();
+    for credential in found {
+        if find_duplicate_config(&store, &credential.config_type, &credential.api_key, &credential.base_url).is_some() {
+            continue;
+        }
+        let name = match &credential.source {
+            Some(var) => format!("{} ({})", file_name, var),
+            None => file_name.clone(),
+        };
+        let mut config = Config {
+            id: Uuid::new_v4().to_string(),
+            name,
+            config_type: credential.config_type,
+            api_key: credential.api_key,
+            base_url: credential.base_url,
+            model: credential.model,
+            ..Default::default()
+        };
+        mark_created(&mut config);
+        config.sort_index = next_sort_index(&store, &config.config_type);
+        store.configs.push(config.clone());
+        imported.push(config);
+    }
+
+    if !imported.is_empty() {
+        save_store(&store)?;
+    }
+    Ok(imported)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExternalImportResult {
+    pub imported: Vec<Config>,
+    /// (name, reason) for each entry that wasn't imported.
+    pub skipped: Vec<(String, String)>,
+}
+
+/// Imports configs from another tool's export; see `external.rs` for the
+/// formats. Entries matching a stored config (same type, key and base URL)
+/// are skipped, and nothing is activated. Everything is saved in one write,
+/// so a failure leaves the store as it was.
+#[tauri::command]
+fn import_external(path: String, format: String) -> Result<ExternalImportResult, String> {
+    let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let parsed = external::parse(&content, &format)?;
+
+    let (_lock, mut store) = load_store_locked();
+    let mut imported = Vec::new();
+    let mut skipped = parsed.skipped;
+    for entry in parsed.entries {
+        if let Some(existing) = find_duplicate_config(&store, &entry.config_type, &entry.api_key, &entry.base_url) {
+            skipped.push((entry.name, format!("Same key and base URL as '{}'", existing.name)));
+            continue;
+        }
+        let mut config = Config {
+            id: Uuid::new_v4().to_string(),
+            name: entry.name,
+            config_type: entry.config_type,
+            api_key: entry.api_key,
+            base_url: entry.base_url,
+            ..Default::default()
+        };
+        mark_created(&mut config);
+        config.sort_index = next_sort_index(&store, &config.config_type);
+        store.configs.push(config.clone());
+        imported.push(config);
+    }
+
+    if !imported.is_empty() {
+        save_store(&store)?;
+    }
+    Ok(ExternalImportResult { imported, skipped })
+}
+
+struct DetectedCredential {
+    detected: DetectedConfig,
+    api_key: String,
+    options: ConfigOptions,
+}
+
+fn find_duplicate_config<'a>(
+    store: &'a ConfigStore,
+    config_type: &ConfigType,
+    api_key: &str,
+    base_url: &str,
+) -> Option<&'a Config> {
+    store
+        .configs
+        .iter()
+        .find(|c| &c.config_type == config_type && c.api_key == api_key && c.base_url == base_url)
+}
+
+fn new_detected_credential(
+    id: String,
+    source: String,
+    config_type: ConfigType,
+    api_key: String,
+    base_url: String,
+    model: String,
+    options: ConfigOptions,
+) -> DetectedCredential {
+    DetectedCredential {
+        detected: DetectedConfig {
+            id,
+            source,
+            config_type,
+            masked_key: mask_api_key(&api_key),
+            base_url,
+            model,
+            already_imported: false,
+        },
+        api_key,
+        options,
+    }
+}
+
+/// Reads the credentials currently live in a tool's own files, reusing the
+/// same reader `verify_apply_success` relies on.
+fn detect_from_tool_files(config_type: &ConfigType) -> Option<DetectedCredential> {
+    let values = match read_applied_values(config_type) {
+        Ok(values) => values,
+        Err(e) => {
+            eprintln!("Skipping {:?} credential detection: {}", config_type, e);
+            return None;
+        }
+    };
+    let [(_, key_var), (_, url_var), (_, model_var)] = managed_keys(config_type)?;
+    let api_key = values.get(key_var).filter(|k| !k.is_empty())?.clone();
+    let model = values.get(model_var).cloned().unwrap_or_default();
+
+    let (id, source, base_url, options) = match config_type {
+        ConfigType::Claude => {
+            ("tool:claude", "~/.claude/settings.json", values.get(url_var).cloned(), ConfigOptions::default())
+        }
+        ConfigType::Gemini => {
+            ("tool:gemini", "~/.gemini/.env", values.get(url_var).cloned(), ConfigOptions::default())
+        }
+        ConfigType::Codex => {
+            // The provider table isn't always "fox" in a hand-written config.toml.
+            let provider = values.get("model_provider").cloned();
+            let base_url = provider
+                .as_ref()
+                .and_then(|p| values.get(&format!("model_providers.{}.base_url", p)).cloned());
+            let codex = CodexOptions {
+                provider_name: provider.filter(|p| p != CODEX_DEFAULT_PROVIDER),
+                ..Default::default()
+            };
+            let mut options = ConfigOptions::default();
+            if codex.provider_name.is_some() && validate_codex_options(&codex).is_ok() {
+                options.codex = Some(codex);
+            }
+            ("tool:codex", "~/.codex/auth.json", base_url, options)
+        }
+        ConfigType::Other(_) => return None,
+    };
+
+    Some(new_detected_credential(
+        id.to_string(),
+        source.to_string(),
+        config_type.clone(),
+        api_key,
+        base_url.unwrap_or_default(),
+        model,
+        options,
+    ))
+}
+
+/// The provider blocks `apply_opencode_config` fills, by the config type they take.
+const OPENCODE_PROVIDER_TYPES: [(&str, ConfigType); 3] = [
+    ("foxcode-claude", ConfigType::Claude),
+    ("foxcode-gemini", ConfigType::Gemini),
+    ("foxcode-oai", ConfigType::Codex),
+];
+
+/// Picks up keys already filled into the OpenCode provider blocks that
+/// `apply_opencode_config` writes to.
+fn detect_from_opencode() -> Vec<DetectedCredential> {
+    let json = match read_json_object(&get_opencode_config_path()) {
+        Ok(json) => json,
+        Err(e) => {
+            eprintln!("Skipping OpenCode credential detection: {}", e);
+            return Vec::new();
+        }
+    };
+    let top_model = json.get("model").and_then(|m| m.as_str()).unwrap_or("");
+    let auth = opencode_auth::read();
+
+    OPENCODE_PROVIDER_TYPES
+        .iter()
+        .filter_map(|(provider_key, config_type)| {
+            let options = json.get("provider")?.get(provider_key)?.get("options")?;
+            let api_key = Some(opencode_api_key(Some(options), &auth, provider_key)).filter(|k| !k.is_empty())?;
+            let base_url = options.get("baseURL").and_then(|u| u.as_str()).unwrap_or("");
+            let model = top_model
+                .strip_prefix(provider_key)
+                .and_then(|m| m.strip_prefix('/'))
+                .unwrap_or("");
+            Some(new_detected_credential(
+                format!("opencode:{}", provider_key),
+                format!("~/.config/opencode/opencode.json ({})", provider_key),
+                config_type.clone(),
+                api_key.to_string(),
+                base_url.to_string(),
+                model.to_string(),
+                ConfigOptions::default(),
+            ))
+        })
+        .collect()
+}
+
+fn get_continue_config_path() -> PathBuf {
+    get_user_home().join(".continue").join("config.json")
+}
+
+/// Keys from the Continue IDE plugin's `models` array. Only the JSON config
+/// is read; a missing file yields nothing.
+fn detect_from_continue() -> Result<Vec<DetectedCredential>, String> {
+    let json = read_json_object(&get_continue_config_path())?;
+    let Some(models) = json.get("models").and_then(|m| m.as_array()) else {
+        return Ok(Vec::new());
+    };
+    Ok(models
+        .iter()
+        .enumerate()
+        .filter_map(|(index, model)| {
+            let config_type = match model.get("provider")?.as_str()? {
+                "anthropic" => ConfigType::Claude,
+                "gemini" => ConfigType::Gemini,
+                "openai" => ConfigType::Codex,
+                _ => return None,
+            };
+            let field = |key: &str| model.get(key).and_then(|v| v.as_str()).unwrap_or("").to_string();
+            let api_key = field("apiKey");
+            if api_key.is_empty() {
+                return None;
+            }
+            let title = field("title");
+            Some(new_detected_credential(
+                format!("continue:{}", index),
+                format!("~/.continue/config.json ({})", if title.is_empty() { field("model") } else { title }),
+                config_type,
+                api_key,
+                normalize_base_url(&field("apiBase")),
+                field("model"),
+                ConfigOptions::default(),
+            ))
+        })
+        .collect())
+}
+
+fn detect_credentials(store: &ConfigStore) -> Vec<DetectedCredential> {
+    let mut found: Vec<DetectedCredential> = Vec::new();
+    let candidates = [ConfigType::Claude, ConfigType::Gemini, ConfigType::Codex]
+        .iter()
+        .filter_map(detect_from_tool_files)
+        .chain(detect_from_opencode())
+        .chain(detect_from_continue().unwrap_or_else(|e| {
+            eprintln!("Skipping Continue credential detection: {}", e);
+            Vec::new()
+        }));
+    for mut candidate in candidates {
+        // OpenCode usually holds the same key as the tool itself; list it once.
+        let seen = found.iter().any(|f| {
+            f.detected.config_type == candidate.detected.config_type
+                && f.api_key == candidate.api_key
+                && f.detected.base_url == candidate.detected.base_url
+        });
+        if seen {
+            continue;
+        }
+        candidate.detected.already_imported = find_duplicate_config(
+            store,
+            &candidate.detected.config_type,
+            &candidate.api_key,
+            &candidate.detected.base_url,
+        )
+        .is_some();
+        found.push(candidate);
+    }
+    found
+}
+
+#[tauri::command]
+fn detect_existing_configs() -> Vec<DetectedConfig> {
+    detect_credentials(&load_store())
+        .into_iter()
+        .map(|c| c.detected)
+        .collect()
+}
+
+/// Candidates from the Continue plugin only; import them with
+/// `import_detected`. Keys that match one already found in a tool's own
+/// files are listed there instead.
+#[tauri::command]
+fn import_from_continue() -> Result<Vec<DetectedConfig>, String> {
+    // Surface a malformed file here; `detect_credentials` only logs it.
+    detect_from_continue()?;
+    Ok(detect_credentials(&load_store())
+        .into_iter()
+        .map(|c| c.detected)
+        .filter(|d| d.id.starts_with("continue:"))
+        .collect())
+}
+
+/// Guesses one or more configs from pasted text; see `paste.rs`.
+#[tauri::command]
+fn parse_credential(text: String) -> Result<Vec<paste::ParsedCredential>, String> {
+    let found = paste::parse(&text);
+    if found.is_empty() {
+        return Err("No API key recognized in the pasted text".to_string());
+    }
+    Ok(found)
+}
+
+/// Creates configs for the selected candidates. Anything already stored is
+/// skipped, so running the import twice is harmless. A new config is marked
+/// active when its values are exactly what the tool currently has applied.
+#[tauri::command]
+fn import_detected(items: Vec<DetectedConfig>) -> Result<Vec<Config>, String> {
+    let (_lock, mut store) = load_store_locked();
+    let mut imported = Vec::new();
+
+    for candidate in detect_credentials(&store) {
+        if !items.iter().any(|i| i.id == candidate.detected.id) {
+            continue;
+        }
+        let detected = candidate.detected;
+        if find_duplicate_config(&store, &detected.config_type, &candidate.api_key, &detected.base_url).is_some() {
+            continue;
+        }
+
+        let mut config = Config {
+            id: Uuid::new_v4().to_string(),
+            name: format!("Imported from {}", detected.source),
+            config_type: detected.config_type,
+            api_key: candidate.api_key,
+            base_url: detected.base_url,
+            model: detected.model,
+            is_active: false,
+            options: candidate.options,
+            is_shared: false,
+            tags: Vec::new(),
+            created_at: Some(now_millis()),
+            ..Default::default()
+        };
+        let type_has_active = store
+            .configs
+            .iter()
+            .any(|c| c.config_type == config.config_type && c.is_active);
+        config.is_active = !type_has_active && matches!(find_mismatched_keys(&config), Ok(m) if m.is_empty());
+        config.sort_index = next_sort_index(&store, &config.config_type);
+
+        store.configs.push(config.clone());
+        imported.push(config);
+    }
+
+    save_store(&store)?;
+    Ok(imported)
+}
+
+fn get_opencode_template_path() -> PathBuf {
+    get_config_path().with_file_name("opencode-template.json")
+}
+
+/// Checks the parts of a template `apply_opencode_config` relies on, naming
+/// the offending path on failure.
+fn validate_opencode_template(template: &serde_json::Value) -> Result<(), String> {
+    let root = template.as_object().ok_or("template is not a JSON object")?;
+    let providers = root
+        .get("provider")
+        .ok_or("provider missing")?
+        .as_object()
+        .ok_or("provider is not an object")?;
+    for (name, provider) in providers {
+        let provider = provider
+            .as_object()
+            .ok_or_else(|| format!("provider.{} is not an object", name))?;
+        match provider.get("options") {
+            None => return Err(format!("provider.{}.options missing", name)),
+            Some(options) if !options.is_object() => {
+                return Err(format!("provider.{}.options is not an object", name))
+            }
+            Some(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// The user's template from `set_opencode_template_content` if there is a
+/// valid one, else the built-in template.
+fn get_opencode_template() -> String {
+    let path = get_opencode_template_path();
+    let Ok(content) = fs::read_to_string(&path) else {
+        return builtin_opencode_template().to_string();
+    };
+    let valid = serde_json::from_str(&content)
+        .map_err(|e| e.to_string())
+        .and_then(|value| validate_opencode_template(&value));
+    match valid {
+        Ok(()) => content,
+        Err(e) => {
+            eprintln!("Ignoring {}: {}", path.display(), e);
+            builtin_opencode_template().to_string()
+        }
+    }
+}
+
+#[tauri::command]
+fn get_opencode_template_content() -> String {
+    get_opencode_template()
+}
+
+/// Saves `json` as the template used when opencode.json is missing or
+/// unreadable, and for provider blocks `apply_opencode_config` creates.
+#[tauri::command]
+fn set_opencode_template_content(json: String) -> Result<(), String> {
+    let value: serde_json::Value = serde_json::from_str(&json).map_err(|e| format!("Invalid JSON: {}", e))?;
+    validate_opencode_template(&value).map_err(|e| format!("Invalid template: {}", e))?;
+    write_file_atomic(&get_opencode_template_path(), json.as_bytes())
+}
+
+/// Goes back to the built-in template.
+#[tauri::command]
+fn reset_opencode_template() -> Result<(), String> {
+    match fs::remove_file(get_opencode_template_path()) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("Failed to remove custom template: {}", e)),
+    }
+}
+
+fn builtin_opencode_template() -> &'static str {
+    r#"{
+  "$schema": "https://opencode.ai/config.json",
+  "tui": {
+    "scroll_speed": 3,
+    "scroll_acceleration": {
+      "enabled": true
     }
   },
   "provider": {
@@ -592,145 +4690,1530 @@ fn get_opencode_template() -> &'static str {
             }
           }
         }
-      }
-    },
-    "foxcode-gemini": {
-      "npm": "@ai-sdk/google",
-      "name": "foxcode-gemini",
-      "options": {
-        "baseURL": "https://generativelanguage.googleapis.com/v1beta",
-        "apiKey": ""
-      },
-      "models": {
-        "gemini-3-pro": {
-          "name": "gemini-3-pro"
-        },
-        "gemini-2.5-flash-lite": {
-          "name": "gemini-2.5-flash-lite"
-        },
-        "gemini-3-pro-preview": {
-          "name": "gemini-3-pro-preview"
-        },
-        "gemini-3-flash-preview": {
-          "name": "gemini-3-flash-preview"
+      }
+    },
+    "foxcode-gemini": {
+      "npm": "@ai-sdk/google",
+      "name": "foxcode-gemini",
+      "options": {
+        "baseURL": "https://generativelanguage.googleapis.com/v1beta",
+        "apiKey": ""
+      },
+      "models": {
+        "gemini-3-pro": {
+          "name": "gemini-3-pro"
+        },
+        "gemini-2.5-flash-lite": {
+          "name": "gemini-2.5-flash-lite"
+        },
+        "gemini-3-pro-preview": {
+          "name": "gemini-3-pro-preview"
+        },
+        "gemini-3-flash-preview": {
+          "name": "gemini-3-flash-preview"
+        }
+      }
+    }
+  },
+  "model": "foxcode-claude/claude-opus-4-5-20251101",
+  "small_model": "foxcode-claude/claude-haiku-4-5-20251001",
+  "plugin": [],
+  "mcp": {}
+}"#
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OpenCodeProviderStatus {
+    Updated,
+    Created,
+    NotSelected,
+    /// A config was selected but the block isn't in `providers_whitelist`.
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OpenCodeProviderChange {
+    /// "claude", "gemini" or "codex".
+    pub slot: String,
+    pub provider: String,
+    pub status: OpenCodeProviderStatus,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OpenCodeApplyResult {
+    /// The top-level `model` that was set, if any.
+    pub model: Option<String>,
+    pub providers: Vec<OpenCodeProviderChange>,
+}
+
+/// Each slot writes into its `*_provider` block (default `foxcode-claude`,
+/// `foxcode-gemini`, `foxcode-oai`). A block missing from opencode.json is
+/// created from the template's block for that slot. Only `options.apiKey`,
+/// `options.baseURL` (kept as is when the config has none) and the top-level
+/// `model` are written; model metadata
+/// (`cost`, `limit`, `modalities`, `release_date`, ...) and key order are
+/// left as they were. A non-empty `providers_whitelist` limits the write to
+/// the named blocks. Unless `force`, an opencode.json edited by hand since we
+/// last wrote it is reported (`written::EXTERNALLY_MODIFIED`), not written.
+/// Nothing is written when the file already has the same JSON content.
+/// With `config_variant` the result goes to `opencode-<variant>.json` next
+/// to opencode.json instead, for users who keep several (work, personal);
+/// a variant isn't the types' OpenCode target, so no config is marked
+/// `opencode_active`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+fn apply_opencode_config(
+    claude_id: Option<String>,
+    gemini_id: Option<String>,
+    codex_id: Option<String>,
+    primary: Option<String>,
+    claude_provider: Option<String>,
+    gemini_provider: Option<String>,
+    codex_provider: Option<String>,
+    providers_whitelist: Option<Vec<String>>,
+    force: Option<bool>,
+    config_variant: Option<String>,
+) -> Result<OpenCodeApplyResult, String> {
+    let whitelist = providers_whitelist.unwrap_or_default();
+    let variant = config_variant.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+    if let Some(variant) = &variant {
+        if !variant.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(format!("Invalid config variant '{}': use letters, digits, '-' or '_'", variant));
+        }
+    }
+    let (_lock, mut store) = load_store_locked();
+    let use_auth_store = store.settings.opencode_auth_store;
+    // auth.json has one key per provider id for every opencode config file,
+    // so writing a variant's keys there would switch opencode.json's too.
+    if use_auth_store && variant.is_some() {
+        return Err("Config variants can't be used with the OpenCode auth store enabled".to_string());
+    }
+    let mut auth_keys = Vec::new();
+
+    // Resolve a config for each tab that had one selected. An id that doesn't
+    // resolve is an error: skipping it would leave the old key in place.
+    let resolve = |id: Option<&String>, ty: ConfigType| -> Result<Option<Config>, String> {
+        let Some(id) = id else {
+            return Ok(None);
+        };
+        store
+            .configs
+            .iter()
+            .find(|c| &c.id == id && c.config_type == ty)
+            .cloned()
+            .ok_or_else(|| format!("Config with id '{}' not found", id))
+            .and_then(|c| require_api_key(&c).map(|_| Some(c)))
+    };
+    let claude_cfg = resolve(claude_id.as_ref(), ConfigType::Claude)?;
+    let gemini_cfg = resolve(gemini_id.as_ref(), ConfigType::Gemini)?;
+    let codex_cfg = resolve(codex_id.as_ref(), ConfigType::Codex)?;
+    // opencode resolves a relative baseURL against nothing useful, so only
+    // absolute URLs are written. An empty one keeps the block's current value.
+    for config in [&claude_cfg, &gemini_cfg, &codex_cfg].into_iter().flatten() {
+        if !config.base_url.is_empty() && !is_http_url(&config.base_url) {
+            return Err(format!(
+                "Config '{}' has base URL '{}', OpenCode needs an absolute http(s) URL",
+                config.name, config.base_url
+            ));
+        }
+    }
+
+    let config_path = match &variant {
+        Some(variant) => get_opencode_config_path().with_file_name(format!("opencode-{}.json", variant)),
+        None => get_opencode_config_path(),
+    };
+    let opencode_dir = config_path.parent().unwrap_or(Path::new("."));
+
+    fs::create_dir_all(opencode_dir).map_err(|e| format!("Failed to create opencode directory: {}", e))?;
+    let read_only = fs::metadata(opencode_dir)
+        .map(|m| m.permissions().readonly())
+        .unwrap_or(false);
+    if read_only {
+        return Err("opencode config directory is read-only".to_string());
+    }
+
+    let on_disk = fs::read_to_string(&config_path).ok();
+    let existing_content = on_disk.clone().unwrap_or_else(get_opencode_template);
+
+    let template: serde_json::Value = serde_json::from_str(&get_opencode_template()).map_err(|e| e.to_string())?;
+    let mut json_value: serde_json::Value =
+        serde_json::from_str(&existing_content).unwrap_or_else(|_| template.clone());
+
+    let providers = json_value
+        .as_object_mut()
+        .ok_or("opencode.json is not a JSON object")?
+        .entry("provider".to_string())
+        .or_insert_with(|| serde_json::json!({}))
+        .as_object_mut()
+        .ok_or("The 'provider' field in opencode.json is not an object")?;
+
+    // Phase 1: write apiKey/baseURL into each selected provider block.
+    let slots = [
+        ("claude", "foxcode-claude", claude_provider, &claude_cfg),
+        ("gemini", "foxcode-gemini", gemini_provider, &gemini_cfg),
+        ("codex", "foxcode-oai", codex_provider, &codex_cfg),
+    ];
+    let mut changes = Vec::new();
+    for (slot, default_key, custom_key, cfg) in slots {
+        let provider_key = custom_key
+            .filter(|k| !k.trim().is_empty())
+            .unwrap_or_else(|| default_key.to_string());
+        let status = match cfg {
+            None => OpenCodeProviderStatus::NotSelected,
+            Some(_) if !whitelist.is_empty() && !whitelist.contains(&provider_key) => OpenCodeProviderStatus::Skipped,
+            Some(config) => {
+                let created = !providers.contains_key(&provider_key);
+                let provider = providers.entry(provider_key.clone()).or_insert_with(|| {
+                    let mut block = template["provider"][default_key].clone();
+                    block["name"] = serde_json::Value::String(provider_key.clone());
+                    block
+                });
+                let options = provider
+                    .as_object_mut()
+                    .ok_or_else(|| {
+                        format!(
+                            "Provider '{}' in opencode.json is not an object.{}",
+                            provider_key,
+                            opencode_reset_hint(&provider_key)
+                        )
+                    })?
+                    .entry("options".to_string())
+                    .or_insert_with(|| serde_json::json!({}))
+                    .as_object_mut()
+                    .ok_or_else(|| format!("Provider '{}' 'options' field is not an object", provider_key))?;
+                let api_key = if use_auth_store {
+                    auth_keys.push((provider_key.clone(), config.api_key.clone()));
+                    String::new()
+                } else {
+                    config.api_key.clone()
+                };
+                options.insert("apiKey".to_string(), serde_json::Value::String(api_key));
+                if !config.base_url.is_empty() {
+                    options.insert("baseURL".to_string(), serde_json::Value::String(config.base_url.clone()));
+                }
+                let mut headers = Vec::new();
+                if let Some(name) = opencode_version_header(&store, &config.config_type) {
+                    headers.push((name, None));
+                }
+                if slot == "claude" {
+                    let budget = config.options.model_thinking_budget.map(|b| b.to_string());
+                    headers.push((THINKING_BUDGET_HEADER.to_string(), budget));
+                }
+                if let Some((name, value)) = &config.options.api_version_header {
+                    headers.push((name.clone(), Some(value.clone())));
+                }
+                for (name, value) in headers {
+                    set_opencode_header(options, &name, value)
+                        .map_err(|e| format!("Provider '{}' {}", provider_key, e))?;
+                }
+                if created {
+                    OpenCodeProviderStatus::Created
+                } else {
+                    OpenCodeProviderStatus::Updated
+                }
+            }
+        };
+        changes.push(OpenCodeProviderChange {
+            slot: slot.to_string(),
+            provider: provider_key,
+            status,
+        });
+    }
+
+    // Phase 2: pick top-level model from the user-chosen primary, with fallback.
+    let configs = [&claude_cfg, &gemini_cfg, &codex_cfg];
+    let pick_model = |slot: usize| -> Option<String> {
+        configs[slot]
+            .as_ref()
+            .filter(|c| !c.model.is_empty() && changes[slot].status != OpenCodeProviderStatus::Skipped)
+            .map(|c| format!("{}/{}", changes[slot].provider, c.model))
+    };
+    let primary_pick = match primary.as_deref() {
+        Some("claude") => pick_model(0),
+        Some("gemini") => pick_model(1),
+        Some("codex") => pick_model(2),
+        _ => None,
+    };
+    let model_to_set = primary_pick
+        .or_else(|| pick_model(0))
+        .or_else(|| pick_model(1))
+        .or_else(|| pick_model(2));
+
+    if let Some(ref m) = model_to_set {
+        check_opencode_model(&json_value, m)?;
+        json_value["model"] = serde_json::Value::String(m.clone());
+    }
+
+    let content = serde_json::to_string_pretty(&json_value).map_err(|e| e.to_string())?;
+    // Re-applying the same configs leaves the file (and its mtime) alone,
+    // also when it only differs from our output in formatting or key order.
+    let unchanged = on_disk.as_deref().is_some_and(|current| {
+        current == content || serde_json::from_str::<serde_json::Value>(current).is_ok_and(|v| v == json_value)
+    });
+    if !unchanged && !force.unwrap_or(false) {
+        let mut plan = Plan::default();
+        plan.write(&config_path, content.clone());
+        plan.check_external_edits(&stored_keys(&store))?;
+    }
+    // Keys first, so opencode.json never has an emptied apiKey without one.
+    opencode_auth::update(&auth_keys, &[])?;
+    match on_disk.filter(|_| unchanged) {
+        Some(current) => written::record(&config_path, Some(current.as_bytes())),
+        None => write_config_file_atomic(&config_path, &content)?,
+    }
+
+    // Only the default blocks in opencode.json count as the type's OpenCode
+    // target.
+    for ((provider, config_type), (config, change)) in
+        OPENCODE_PROVIDER_TYPES.iter().zip(configs.iter().zip(&changes))
+    {
+        if let Some(config) = config.as_ref().filter(|_| variant.is_none()) {
+            if change.provider == *provider && change.status != OpenCodeProviderStatus::Skipped {
+                set_opencode_active(&mut store, config_type, Some(&config.id));
+            }
+        }
+    }
+    save_store(&store)?;
+    for (config, change) in configs.iter().zip(&changes) {
+        if let Some(config) = config.as_ref().filter(|_| change.status != OpenCodeProviderStatus::Skipped) {
+            events::emit(events::OPENCODE_UPDATED, config);
+        }
+    }
+
+    Ok(OpenCodeApplyResult {
+        model: model_to_set,
+        providers: changes,
+    })
+}
+
+/// OpenCode refuses to start when `model` names a provider or model it doesn't
+/// know, so check `provider/model_id` against the `provider` object first.
+/// Model ids may contain `/` themselves; the provider is everything up to the
+/// first one.
+fn check_opencode_model(json: &serde_json::Value, model: &str) -> Result<(), String> {
+    let Some((provider, model_id)) = model.split_once('/').filter(|(p, m)| !p.is_empty() && !m.is_empty()) else {
+        return Err(format!("OpenCode model '{}' is not in 'provider/model_id' form", model));
+    };
+    let provider_obj = json
+        .get("provider")
+        .and_then(|p| p.get(provider))
+        .ok_or_else(|| format!("OpenCode provider '{}' does not exist in opencode.json", provider))?;
+    if provider_obj.get("models").and_then(|m| m.get(model_id)).is_none() {
+        return Err(format!(
+            "Model '{}' is not listed under provider '{}' in opencode.json; add it to the provider first",
+            model_id, provider
+        ));
+    }
+    Ok(())
+}
+
+/// Loads opencode.json for targeted edits. Unlike `apply_opencode_config`, an
+/// unparseable file is an error here rather than a reason to fall back to the
+/// template, since these commands only ever touch one small part of it.
+fn read_opencode_json() -> Result<serde_json::Value, String> {
+    let path = get_opencode_config_path();
+    if !path.exists() {
+        return serde_json::from_str(&get_opencode_template()).map_err(|e| e.to_string());
+    }
+    read_json_object(&path)
+}
+
+/// `(model, small_model)` from opencode.json, empty when a key is unset.
+#[tauri::command]
+fn get_opencode_current_model() -> Result<(String, String), String> {
+    let json = read_opencode_json()?;
+    let get = |key: &str| json.get(key).and_then(|v| v.as_str()).unwrap_or("").to_string();
+    Ok((get("model"), get("small_model")))
+}
+
+fn get_opencode_provider_mut<'a>(
+    json: &'a mut serde_json::Value,
+    provider: &str,
+) -> Result<&'a mut serde_json::Map<String, serde_json::Value>, String> {
+    json.get_mut("provider")
+        .and_then(|p| p.get_mut(provider))
+        .and_then(|p| p.as_object_mut())
+        .ok_or_else(|| format!("Provider '{}' not found in opencode.json.{}", provider, opencode_reset_hint(provider)))
+}
+
+/// Points at `reset_opencode_config` when it can restore `provider`, i.e.
+/// the template has a block of that name.
+fn opencode_reset_hint(provider: &str) -> &'static str {
+    let in_template = serde_json::from_str::<serde_json::Value>(&get_opencode_template())
+        .is_ok_and(|template| template["provider"].get(provider).is_some_and(|p| p.is_object()));
+    if in_template {
+        " Run reset_opencode_config() to restore it."
+    } else {
+        ""
+    }
+}
+
+/// Puts the template's provider blocks back into opencode.json where they're
+/// missing or not objects, e.g. deleted by hand. Blocks that are there are
+/// left alone. Returns the names restored.
+#[tauri::command]
+fn reset_opencode_config() -> Result<Vec<String>, String> {
+    let mut json = read_opencode_json()?;
+    let template: serde_json::Value =
+        serde_json::from_str(&get_opencode_template()).map_err(|e| e.to_string())?;
+    let providers = json
+        .as_object_mut()
+        .ok_or("opencode.json is not a JSON object")?
+        .entry("provider".to_string())
+        .or_insert_with(|| serde_json::json!({}));
+    if !providers.is_object() {
+        *providers = serde_json::json!({});
+    }
+    let providers = providers.as_object_mut().ok_or("The 'provider' field in opencode.json is not an object")?;
+    let mut restored = Vec::new();
+    for (name, block) in template["provider"].as_object().into_iter().flatten() {
+        if !providers.get(name).is_some_and(|p| p.is_object()) {
+            providers.insert(name.clone(), block.clone());
+            restored.push(name.clone());
+        }
+    }
+    if !restored.is_empty() {
+        write_json_pretty(&get_opencode_config_path(), &json)?;
+    }
+    Ok(restored)
+}
+
+#[tauri::command]
+fn add_opencode_model(provider: String, model_id: String, model_name: String) -> Result<(), String> {
+    if model_id.trim().is_empty() {
+        return Err("Model id must not be empty".to_string());
+    }
+    let mut json = read_opencode_json()?;
+    let provider_obj = get_opencode_provider_mut(&mut json, &provider)?;
+
+    let models = provider_obj
+        .entry("models".to_string())
+        .or_insert_with(|| serde_json::json!({}))
+        .as_object_mut()
+        .ok_or_else(|| format!("Provider '{}' 'models' field is not an object", provider))?;
+
+    let name = if model_name.trim().is_empty() { model_id.clone() } else { model_name };
+    // Keep any existing metadata (options, variants, cost, ...) and only set the name.
+    match models.get_mut(&model_id).and_then(|m| m.as_object_mut()) {
+        Some(existing) => {
+            existing.insert("name".to_string(), serde_json::Value::String(name));
+        }
+        None => {
+            models.insert(model_id, serde_json::json!({ "name": name }));
         }
-      }
     }
-  },
-  "model": "foxcode-claude/claude-opus-4-5-20251101",
-  "small_model": "foxcode-claude/claude-haiku-4-5-20251001",
-  "plugin": [],
-  "mcp": {}
-}"#
+
+    write_json_pretty(&get_opencode_config_path(), &json)
 }
 
 #[tauri::command]
-fn apply_opencode_config(
-    claude_id: Option<String>,
-    gemini_id: Option<String>,
-    codex_id: Option<String>,
-    primary: Option<String>,
-) -> Result<Option<String>, String> {
-    let store = load_store();
-    let home = get_user_home();
-    let opencode_dir = home.join(".config").join("opencode");
-    let config_path = opencode_dir.join("opencode.json");
+fn remove_opencode_model(provider: String, model_id: String) -> Result<(), String> {
+    let mut json = read_opencode_json()?;
+    let provider_obj = get_opencode_provider_mut(&mut json, &provider)?;
+
+    let removed = provider_obj
+        .get_mut("models")
+        .and_then(|m| m.as_object_mut())
+        .and_then(|m| m.shift_remove(&model_id));
+    if removed.is_none() {
+        return Err(format!("Model '{}' not found in provider '{}'", model_id, provider));
+    }
+
+    write_json_pretty(&get_opencode_config_path(), &json)
+}
+
+/// Appends a plugin (npm package or file URL) to opencode.json's `plugin`
+/// array. Already listed plugins are rejected rather than duplicated, since
+/// OpenCode would load them twice.
+#[tauri::command]
+fn add_opencode_plugin(plugin: String) -> Result<(), String> {
+    let plugin = plugin.trim().to_string();
+    if plugin.is_empty() {
+        return Err("Plugin name must not be empty".to_string());
+    }
+    let mut json = read_opencode_json()?;
+    let plugins = json
+        .as_object_mut()
+        .ok_or("opencode.json is not a JSON object")?
+        .entry("plugin".to_string())
+        .or_insert_with(|| serde_json::json!([]))
+        .as_array_mut()
+        .ok_or("The 'plugin' field in opencode.json is not an array")?;
+    if plugins.iter().any(|p| p.as_str() == Some(plugin.as_str())) {
+        return Err(format!("Plugin '{}' is already registered", plugin));
+    }
+    plugins.push(serde_json::Value::String(plugin));
+
+    write_json_pretty(&get_opencode_config_path(), &json)
+}
+
+/// Sets one key under `provider.<provider>.options`. A `null` value removes
+/// the key so OpenCode falls back to its own default.
+#[tauri::command]
+fn set_opencode_provider_option(
+    provider: String,
+    option_key: String,
+    option_value: serde_json::Value,
+) -> Result<(), String> {
+    if option_key.trim().is_empty() {
+        return Err("Option key must not be empty".to_string());
+    }
+    let mut json = read_opencode_json()?;
+    let provider_obj = get_opencode_provider_mut(&mut json, &provider)?;
+
+    let options = provider_obj
+        .entry("options".to_string())
+        .or_insert_with(|| serde_json::json!({}))
+        .as_object_mut()
+        .ok_or_else(|| format!("Provider '{}' 'options' field is not an object", provider))?;
+
+    if option_value.is_null() {
+        options.shift_remove(&option_key);
+    } else {
+        options.insert(option_key, option_value);
+    }
 
-    fs::create_dir_all(&opencode_dir).map_err(|e| format!("Failed to create opencode directory: {}", e))?;
+    write_json_pretty(&get_opencode_config_path(), &json)
+}
 
-    let existing_content = if config_path.exists() {
-        fs::read_to_string(&config_path).unwrap_or_else(|_| get_opencode_template().to_string())
+/// Pins the provider's `npm` package to `version`, e.g. `@ai-sdk/anthropic`
+/// to `@ai-sdk/anthropic@1.2.3`, replacing any earlier pin. An empty
+/// `version` removes the pin.
+#[tauri::command]
+fn set_provider_npm_version(provider: String, version: String) -> Result<(), String> {
+    let version = version.trim();
+    if !version.is_empty() {
+        semver::Version::parse(version).map_err(|e| format!("Invalid version '{}': {}", version, e))?;
+    }
+    let mut json = read_opencode_json()?;
+    let provider_obj = get_opencode_provider_mut(&mut json, &provider)?;
+    let npm = provider_obj
+        .get("npm")
+        .and_then(|n| n.as_str())
+        .ok_or_else(|| format!("Provider '{}' has no 'npm' package", provider))?;
+    // A scoped name starts with '@'; only a later one starts the version.
+    let package = match npm.rfind('@') {
+        Some(at) if at > 0 => &npm[..at],
+        _ => npm,
+    };
+    let pinned = if version.is_empty() {
+        package.to_string()
     } else {
-        get_opencode_template().to_string()
+        format!("{}@{}", package, version)
     };
+    provider_obj.insert("npm".to_string(), serde_json::Value::String(pinned));
 
-    let mut json_value: serde_json::Value = serde_json::from_str(&existing_content)
-        .unwrap_or_else(|_| serde_json::from_str(get_opencode_template()).unwrap());
+    write_json_pretty(&get_opencode_config_path(), &json)
+}
 
-    let providers = json_value.get_mut("provider")
-        .ok_or("No 'provider' field found in opencode.json")?;
+#[tauri::command]
+fn set_cache_key_enabled(provider: String, enabled: bool) -> Result<(), String> {
+    set_opencode_provider_option(provider, "setCacheKey".to_string(), serde_json::Value::Bool(enabled))
+}
 
-    // Resolve a (provider_key, config) pair for each tab if a config was selected.
-    let resolve = |id: Option<&String>, ty: ConfigType| -> Option<Config> {
-        let id = id?;
-        store.configs.iter().find(|c| &c.id == id && c.config_type == ty).cloned()
-    };
-    let claude_cfg = resolve(claude_id.as_ref(), ConfigType::Claude);
-    let gemini_cfg = resolve(gemini_id.as_ref(), ConfigType::Gemini);
-    let codex_cfg = resolve(codex_id.as_ref(), ConfigType::Codex);
+/// Sets one key under `provider.<provider>.models.<model>.options`; `None`
+/// removes it. The model must already exist.
+fn set_opencode_model_option(
+    provider: &str,
+    model: &str,
+    option_key: &str,
+    option_value: Option<serde_json::Value>,
+) -> Result<(), String> {
+    let mut json = read_opencode_json()?;
+    let provider_obj = get_opencode_provider_mut(&mut json, provider)?;
 
-    // Phase 1: write apiKey/baseURL into each selected provider block.
-    let writes: [(&str, &Option<Config>); 3] = [
-        ("foxcode-claude", &claude_cfg),
-        ("foxcode-gemini", &gemini_cfg),
-        ("foxcode-oai", &codex_cfg),
-    ];
-    for (provider_key, cfg) in &writes {
-        if let Some(config) = cfg {
-            if let Some(provider) = providers.get_mut(*provider_key) {
-                if let Some(options) = provider.get_mut("options") {
-                    options["apiKey"] = serde_json::Value::String(config.api_key.clone());
-                    if !config.base_url.is_empty() {
-                        options["baseURL"] = serde_json::Value::String(config.base_url.clone());
-                    }
-                }
-            }
+    let model_obj = provider_obj
+        .get_mut("models")
+        .and_then(|m| m.get_mut(model))
+        .and_then(|m| m.as_object_mut())
+        .ok_or_else(|| format!("Model '{}' not found in provider '{}'", model, provider))?;
+    let options = model_obj
+        .entry("options".to_string())
+        .or_insert_with(|| serde_json::json!({}))
+        .as_object_mut()
+        .ok_or_else(|| format!("Model '{}' 'options' field is not an object", model))?;
+
+    match option_value {
+        Some(value) => {
+            options.insert(option_key.to_string(), value);
+        }
+        None => {
+            options.shift_remove(option_key);
         }
     }
 
-    // Phase 2: pick top-level model from the user-chosen primary, with fallback.
-    let pick_model = |cfg: &Option<Config>, provider_key: &str| -> Option<String> {
-        cfg.as_ref()
-            .filter(|c| !c.model.is_empty())
-            .map(|c| format!("{}/{}", provider_key, c.model))
-    };
-    let primary_pick = match primary.as_deref() {
-        Some("claude") => pick_model(&claude_cfg, "foxcode-claude"),
-        Some("gemini") => pick_model(&gemini_cfg, "foxcode-gemini"),
-        Some("codex") => pick_model(&codex_cfg, "foxcode-oai"),
-        _ => None,
+    write_json_pretty(&get_opencode_config_path(), &json)
+}
+
+/// Sets the model's `include` list (e.g. `reasoning.encrypted_content`). An
+/// empty list removes the option.
+#[tauri::command]
+fn set_model_include_option(provider: String, model: String, include_items: Vec<String>) -> Result<(), String> {
+    let items: Vec<String> = include_items
+        .into_iter()
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect();
+    let value = (!items.is_empty()).then(|| serde_json::json!(items));
+    set_opencode_model_option(&provider, &model, "include", value)
+}
+
+#[tauri::command]
+fn set_model_store_option(provider: String, model: String, store: bool) -> Result<(), String> {
+    set_opencode_model_option(&provider, &model, "store", Some(serde_json::Value::Bool(store)))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OpenCodeProviderInfo {
+    pub provider: String,
+    pub key_set: bool,
+    pub masked_key: String,
+    pub base_url: String,
+}
+
+/// Which provider blocks in opencode.json have a key, there or in the
+/// auth store.
+#[tauri::command]
+fn get_opencode_status() -> Result<Vec<OpenCodeProviderInfo>, String> {
+    let json = read_opencode_json()?;
+    let auth = opencode_auth::read();
+    let Some(providers) = json.get("provider").and_then(|p| p.as_object()) else {
+        return Ok(Vec::new());
     };
-    let model_to_set = primary_pick
-        .or_else(|| pick_model(&claude_cfg, "foxcode-claude"))
-        .or_else(|| pick_model(&gemini_cfg, "foxcode-gemini"))
-        .or_else(|| pick_model(&codex_cfg, "foxcode-oai"));
+    Ok(providers
+        .iter()
+        .map(|(name, provider)| {
+            let option = |key: &str| {
+                provider
+                    .get("options")
+                    .and_then(|o| o.get(key))
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("")
+                    .to_string()
+            };
+            let api_key = opencode_api_key(provider.get("options"), &auth, name).to_string();
+            OpenCodeProviderInfo {
+                provider: name.clone(),
+                key_set: !api_key.is_empty(),
+                masked_key: if api_key.is_empty() { String::new() } else { mask_api_key(&api_key) },
+                base_url: option("baseURL"),
+            }
+        })
+        .collect())
+}
 
-    if let Some(ref m) = model_to_set {
-        json_value["model"] = serde_json::Value::String(m.clone());
+/// The template's `baseURL` for a provider block: the block of the same name,
+/// or else one using the same `npm` package (for custom-named blocks).
+fn opencode_template_base_url(template: &serde_json::Value, name: &str, npm: Option<&str>) -> Option<String> {
+    let providers = template.get("provider")?.as_object()?;
+    let block = providers.get(name).or_else(|| {
+        let npm = npm?;
+        providers.values().find(|p| p.get("npm").and_then(|n| n.as_str()) == Some(npm))
+    })?;
+    block.get("options")?.get("baseURL")?.as_str().map(str::to_string)
+}
+
+/// Blanks `apiKey` in the named provider blocks, leaving the rest of
+/// opencode.json as it is. With `reset_base_url`, `baseURL` goes back to the
+/// template default too. A missing file has nothing to clear; an unparseable
+/// one is reported and left untouched.
+#[tauri::command]
+fn clear_opencode_config(provider_slots: Vec<String>, reset_base_url: Option<bool>) -> Result<(), String> {
+    let path = get_opencode_config_path();
+    if !path.exists() {
+        return Ok(());
     }
+    let (_lock, mut store) = load_store_locked();
+    let mut json = read_json_object(&path)?;
+    let template: serde_json::Value =
+        serde_json::from_str(&get_opencode_template()).map_err(|e| e.to_string())?;
 
-    let content = serde_json::to_string_pretty(&json_value).map_err(|e| e.to_string())?;
-    fs::write(&config_path, content).map_err(|e| format!("Failed to write opencode.json: {}", e))?;
+    for name in &provider_slots {
+        let version_header = OPENCODE_PROVIDER_TYPES
+            .iter()
+            .find(|(provider, _)| provider == name)
+            .and_then(|(_, config_type)| opencode_version_header(&store, config_type));
+        let provider_obj = get_opencode_provider_mut(&mut json, name)?;
+        let npm = provider_obj.get("npm").and_then(|n| n.as_str()).map(str::to_string);
+        let Some(options) = provider_obj.get_mut("options").and_then(|o| o.as_object_mut()) else {
+            continue;
+        };
+        options.insert("apiKey".to_string(), serde_json::Value::String(String::new()));
+        for header in [Some(THINKING_BUDGET_HEADER.to_string()), version_header].into_iter().flatten() {
+            set_opencode_header(options, &header, None).map_err(|e| format!("Provider '{}' {}", name, e))?;
+        }
+        if reset_base_url.unwrap_or(false) {
+            if let Some(url) = opencode_template_base_url(&template, name, npm.as_deref()) {
+                options.insert("baseURL".to_string(), serde_json::Value::String(url));
+            }
+        }
+    }
+
+    write_json_pretty(&path, &json)?;
+    opencode_auth::update(&[], &provider_slots)?;
 
-    Ok(model_to_set)
+    let mut changed = false;
+    for (provider, config_type) in OPENCODE_PROVIDER_TYPES.iter() {
+        if provider_slots.iter().any(|slot| slot == provider) {
+            set_opencode_active(&mut store, config_type, None);
+            changed = true;
+        }
+    }
+    if changed {
+        save_store(&store)?;
+    }
+    Ok(())
 }
 
 #[tauri::command]
 fn restore_claude_login() -> Result<(), String> {
-    let mut store = load_store();
+    let (_lock, mut store) = load_store_locked();
     // Deactivate any active Claude config
     for config in &mut store.configs {
         if config.config_type == ConfigType::Claude && config.is_active {
             config.is_active = false;
         }
     }
-    save_store(&store)?;
-    clear_claude_config()
+    clear_config(&ConfigType::Claude, &mut store)?;
+    save_store(&store)
+}
+
+#[tauri::command]
+fn restore_codex_backup() -> Result<(), String> {
+    if !restore_codex_files()? {
+        return Err("No Codex backup found".to_string());
+    }
+    // The user's original files are live again, so no stored Codex config is applied.
+    deactivate_all_of_type(&ConfigType::Codex)
+}
+
+/// Clears Codex files like `clear_codex_config`, but lets the caller keep
+/// either file, e.g. a hand-tuned config.toml while removing the key.
+#[tauri::command]
+fn clear_codex_config_selective(keep_auth: bool, keep_toml: bool) -> Result<(), String> {
+    let (_lock, mut store) = load_store_locked();
+    let stale = store.applied_env.get(&ConfigType::Codex).cloned().unwrap_or_default();
+    let mut plan = Plan::default();
+    clear_codex_files(&mut plan, keep_auth, keep_toml, &stale)?;
+    plan.execute()?;
+    if !keep_auth {
+        store.applied_env.remove(&ConfigType::Codex);
+        save_store(&store)?;
+        deactivate_all_of_type(&ConfigType::Codex)?;
+    }
+    Ok(())
+}
+
+fn deactivate_all_of_type(config_type: &ConfigType) -> Result<(), String> {
+    let (_lock, mut store) = load_store_locked();
+    for config in &mut store.configs {
+        if &config.config_type == config_type && config.is_active {
+            config.is_active = false;
+        }
+    }
+    save_store(&store)
+}
+
+pub use cli::run as run_cli;
+
+/// Sends `tracing` events to stderr, filtered by `RUST_LOG` (e.g.
+/// `RUST_LOG=claude_config_manager_lib=debug`). Only errors by default;
+/// everything else still goes through `eprintln!`.
+pub fn init_logging() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_writer(std::io::stderr)
+        .try_init();
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    tauri::Builder::default()
+    let builder = tauri::Builder::default();
+    // Registered first, as the plugin requires. A second launch, e.g. from a
+    // ccm:// link, hands its URLs to the deep-link plugin here and exits.
+    #[cfg(desktop)]
+    let builder = builder.plugin(tauri_plugin_single_instance::init(|app, _args, _cwd| {
+        deeplink::focus_main_window(app);
+    }));
+    builder
+        .plugin(tauri_plugin_deep_link::init())
+        .setup(|app| {
+            events::init(app.handle().clone());
+            deeplink::start(app.handle().clone());
+            watcher::start(app.handle().clone());
+            failover::start(app.handle().clone());
+            expiry::start(app.handle().clone());
+            rotation::start(app.handle().clone());
+            tray::start(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
+            check_store,
             get_configs,
+            reorder_configs,
+            get_config_by_name,
+            preview_activation,
+            preview_deactivation,
+            get_configs_with_status,
+            search_configs,
+            list_tags,
+            reveal_api_key,
+            get_env_vars_for_config_type,
+            export_env_snippet,
+            validate_config,
             add_config,
             update_config,
+            duplicate_config,
+            import_config_from_json_string,
+            import_shared_configs,
             delete_config,
             activate_config,
+            force_apply_config,
+            record_usage_cost,
             deactivate_config,
+            deactivate_config_with_options,
+            get_history,
+            compact_event_log,
+            set_history_limits,
+            reactivate_previous,
+            migrate_secrets_to_keyring,
+            list_profiles,
+            create_profile,
+            update_profile,
+            delete_profile,
+            activate_profile,
+            activate_config_for_project,
+            deactivate_config_for_project,
+            get_project_activations,
+            detect_existing_configs,
+            import_detected,
+            import_external,
+            import_from_env_file,
+            check_all_configs,
+            cancel_health_check,
+            parse_credential,
+            import_from_continue,
+            verify_apply_success,
+            verify_active_configs,
+            benchmark_configs,
+            fetch_usage,
+            set_usage_note,
+            get_applied_state,
+            open_config_directory,
+            open_config_notes,
+            list_backups,
+            restore_backup,
+            set_backup_retention,
+            get_expiring_configs,
+            get_configs_needing_rotation,
+            get_never_used_configs,
+            get_expiry_warning_days,
+            set_expiry_warning_days,
+            get_opencode_template_content,
+            set_opencode_template_content,
+            reset_opencode_template,
+            reset_opencode_config,
+            get_fallback_chains,
+            set_fallback_chain,
+            get_settings,
+            update_settings,
+            get_failover_settings,
+            set_failover_settings,
+            get_rotation_policy,
+            set_rotation_policy,
+            rotate_now,
+            repair,
             restore_claude_login,
+            restore_codex_backup,
+            clear_codex_config_selective,
             apply_opencode_config,
+            get_opencode_current_model,
+            add_opencode_model,
+            add_opencode_plugin,
+            remove_opencode_model,
+            set_opencode_provider_option,
+            set_cache_key_enabled,
+            set_provider_npm_version,
+            set_model_include_option,
+            set_model_store_option,
+            get_opencode_status,
+            clear_opencode_config,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_support::{add, sandbox};
+
+    #[test]
+    fn switching_configs_keeps_env_vars_the_user_set() {
+        let sandbox = sandbox();
+        let settings = sandbox.home().join(".claude").join("settings.json");
+        fs::create_dir_all(settings.parent().unwrap()).unwrap();
+        fs::write(&settings, r#"{"env":{"DO_NOT_TRACK":"1"}}"#).unwrap();
+        let options = ConfigOptions {
+            timeout_seconds: Some(60),
+            ..Default::default()
+        };
+        let tuned = add("tuned", "claude", "sk-ant-REDACTED", "", options);
+        let plain = add("plain", "claude", "sk-ant-REDACTED", "", ConfigOptions::default());
+
+        activate_config(tuned.id, None).unwrap();
+        activate_config(plain.id.clone(), None).unwrap();
+        let env = read_json_object(&settings).unwrap()["env"].clone();
+        assert_eq!(env["DO_NOT_TRACK"], "1");
+        assert!(env.get("ANTHROPIC_TIMEOUT").is_none());
+
+        deactivate_config(plain.id, None).unwrap();
+        let env = read_json_object(&settings).unwrap()["env"].clone();
+        assert_eq!(env["DO_NOT_TRACK"], "1");
+    }
+
+    #[test]
+    fn codex_retry_var_is_only_removed_when_we_wrote_it() {
+        let sandbox = sandbox();
+        let env_path = sandbox.home().join(".codex").join(".env");
+        fs::create_dir_all(env_path.parent().unwrap()).unwrap();
+        fs::write(&env_path, "OPENAI_MAX_RETRIES=7\n").unwrap();
+        let plain = add("plain", "codex", "sk-proj-plainkey000000000000", "", ConfigOptions::default());
+        activate_config(plain.id.clone(), None).unwrap();
+        deactivate_config(plain.id.clone(), None).unwrap();
+        assert_eq!(fs::read_to_string(&env_path).unwrap(), "OPENAI_MAX_RETRIES=7\n");
+
+        fs::remove_file(&env_path).unwrap();
+        let options = ConfigOptions {
+            max_retries: Some(3),
+            ..Default::default()
+        };
+        let retrying = add("retrying", "codex", "sk-proj-retrykey000000000000", "", options);
+        activate_config(retrying.id, None).unwrap();
+        assert_eq!(fs::read_to_string(&env_path).unwrap(), "OPENAI_MAX_RETRIES=3\n");
+        activate_config(plain.id, None).unwrap();
+        assert!(!env_path.exists());
+    }
+
+    #[test]
+    fn users_azure_key_survives_plain_codex_switches() {
+        let sandbox = sandbox();
+        let codex_dir = sandbox.home().join(".codex");
+        let env_path = codex_dir.join(".env");
+        fs::create_dir_all(&codex_dir).unwrap();
+        fs::write(&env_path, "AZURE_OPENAI_API_KEY=users-own-key\n").unwrap();
+        let plain = add("plain", "codex", "sk-proj-plainkey000000000000", "", ConfigOptions::default());
+        activate_config(plain.id.clone(), None).unwrap();
+        deactivate_config(plain.id.clone(), None).unwrap();
+        assert_eq!(fs::read_to_string(&env_path).unwrap(), "AZURE_OPENAI_API_KEY=users-own-key\n");
+
+        // Our own Azure key replaces it and is removed on the way back, along
+        // with the auth.json of the plain activation before it.
+        fs::remove_file(&env_path).unwrap();
+        let options = ConfigOptions {
+            codex: azure("prod-gpt5"),
+            ..Default::default()
+        };
+        let azure = add("azure", "codex", "azure-key-0000000000000000", "https://contoso.openai.azure.com/openai", options);
+        activate_config(plain.id.clone(), None).unwrap();
+        activate_config(azure.id.clone(), None).unwrap();
+        assert!(!codex_dir.join("auth.json").exists());
+        assert_eq!(fs::read_to_string(&env_path).unwrap(), "AZURE_OPENAI_API_KEY=azure-key-0000000000000000\n");
+        activate_config(plain.id, None).unwrap();
+        assert!(!env_path.exists());
+    }
+
+    fn apply_opencode(claude_id: Option<&str>, codex_id: Option<&str>) -> Result<OpenCodeApplyResult, String> {
+        let id = |id: Option<&str>| id.map(str::to_string);
+        apply_opencode_config(id(claude_id), None, id(codex_id), None, None, None, None, None, None, None)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn opencode_config_is_replaced_atomically() {
+        use std::os::unix::fs::PermissionsExt;
+        let sandbox = sandbox();
+        let path = sandbox.home().join(".config").join("opencode").join("opencode.json");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "{}").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        let claude = add("relay", "claude", "sk-ant-REDACTED", "https://relay.example.com", ConfigOptions::default());
+
+        apply_opencode(Some(&claude.id), None).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert!(!path.with_file_name("opencode.json.pending").exists());
+        let json = read_json_object(&path).unwrap();
+        assert!(json["provider"].as_object().is_some_and(|p| !p.is_empty()));
+    }
+
+    #[test]
+    fn failed_write_rolls_back_earlier_files_and_flags() {
+        let sandbox = sandbox();
+        let codex_dir = sandbox.home().join(".codex");
+        let first = add("first", "codex", "sk-proj-firstkey000000000000", "", ConfigOptions::default());
+        let second = add(
+            "second",
+            "codex",
+            "sk-proj-secondkey00000000000",
+            "https://relay.example.com/v1",
+            ConfigOptions::default(),
+        );
+        activate_config(first.id.clone(), None).unwrap();
+        let auth_before = fs::read(codex_dir.join("auth.json")).unwrap();
+
+        // auth.json is written first; config.toml can't be, as its pending
+        // file can't be created.
+        fs::create_dir_all(codex_dir.join("config.toml.pending")).unwrap();
+        assert!(activate_config(second.id.clone(), None).is_err());
+
+        assert_eq!(fs::read(codex_dir.join("auth.json")).unwrap(), auth_before);
+        let store = load_store();
+        let is_active = |id: &str| store.configs.iter().find(|c| c.id == id).unwrap().is_active;
+        assert!(is_active(&first.id));
+        assert!(!is_active(&second.id));
+    }
+
+    #[test]
+    fn mask_api_key_hides_short_keys_entirely() {
+        assert_eq!(mask_api_key(""), "****");
+        assert_eq!(mask_api_key("sk-12"), "****");
+        assert_eq!(mask_api_key("123456789"), "****");
+        assert_eq!(mask_api_key("1234567890"), "1234567...7890");
+        assert_eq!(mask_api_key("sk-ant-api03-abcdefWXYZ"), "sk-ant-...WXYZ");
+        // Counted in chars, so a multi-byte key can't split a code point.
+        assert_eq!(mask_api_key("ключключключ"), "ключклю...ключ");
+    }
+
+    #[test]
+    fn update_config_keeps_the_key_when_given_its_mask() {
+        let _sandbox = sandbox();
+        let key = "sk-ant-REDACTED";
+        let short = "tiny";
+        let long = add("long", "claude", key, "", ConfigOptions::default());
+        let tiny = add("tiny", "claude", short, "https://relay.example.com", ConfigOptions::default());
+        let stored = |id: &str| load_store().configs.into_iter().find(|c| c.id == id).unwrap();
+        let update = |config: &Config, api_key: &str| {
+            update_config(
+                config.id.clone(),
+                format!("{} renamed", config.name),
+                api_key.to_string(),
+                config.base_url.clone(),
+                config.model.clone(),
+                None,
+                None,
+            )
+        };
+
+        update(&long, &mask_api_key(key)).unwrap();
+        assert_eq!(stored(&long.id).api_key, key);
+        assert_eq!(stored(&long.id).name, "long renamed");
+        update(&tiny, "****").unwrap();
+        assert_eq!(stored(&tiny.id).api_key, short);
+
+        update(&long, "sk-ant-REDACTED").unwrap();
+        assert_eq!(stored(&long.id).api_key, "sk-ant-REDACTED");
+    }
+
+    const TRICKY_VALUE: &str = "it's \"quoted\" $HOME `whoami` \\n\nline2";
+
+    #[test]
+    fn env_snippet_lines_quote_values_literally() {
+        assert_eq!(EnvFormat::Dotenv.line("K", "plain $HOME `x`"), "K='plain $HOME `x`'");
+        assert_eq!(
+            EnvFormat::Dotenv.line("K", TRICKY_VALUE),
+            "K=\"it's \\\"quoted\\\" \\$HOME `whoami` \\\\n\\nline2\""
+        );
+        assert_eq!(
+            EnvFormat::Posix.line("K", TRICKY_VALUE),
+            "export K='it'\\''s \"quoted\" $HOME `whoami` \\n\nline2'"
+        );
+        assert_eq!(
+            EnvFormat::Powershell.line("K", TRICKY_VALUE),
+            "$env:K = 'it''s \"quoted\" $HOME `whoami` \\n\nline2'"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn posix_snippet_round_trips_through_sh() {
+        let script = format!("{}\nprintf '%s' \"$K\"", EnvFormat::Posix.line("K", TRICKY_VALUE));
+        let output = std::process::Command::new("sh").arg("-c").arg(script).output().unwrap();
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), TRICKY_VALUE);
+    }
+
+    #[test]
+    fn export_env_snippet_escapes_override_values() {
+        let _sandbox = sandbox();
+        let options = ConfigOptions {
+            env_overrides: vec![EnvVar {
+                key: "X_NOTE".to_string(),
+                value: TRICKY_VALUE.to_string(),
+            }],
+            ..Default::default()
+        };
+        let config = add("relay", "claude", "sk-ant-REDACTED", "", options);
+        let ids = Some(vec![config.id]);
+        let snippet = |format: &str| export_env_snippet(None, format.to_string(), ids.clone()).unwrap();
+
+        assert!(snippet("dotenv").contains(&EnvFormat::Dotenv.line("X_NOTE", TRICKY_VALUE)));
+        assert!(snippet("posix").contains(&EnvFormat::Posix.line("X_NOTE", TRICKY_VALUE)));
+        assert!(snippet("powershell").contains(&EnvFormat::Powershell.line("X_NOTE", TRICKY_VALUE)));
+        assert!(snippet("posix").starts_with("# claude: relay\nexport ANTHROPIC_AUTH_TOKEN='sk-ant-REDACTED'\n"));
+    }
+
+    fn codex(base_url: &str, model: &str, codex: Option<CodexOptions>) -> Config {
+        Config {
+            id: "x".to_string(),
+            name: "x".to_string(),
+            config_type: ConfigType::Codex,
+            api_key: "sk-proj-codexkey000000000000".to_string(),
+            base_url: base_url.to_string(),
+            model: model.to_string(),
+            options: ConfigOptions {
+                codex,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn merge_codex_toml_keeps_everything_it_doesnt_own() {
+        let existing = r#"# my settings
+model = "o3"
+approval_policy = "on-request"
+
+[mcp_servers.docs]
+command = "npx"
+args = ["-y", "docs-mcp"]
+
+[profiles.fast]
+model = "gpt-5-mini"
+"#;
+        let merged = merge_codex_toml(existing, &codex("https://relay.example.com/v1", "", None)).unwrap();
+        assert!(merged.starts_with("# my settings\n"));
+        let doc: toml_edit::DocumentMut = merged.parse().unwrap();
+        assert_eq!(doc["model"].as_str(), Some("o3"));
+        assert_eq!(doc["approval_policy"].as_str(), Some("on-request"));
+        assert_eq!(doc["model_provider"].as_str(), Some("fox"));
+        assert_eq!(doc["model_providers"]["fox"]["base_url"].as_str(), Some("https://relay.example.com/v1"));
+        assert_eq!(doc["mcp_servers"]["docs"]["command"].as_str(), Some("npx"));
+        assert_eq!(doc["profiles"]["fast"]["model"].as_str(), Some("gpt-5-mini"));
+    }
+
+    #[test]
+    fn codex_config_toml_is_created_from_scratch_or_refused_when_invalid() {
+        let sandbox = sandbox();
+        let path = sandbox.home().join(".codex").join("config.toml");
+        let config = add("relay", "codex", "sk-proj-scratchkey0000000000", "https://relay.example.com/v1", ConfigOptions::default());
+        activate_config(config.id.clone(), None).unwrap();
+        let stored = load_store().configs.into_iter().find(|c| c.id == config.id).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), generate_codex_toml(&stored));
+
+        deactivate_config(config.id.clone(), None).unwrap();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "model = [unclosed").unwrap();
+        let err = activate_config(config.id, None).unwrap_err();
+        assert!(err.contains("is not valid TOML"), "{}", err);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "model = [unclosed");
+        assert!(!path.with_file_name("auth.json").exists());
+    }
+
+    fn overrides(vars: &[(&str, &str)]) -> ConfigOptions {
+        ConfigOptions {
+            env_overrides: vars
+                .iter()
+                .map(|(key, value)| EnvVar {
+                    key: key.to_string(),
+                    value: value.to_string(),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn stale_env_overrides_are_removed_on_switch_edit_and_deactivate() {
+        let sandbox = sandbox();
+        let settings = sandbox.home().join(".claude").join("settings.json");
+        let env = || read_json_object(&settings).unwrap()["env"].clone();
+        let relay = add(
+            "relay",
+            "claude",
+            "sk-ant-REDACTED",
+            "https://relay.example.com",
+            overrides(&[("ANTHROPIC_API_KEY", "relay-key"), ("API_TIMEOUT_MS", "600000")]),
+        );
+        let plain = add("plain", "claude", "sk-ant-REDACTED", "", ConfigOptions::default());
+
+        activate_config(relay.id.clone(), None).unwrap();
+        assert_eq!(env()["API_TIMEOUT_MS"], "600000");
+        activate_config(plain.id, None).unwrap();
+        assert!(env().get("API_TIMEOUT_MS").is_none());
+        assert!(env().get("ANTHROPIC_API_KEY").is_none());
+        assert!(!load_store().applied_env.contains_key(&ConfigType::Claude));
+
+        // Editing the active config drops the overrides it no longer has.
+        activate_config(relay.id.clone(), None).unwrap();
+        update_config(
+            relay.id.clone(),
+            relay.name.clone(),
+            relay.api_key.clone(),
+            relay.base_url.clone(),
+            relay.model.clone(),
+            Some(overrides(&[("API_TIMEOUT_MS", "300000")])),
+            None,
+        )
+        .unwrap();
+        assert_eq!(env()["API_TIMEOUT_MS"], "300000");
+        assert!(env().get("ANTHROPIC_API_KEY").is_none());
+
+        deactivate_config(relay.id, None).unwrap();
+        assert!(env().get("API_TIMEOUT_MS").is_none());
+        assert_eq!(load_store().applied_env.get(&ConfigType::Claude), None);
+    }
+
+    #[test]
+    fn failed_store_write_leaves_the_original_intact() {
+        let _sandbox = sandbox();
+        add("kept", "claude", "sk-ant-REDACTED", "", ConfigOptions::default());
+        let path = get_config_path();
+        let original = fs::read(&path).unwrap();
+        let pending = path.with_file_name("configs.json.pending");
+
+        // The pending file can't be created, so the write fails before the rename.
+        fs::create_dir(&pending).unwrap();
+        let mut store = load_store();
+        store.configs.clear();
+        assert!(save_store(&store).is_err());
+        assert_eq!(fs::read(&path).unwrap(), original);
+        state::reset();
+        assert_eq!(load_store().configs.len(), 1);
+
+        // Leftovers of an interrupted write are simply replaced.
+        fs::remove_dir(&pending).unwrap();
+        fs::write(&pending, "{\"truncated").unwrap();
+        save_store(&store).unwrap();
+        assert!(!pending.exists());
+        state::reset();
+        assert!(load_store().configs.is_empty());
+    }
+
+    #[test]
+    fn opencode_creates_missing_provider_blocks_and_honors_custom_names() {
+        let sandbox = sandbox();
+        let path = sandbox.home().join(".config").join("opencode").join("opencode.json");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, r#"{"provider":{}}"#).unwrap();
+        let claude = add("relay", "claude", "sk-ant-REDACTED", "https://relay.example.com", ConfigOptions::default());
+
+        let result = apply_opencode(Some(&claude.id), None).unwrap();
+        let status = |slot: &str| result.providers.iter().find(|c| c.slot == slot).unwrap().status.clone();
+        assert_eq!(status("claude"), OpenCodeProviderStatus::Created);
+        assert_eq!(status("gemini"), OpenCodeProviderStatus::NotSelected);
+        assert_eq!(status("codex"), OpenCodeProviderStatus::NotSelected);
+        let json = read_json_object(&path).unwrap();
+        let block = &json["provider"]["foxcode-claude"];
+        assert_eq!(block["options"]["apiKey"], "sk-ant-REDACTED");
+        assert_eq!(block["options"]["baseURL"], "https://relay.example.com");
+        assert_eq!(block["npm"], "@ai-sdk/anthropic");
+        assert!(block["models"].as_object().is_some_and(|m| !m.is_empty()));
+        assert!(json["provider"].get("foxcode-oai").is_none());
+
+        let result = apply_opencode_config(
+            Some(claude.id.clone()),
+            None,
+            None,
+            None,
+            Some("my-anthropic".to_string()),
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(result.providers[0].provider, "my-anthropic");
+        assert_eq!(result.providers[0].status, OpenCodeProviderStatus::Created);
+        let json = read_json_object(&path).unwrap();
+        let custom = &json["provider"]["my-anthropic"];
+        assert_eq!(custom["name"], "my-anthropic");
+        assert_eq!(custom["npm"], "@ai-sdk/anthropic");
+        assert_eq!(custom["options"]["apiKey"], "sk-ant-REDACTED");
+        assert_eq!(json["provider"]["foxcode-claude"], *block);
+
+        let result = apply_opencode_config(
+            Some(claude.id),
+            None,
+            None,
+            None,
+            Some("my-anthropic".to_string()),
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(result.providers[0].status, OpenCodeProviderStatus::Updated);
+    }
+
+    #[test]
+    fn base_url_trailing_slashes_are_stripped_on_add_and_update() {
+        assert_eq!(normalize_base_url("https://api.example.com/"), "https://api.example.com");
+        assert_eq!(normalize_base_url(" https://api.example.com/v1// "), "https://api.example.com/v1");
+        assert_eq!(normalize_base_url("https://api.example.com"), "https://api.example.com");
+
+        let _sandbox = sandbox();
+        let config = add("relay", "claude", "sk-ant-REDACTED", "https://api.example.com/", ConfigOptions::default());
+        assert_eq!(config.base_url, "https://api.example.com");
+        update_config(
+            config.id.clone(),
+            config.name,
+            config.api_key,
+            "https://api.example.com/v1/".to_string(),
+            config.model,
+            None,
+            None,
+        )
+        .unwrap();
+        let stored = load_store().configs.into_iter().find(|c| c.id == config.id).unwrap();
+        assert_eq!(stored.base_url, "https://api.example.com/v1");
+    }
+
+    #[test]
+    fn applying_opencode_leaves_model_metadata_alone() {
+        let sandbox = sandbox();
+        let path = sandbox.home().join(".config").join("opencode").join("opencode.json");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let mut before: serde_json::Value = serde_json::from_str(builtin_opencode_template()).unwrap();
+        // A price the user corrected by hand.
+        before["provider"]["foxcode-oai"]["models"]["gpt-5.2"]["cost"]["input"] = serde_json::json!(2.5);
+        fs::write(&path, serde_json::to_string_pretty(&before).unwrap()).unwrap();
+        let codex = add_config(
+            "relay".to_string(),
+            "codex".to_string(),
+            "sk-proj-metadatakey00000000".to_string(),
+            "https://relay.example.com/v1".to_string(),
+            "gpt-5.2".to_string(),
+            None,
+            Some(true),
+            None,
+            Some(true),
+        )
+        .unwrap();
+
+        let result = apply_opencode(None, Some(&codex.id)).unwrap();
+        assert_eq!(result.model.as_deref(), Some("foxcode-oai/gpt-5.2"));
+        let after: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let model = |json: &serde_json::Value| json["provider"]["foxcode-oai"]["models"]["gpt-5.2"].clone();
+        assert_eq!(model(&after), model(&before));
+        assert_eq!(model(&after)["cost"], serde_json::json!({"input": 2.5, "output": 14, "cache_read": 0.175}));
+        let keys = |json: &serde_json::Value| {
+            let provider = json["provider"]["foxcode-oai"].as_object().unwrap();
+            provider.keys().cloned().collect::<Vec<_>>()
+        };
+        assert_eq!(keys(&after), keys(&before));
+        assert_eq!(after["provider"]["foxcode-oai"]["options"]["apiKey"], "sk-proj-metadatakey00000000");
+    }
+
+    #[test]
+    fn profile_blocks_deleting_a_config_it_references() {
+        let _sandbox = sandbox();
+        let claude = add("relay", "claude", "sk-ant-REDACTED", "", ConfigOptions::default());
+        let codex = add("relay", "codex", "sk-proj-profilekey0000000000", "", ConfigOptions::default());
+        let entries = HashMap::from([(ConfigType::Claude, claude.id.clone())]);
+        let profile = create_profile("work".to_string(), entries, false).unwrap();
+
+        assert_eq!(delete_config(claude.id.clone()), Err("Config is used by profile 'work'".to_string()));
+        assert!(load_store().configs.iter().any(|c| c.id == claude.id));
+        delete_config(codex.id).unwrap();
+
+        delete_profile(profile.id).unwrap();
+        delete_config(claude.id.clone()).unwrap();
+        assert!(load_store().configs.iter().all(|c| c.id != claude.id));
+    }
+
+    fn azure(deployment: &str) -> Option<CodexOptions> {
+        Some(CodexOptions {
+            azure: Some(AzureOptions {
+                deployment: deployment.to_string(),
+                api_version: "2025-04-01-preview".to_string(),
+            }),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn codex_toml_snapshots() {
+        let plain = codex("https://relay.example.com/v1", "gpt-5.2", None);
+        let azure = codex("https://contoso.openai.azure.com/openai", "", azure("prod-gpt5"));
+        let existing = include_str!("../tests/fixtures/codex/existing.toml");
+
+        assert_eq!(generate_codex_toml(&plain), include_str!("../tests/fixtures/codex/generated.toml"));
+        assert_eq!(generate_codex_toml(&azure), include_str!("../tests/fixtures/codex/generated_azure.toml"));
+        assert_eq!(
+            merge_codex_toml(existing, &plain).unwrap(),
+            include_str!("../tests/fixtures/codex/merged.toml")
+        );
+        assert_eq!(
+            merge_codex_toml(existing, &azure).unwrap(),
+            include_str!("../tests/fixtures/codex/merged_azure.toml")
+        );
+    }
+
+    #[test]
+    fn opencode_config_follows_xdg_config_home() {
+        let sandbox = sandbox();
+        let xdg = sandbox.dir.path().join("xdg");
+        std::env::set_var("XDG_CONFIG_HOME", &xdg);
+        let claude = add("relay", "claude", "sk-ant-REDACTED", "https://relay.example.com", ConfigOptions::default());
+        let default_path = sandbox.home().join(".config").join("opencode").join("opencode.json");
+
+        apply_opencode(Some(&claude.id), None).unwrap();
+        let path = xdg.join("opencode").join("opencode.json");
+        assert_eq!(get_opencode_config_path(), path);
+        assert_eq!(
+            read_json_object(&path).unwrap()["provider"]["foxcode-claude"]["options"]["apiKey"],
+            "sk-ant-REDACTED"
+        );
+        assert!(!default_path.exists());
+
+        // The spec says to ignore a relative XDG_CONFIG_HOME.
+        std::env::set_var("XDG_CONFIG_HOME", "relative/config");
+        assert_eq!(get_opencode_config_path(), default_path);
+    }
+
+    #[test]
+    fn path_settings_redirect_the_tool_files() {
+        let sandbox = sandbox();
+        let custom = sandbox.dir.path().join("custom");
+        std::env::set_var("CCM_TEST_CUSTOM", &custom);
+        update_settings(Settings {
+            codex_dir: Some(" $CCM_TEST_CUSTOM/codex ".to_string()),
+            opencode_config_path: Some(custom.join("oc.json").to_string_lossy().into_owned()),
+            claude_settings_path: Some("~/portable/claude.json".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(load_store().settings.codex_dir.as_deref(), Some("$CCM_TEST_CUSTOM/codex"));
+        let claude = add("relay", "claude", "sk-ant-REDACTED", "https://relay.example.com", ConfigOptions::default());
+        let codex = add("relay", "codex", "sk-proj-portablekey000000000", "", ConfigOptions::default());
+
+        activate_config(claude.id.clone(), None).unwrap();
+        activate_config(codex.id, None).unwrap();
+        apply_opencode(Some(&claude.id), None).unwrap();
+        let claude_settings = sandbox.home().join("portable").join("claude.json");
+        assert_eq!(
+            read_json_object(&claude_settings).unwrap()["env"]["ANTHROPIC_AUTH_TOKEN"],
+            "sk-ant-REDACTED"
+        );
+        assert!(custom.join("codex").join("auth.json").exists());
+        assert!(custom.join("codex").join("config.toml").exists());
+        assert!(custom.join("oc.json").exists());
+        assert!(!sandbox.home().join(".claude").exists());
+        assert!(!sandbox.home().join(".codex").exists());
+        assert!(!sandbox.home().join(".config").join("opencode").exists());
+
+        // An unset variable falls back to the default location.
+        std::env::remove_var("CCM_TEST_CUSTOM");
+        assert_eq!(get_codex_dir(), sandbox.home().join(".codex"));
+        assert!(update_settings(Settings {
+            codex_dir: Some("relative/codex".to_string()),
+            ..Default::default()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn add_opencode_plugin_rejects_duplicates() {
+        let sandbox = sandbox();
+        let path = sandbox.home().join(".config").join("opencode").join("opencode.json");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, r#"{"plugin":["opencode-wakatime"]}"#).unwrap();
+
+        add_opencode_plugin(" file:///plugins/notify.js ".to_string()).unwrap();
+        assert_eq!(
+            add_opencode_plugin("opencode-wakatime".to_string()),
+            Err("Plugin 'opencode-wakatime' is already registered".to_string())
+        );
+        assert_eq!(
+            add_opencode_plugin("file:///plugins/notify.js".to_string()),
+            Err("Plugin 'file:///plugins/notify.js' is already registered".to_string())
+        );
+        assert!(add_opencode_plugin("  ".to_string()).is_err());
+        assert_eq!(
+            read_json_object(&path).unwrap()["plugin"],
+            serde_json::json!(["opencode-wakatime", "file:///plugins/notify.js"])
+        );
+    }
+
+    #[test]
+    fn opencode_auth_store_keeps_oauth_logins_and_clears_only_our_providers() {
+        let sandbox = sandbox();
+        let auth_path = sandbox.home().join(".local").join("share").join("opencode").join("auth.json");
+        fs::create_dir_all(auth_path.parent().unwrap()).unwrap();
+        let copilot = serde_json::json!({ "type": "oauth", "refresh": "r-token", "access": "a-token", "expires": 1 });
+        let other = serde_json::json!({ "type": "api", "key": "sk-other-tool" });
+        fs::write(&auth_path, serde_json::json!({ "github-copilot": copilot, "openrouter": other }).to_string()).unwrap();
+        update_settings(Settings {
+            opencode_auth_store: true,
+            ..Default::default()
+        })
+        .unwrap();
+        let claude = add("relay", "claude", "sk-ant-REDACTED", "https://relay.example.com", ConfigOptions::default());
+        let codex = add("relay", "codex", "sk-proj-authstorekey00000000", "https://relay.example.com/v1", ConfigOptions::default());
+
+        apply_opencode(Some(&claude.id), Some(&codex.id)).unwrap();
+        let auth = read_json_object(&auth_path).unwrap();
+        assert_eq!(auth["github-copilot"], copilot);
+        assert_eq!(auth["openrouter"], other);
+        assert_eq!(opencode_auth::key(&auth, "foxcode-claude"), Some("sk-ant-REDACTED"));
+        assert_eq!(opencode_auth::key(&auth, "foxcode-oai"), Some("sk-proj-authstorekey00000000"));
+        let opencode = read_json_object(&get_opencode_config_path()).unwrap();
+        assert_eq!(opencode["provider"]["foxcode-claude"]["options"]["apiKey"], "");
+
+        clear_opencode_config(vec!["foxcode-claude".to_string()], None).unwrap();
+        let auth = read_json_object(&auth_path).unwrap();
+        assert!(auth.get("foxcode-claude").is_none());
+        assert_eq!(opencode_auth::key(&auth, "foxcode-oai"), Some("sk-proj-authstorekey00000000"));
+        assert_eq!(auth["github-copilot"], copilot);
+        assert_eq!(auth["openrouter"], other);
+    }
+
+    #[test]
+    fn confirming_a_weak_key_still_checks_for_duplicates() {
+        let _sandbox = sandbox();
+        let add_weak = |force: bool, allow_weak_key: bool| {
+            add_config(
+                "weak".to_string(),
+                "claude".to_string(),
+                "hunter2".to_string(),
+                String::new(),
+                String::new(),
+                None,
+                Some(force),
+                None,
+                Some(allow_weak_key),
+            )
+        };
+        let warning = add_weak(false, false).unwrap_err();
+        assert_eq!(
+            serde_json::to_value(&warning).unwrap(),
+            serde_json::json!({"kind": "weak_key", "reason": "shorter than 20 characters"})
+        );
+        // Forcing past a duplicate doesn't confirm a weak key either.
+        assert!(matches!(add_weak(true, false), Err(AddConfigError::WeakKey(_))));
+        add_weak(false, true).unwrap();
+
+        assert_eq!(
+            add_weak(false, true),
+            Err(AddConfigError::Duplicate {
+                existing: "weak".to_string()
+            })
+        );
+        add_weak(true, true).unwrap();
+        assert_eq!(load_store().configs.len(), 2);
+    }
+}
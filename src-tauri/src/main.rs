@@ -1,5 +1,10 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    claude_config_manager_lib::init_logging();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("--cli") {
+        std::process::exit(claude_config_manager_lib::run_cli(&args[1..]));
+    }
     claude_config_manager_lib::run()
 }
@@ -0,0 +1,130 @@
+//! Upgrades the raw `configs.json` value from older store versions before it
+//! is deserialized. Each migration takes the store from version `i` to `i + 1`;
+//! append new ones to `MIGRATIONS` and `CURRENT_VERSION` follows.
+
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::normalize_base_url;
+
+type Migration = fn(&mut Value) -> Result<(), String>;
+
+const MIGRATIONS: &[Migration] = &[migrate_v0_to_v1, migrate_v1_to_v2];
+
+pub const CURRENT_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// Version 0 is any store written before `version` existed.
+pub fn store_version(value: &Value) -> u32 {
+    value.get("version").and_then(Value::as_u64).unwrap_or(0) as u32
+}
+
+pub fn newer_store_error(version: u32) -> String {
+    format!(
+        "configs.json was written by a newer version of the app (store version {}, this app supports up to {}). Please update the app.",
+        version, CURRENT_VERSION
+    )
+}
+
+/// Runs every migration the store still needs and stamps the new version.
+/// Returns whether anything ran, so the caller knows to write the store back.
+pub fn migrate(value: &mut Value) -> Result<bool, String> {
+    let version = store_version(value);
+    if version > CURRENT_VERSION {
+        return Err(newer_store_error(version));
+    }
+    if version == CURRENT_VERSION {
+        return Ok(false);
+    }
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        migration(value).map_err(|e| format!("Store migration from version {} failed: {}", from, e))?;
+    }
+    value
+        .as_object_mut()
+        .ok_or("configs.json is not a JSON object")?
+        .insert("version".to_string(), Value::from(CURRENT_VERSION));
+    Ok(true)
+}
+
+/// Base URLs are stored without trailing slashes since `add_config` and
+/// `update_config` started normalizing them.
+fn migrate_v0_to_v1(value: &mut Value) -> Result<(), String> {
+    let Some(configs) = value.get_mut("configs").and_then(Value::as_array_mut) else {
+        return Ok(());
+    };
+    for config in configs {
+        if let Some(url) = config.get("base_url").and_then(Value::as_str) {
+            let normalized = normalize_base_url(url);
+            config["base_url"] = Value::String(normalized);
+        }
+    }
+    Ok(())
+}
+
+/// `sort_index` orders configs within a type. Existing configs get indices in
+/// their current (insertion) order, so the list looks the same as before.
+fn migrate_v1_to_v2(value: &mut Value) -> Result<(), String> {
+    let Some(configs) = value.get_mut("configs").and_then(Value::as_array_mut) else {
+        return Ok(());
+    };
+    let mut next: HashMap<String, i64> = HashMap::new();
+    for config in configs {
+        let config_type = config.get("config_type").and_then(Value::as_str).unwrap_or_default().to_string();
+        let index = next.entry(config_type).or_insert(0);
+        if let Some(obj) = config.as_object_mut() {
+            obj.insert("sort_index".to_string(), Value::from(*index));
+        }
+        *index += 1;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ConfigStore;
+
+    /// The same store as each version wrote it.
+    const FIXTURES: [&str; 3] = [
+        include_str!("../tests/fixtures/migrations/v0.json"),
+        include_str!("../tests/fixtures/migrations/v1.json"),
+        include_str!("../tests/fixtures/migrations/v2.json"),
+    ];
+
+    fn fixture(version: usize) -> Value {
+        serde_json::from_str(FIXTURES[version]).unwrap()
+    }
+
+    #[test]
+    fn fixtures_cover_every_version() {
+        assert_eq!(FIXTURES.len(), CURRENT_VERSION as usize + 1);
+    }
+
+    #[test]
+    fn every_version_migrates_to_the_current_fixture() {
+        let current = fixture(CURRENT_VERSION as usize);
+        for version in 0..CURRENT_VERSION as usize {
+            let mut value = fixture(version);
+            assert!(migrate(&mut value).unwrap(), "v{} needed no migration", version);
+            assert_eq!(value, current, "v{} migrated differently", version);
+        }
+        let mut value = current.clone();
+        assert!(!migrate(&mut value).unwrap());
+        assert_eq!(value, current);
+    }
+
+    #[test]
+    fn migrated_store_has_sort_indices_per_type() {
+        let mut value = fixture(0);
+        migrate(&mut value).unwrap();
+        let store: ConfigStore = serde_json::from_value(value).unwrap();
+        let indices: Vec<(&str, i32)> = store.configs.iter().map(|c| (c.id.as_str(), c.sort_index)).collect();
+        assert_eq!(indices, [("c1", 0), ("g1", 0), ("c2", 1)]);
+    }
+
+    #[test]
+    fn newer_store_is_refused() {
+        let mut value = fixture(CURRENT_VERSION as usize);
+        value["version"] = Value::from(CURRENT_VERSION + 1);
+        assert_eq!(migrate(&mut value), Err(newer_store_error(CURRENT_VERSION + 1)));
+    }
+}
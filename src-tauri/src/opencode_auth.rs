@@ -0,0 +1,116 @@
+//! OpenCode's credential store, `auth.json` in its data directory (what
+//! `opencode auth login` writes), keyed by provider id. With
+//! `Settings.opencode_auth_store` keys go here and opencode.json's `apiKey`
+//! fields stay empty, so opencode.json can live in a dotfile repo. Entries
+//! for providers we don't write to (an oauth login, another tool's key) are
+//! never touched.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{get_user_home, write_config_file_atomic};
+
+/// OpenCode uses XDG paths on every platform, like for opencode.json.
+pub fn path() -> PathBuf {
+    std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .unwrap_or_else(|| get_user_home().join(".local").join("share"))
+        .join("opencode")
+        .join("auth.json")
+}
+
+/// The parsed file; empty if it doesn't exist or can't be read.
+pub fn read() -> serde_json::Value {
+    fs::read_to_string(path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_else(|| serde_json::json!({}))
+}
+
+/// The API key stored for `provider`, if it has one (oauth entries don't).
+pub fn key<'a>(auth: &'a serde_json::Value, provider: &str) -> Option<&'a str> {
+    let entry = auth.get(provider)?;
+    if entry.get("type").and_then(|t| t.as_str()) != Some("api") {
+        return None;
+    }
+    entry.get("key")?.as_str().filter(|k| !k.is_empty())
+}
+
+/// `content` with an `api` entry per `set` and the entries named in
+/// `remove` dropped; every other entry is kept as is.
+pub fn merge(content: Option<&str>, set: &[(String, String)], remove: &[String]) -> Result<String, String> {
+    let mut json: serde_json::Value = match content {
+        Some(content) if !content.trim().is_empty() => {
+            serde_json::from_str(content).map_err(|e| format!("Invalid JSON in OpenCode auth.json: {}", e))?
+        }
+        _ => serde_json::json!({}),
+    };
+    let entries = json.as_object_mut().ok_or("OpenCode auth.json is not a JSON object")?;
+    for provider in remove {
+        entries.shift_remove(provider);
+    }
+    for (provider, key) in set {
+        entries.insert(provider.clone(), serde_json::json!({ "type": "api", "key": key }));
+    }
+    serde_json::to_string_pretty(&json).map_err(|e| e.to_string())
+}
+
+/// Applies `merge` to the file on disk. Nothing is written when there's
+/// nothing to change, so a missing file stays missing.
+pub fn update(set: &[(String, String)], remove: &[String]) -> Result<(), String> {
+    let path = path();
+    let current = match fs::read_to_string(&path) {
+        Ok(content) => Some(content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    if set.is_empty() && current.is_none() {
+        return Ok(());
+    }
+    let merged = merge(current.as_deref(), set, remove)?;
+    if current.as_deref() == Some(merged.as_str()) {
+        return Ok(());
+    }
+    write_config_file_atomic(&path, &merged)?;
+    restrict_permissions(&path);
+    Ok(())
+}
+
+/// OpenCode creates the file as 0600; do the same for one we create.
+#[cfg(unix)]
+fn restrict_permissions(path: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+    if let Err(e) = fs::set_permissions(path, fs::Permissions::from_mode(0o600)) {
+        eprintln!("opencode auth: failed to restrict {}: {}", path.display(), e);
+    }
+}
+
+#[cfg(not(unix))]
+fn restrict_permissions(_path: &Path) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_keeps_entries_it_doesnt_manage() {
+        let current = r#"{
+  "github-copilot": { "type": "oauth", "refresh": "r-token", "access": "a-token", "expires": 1 },
+  "foxcode-claude": { "type": "api", "key": "sk-old" }
+}"#;
+        let set = [("foxcode-oai".to_string(), "sk-new".to_string())];
+        let merged: serde_json::Value =
+            serde_json::from_str(&merge(Some(current), &set, &["foxcode-claude".to_string()]).unwrap()).unwrap();
+        assert_eq!(
+            merged,
+            serde_json::json!({
+                "github-copilot": { "type": "oauth", "refresh": "r-token", "access": "a-token", "expires": 1 },
+                "foxcode-oai": { "type": "api", "key": "sk-new" }
+            })
+        );
+        assert_eq!(key(&merged, "foxcode-oai"), Some("sk-new"));
+        assert_eq!(key(&merged, "github-copilot"), None);
+        assert!(merge(Some("[]"), &set, &[]).is_err());
+    }
+}
@@ -0,0 +1,223 @@
+//! Guesses configs from pasted text for "add from clipboard": a bare key, a
+//! dotenv / `export` block, or a JSON snippet such as a settings.json `env`
+//! object. Only parses; the frontend still creates configs via `add_config`.
+
+use serde::Serialize;
+
+use crate::{normalize_base_url, parse_dotenv, ConfigType};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ParsedCredential {
+    pub config_type: ConfigType,
+    pub api_key: String,
+    pub base_url: String,
+    pub model: String,
+    /// The type came from a variable name or an unambiguous key prefix, not
+    /// just a generic `sk-` key.
+    pub confident: bool,
+    /// The variable or JSON field the key was read from, if any.
+    pub source: Option<String>,
+}
+
+/// Key, base URL and model variables each tool reads.
+const KNOWN_VARS: [(ConfigType, &[&str], &str, &str); 3] = [
+    (
+        ConfigType::Claude,
+        &["ANTHROPIC_AUTH_TOKEN", "ANTHROPIC_API_KEY"],
+        "ANTHROPIC_BASE_URL",
+        "ANTHROPIC_MODEL",
+    ),
+    (
+        ConfigType::Gemini,
+        &["GEMINI_API_KEY", "GOOGLE_API_KEY"],
+        "GOOGLE_GEMINI_BASE_URL",
+        "GEMINI_MODEL",
+    ),
+    (ConfigType::Codex, &["OPENAI_API_KEY"], "OPENAI_BASE_URL", "OPENAI_MODEL"),
+];
+
+/// Field names used for the key / base URL in provider-neutral JSON.
+const GENERIC_KEY_FIELDS: [&str; 3] = ["apikey", "api_key", "key"];
+const GENERIC_URL_FIELDS: [&str; 3] = ["baseurl", "base_url", "url"];
+
+/// The type a key's prefix points to, and whether that prefix is specific to
+/// the provider. Plain `sk-` keys are also handed out by most relays.
+fn guess_key_type(key: &str) -> Option<(ConfigType, bool)> {
+    if key.starts_with("sk-ant-") {
+        Some((ConfigType::Claude, true))
+    } else if key.starts_with("AIza") {
+        Some((ConfigType::Gemini, true))
+    } else if key.starts_with("sk-proj-") {
+        Some((ConfigType::Codex, true))
+    } else if key.starts_with("sk-") {
+        Some((ConfigType::Codex, false))
+    } else {
+        None
+    }
+}
+
+/// Every string leaf of a JSON value as (field name, value), depth first.
+fn json_pairs(value: &serde_json::Value, pairs: &mut Vec<(String, String)>) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map {
+                match value {
+                    serde_json::Value::String(s) => pairs.push((key.clone(), s.clone())),
+                    other => json_pairs(other, pairs),
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter().for_each(|item| json_pairs(item, pairs)),
+        _ => {}
+    }
+}
+
+fn looks_like_key(value: &str) -> bool {
+    value.len() >= 16 && !value.contains(char::is_whitespace) && !value.contains("://")
+}
+
+pub fn parse(text: &str) -> Vec<ParsedCredential> {
+    let text = text.trim();
+    let pairs = match serde_json::from_str::<serde_json::Value>(text) {
+        Ok(value) if value.is_object() => {
+            let mut pairs = Vec::new();
+            json_pairs(&value, &mut pairs);
+            pairs
+        }
+        _ if text.lines().any(|line| line.contains('=')) => parse_dotenv(text),
+        _ => {
+            let key = text.trim_matches(|c| c == '"' || c == '\'');
+            return match guess_key_type(key) {
+                Some((config_type, confident)) if looks_like_key(key) => vec![ParsedCredential {
+                    config_type,
+                    api_key: key.to_string(),
+                    base_url: String::new(),
+                    model: String::new(),
+                    confident,
+                    source: None,
+                }],
+                _ => Vec::new(),
+            };
+        }
+    };
+    from_pairs(&pairs)
+}
+
+/// One credential per key variable in `.env` content. A file holds far more
+/// than keys, so only confident matches (known variable names or
+/// provider-specific key prefixes) count.
+pub fn parse_env_file(content: &str) -> Vec<ParsedCredential> {
+    from_pairs(&parse_dotenv(content))
+        .into_iter()
+        .filter(|c| c.confident)
+        .collect()
+}
+
+fn from_pairs(pairs: &[(String, String)]) -> Vec<ParsedCredential> {
+    let value_of = |name: &str| {
+        pairs
+            .iter()
+            .find(|(k, v)| k == name && !v.is_empty())
+            .map(|(_, v)| v.clone())
+    };
+    let generic_url = pairs
+        .iter()
+        .find(|(k, v)| GENERIC_URL_FIELDS.contains(&k.to_lowercase().as_str()) && v.contains("://"))
+        .map(|(_, v)| v.clone());
+
+    let mut found: Vec<ParsedCredential> = Vec::new();
+    for (name, value) in pairs {
+        let value = value.trim();
+        if !looks_like_key(value) || found.iter().any(|f| f.api_key == value) {
+            continue;
+        }
+        let known = KNOWN_VARS.iter().find(|(_, keys, _, _)| keys.contains(&name.as_str()));
+        let (config_type, confident) = match (known, guess_key_type(value)) {
+            (Some((config_type, ..)), _) => (config_type.clone(), true),
+            (None, Some(guess)) => guess,
+            (None, None) if GENERIC_KEY_FIELDS.contains(&name.to_lowercase().as_str()) => {
+                (ConfigType::Codex, false)
+            }
+            (None, None) => continue,
+        };
+        let (url_var, model_var) = KNOWN_VARS
+            .iter()
+            .find(|(t, ..)| *t == config_type)
+            .map(|(_, _, url, model)| (*url, *model))
+            .unwrap_or_default();
+        found.push(ParsedCredential {
+            base_url: value_of(url_var)
+                .or_else(|| generic_url.clone())
+                .map(|url| normalize_base_url(&url))
+                .unwrap_or_default(),
+            model: value_of(model_var).unwrap_or_default(),
+            config_type,
+            api_key: value.to_string(),
+            confident,
+            source: Some(name.clone()),
+        });
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(found: &[ParsedCredential]) -> Vec<(ConfigType, &str, bool)> {
+        found.iter().map(|c| (c.config_type.clone(), c.api_key.as_str(), c.confident)).collect()
+    }
+
+    #[test]
+    fn bare_keys_are_typed_by_prefix() {
+        let cases = [
+            ("sk-ant-REDACTED", ConfigType::Claude, true),
+            ("AIzaSyAbcdefghijklmnopqrstuv", ConfigType::Gemini, true),
+            ("sk-proj-abcdefghijklmnopqrst", ConfigType::Codex, true),
+            ("sk-abcdefghijklmnopqrstuvwx", ConfigType::Codex, false),
+        ];
+        for (key, config_type, confident) in cases {
+            assert_eq!(summary(&parse(&format!("  \"{}\"\n", key))), [(config_type, key, confident)], "{}", key);
+        }
+        assert!(parse("sk-short").is_empty());
+        assert!(parse("not a key at all").is_empty());
+    }
+
+    #[test]
+    fn env_blocks_use_variable_names_and_urls() {
+        let found = parse(
+            "export ANTHROPIC_AUTH_TOKEN=relay-token-0123456789\n\
+             export ANTHROPIC_BASE_URL=https://relay.example.com/\n\
+             export ANTHROPIC_MODEL=claude-sonnet-4-5\n",
+        );
+        assert_eq!(summary(&found), [(ConfigType::Claude, "relay-token-0123456789", true)]);
+        assert_eq!(found[0].base_url, "https://relay.example.com");
+        assert_eq!(found[0].model, "claude-sonnet-4-5");
+        assert_eq!(found[0].source.as_deref(), Some("ANTHROPIC_AUTH_TOKEN"));
+    }
+
+    #[test]
+    fn json_snippets_are_searched_at_any_depth() {
+        let found = parse(r#"{"env": {"GEMINI_API_KEY": "AIzaSyAbcdefghijklmnopqrstuv"}, "baseUrl": "https://g.example.com"}"#);
+        assert_eq!(summary(&found), [(ConfigType::Gemini, "AIzaSyAbcdefghijklmnopqrstuv", true)]);
+        assert_eq!(found[0].base_url, "https://g.example.com");
+    }
+
+    #[test]
+    fn env_files_skip_comments_and_unconfident_keys() {
+        let content = "# Claude relay\n\
+                       ANTHROPIC_AUTH_TOKEN=\"relay-token-0123456789\"\n\
+                       # GEMINI_API_KEY=AIzaSyCommentedOutKey000000\n\
+                       \n\
+                       SESSION_SECRET=abcdefghijklmnopqrstuvwxyz\n\
+                       RELAY_TOKEN=sk-abcdefghijklmnopqrstuvwx\n\
+                       export OPENAI_API_KEY='sk-proj-abcdefghijklmnopqrst'\n";
+        assert_eq!(
+            summary(&parse_env_file(content)),
+            [
+                (ConfigType::Claude, "relay-token-0123456789", true),
+                (ConfigType::Codex, "sk-proj-abcdefghijklmnopqrst", true),
+            ]
+        );
+    }
+}
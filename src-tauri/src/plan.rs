@@ -0,0 +1,431 @@
+//! File changes worked out before anything is written. `apply_config` and
+//! `clear_config` build a `Plan` and execute it; `preview_activation` and
+//! `preview_deactivation` describe the very same plan instead, so a preview
+//! shows exactly what the switch would do.
+
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{mask_api_key, parse_dotenv, write_config_file_atomic, written};
+
+/// Lines of unchanged context around each hunk of a diff.
+const DIFF_CONTEXT: usize = 3;
+const SHORT_DIFF_LINES: usize = 30;
+
+#[derive(Debug, Default)]
+pub struct Plan {
+    /// Final content per file, `None` to remove it, in first-touched order.
+    changes: Vec<(PathBuf, Option<String>)>,
+}
+
+impl Plan {
+    /// The file as it will be once the steps planned so far have run; `None`
+    /// if it won't exist.
+    pub fn read(&self, path: &Path) -> Result<Option<String>, String> {
+        if let Some((_, content)) = self.changes.iter().find(|(p, _)| p == path) {
+            return Ok(content.clone());
+        }
+        match fs::read_to_string(path) {
+            Ok(content) => Ok(Some(content)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn exists(&self, path: &Path) -> bool {
+        match self.changes.iter().find(|(p, _)| p == path) {
+            Some((_, content)) => content.is_some(),
+            None => path.exists(),
+        }
+    }
+
+    pub fn write(&mut self, path: &Path, content: String) {
+        self.set(path, Some(content));
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        self.set(path, None);
+    }
+
+    fn set(&mut self, path: &Path, content: Option<String>) {
+        match self.changes.iter_mut().find(|(p, _)| p == path) {
+            Some(entry) => entry.1 = content,
+            None => self.changes.push((path.to_path_buf(), content)),
+        }
+    }
+
+    /// Writes (through `write_config_file_atomic`) and removes the files in
+    /// order. Files whose content wouldn't change are left untouched.
+    pub fn execute(&self) -> Result<(), String> {
+        for (path, content) in &self.changes {
+            match content {
+                Some(content) => {
+                    if fs::read_to_string(path).is_ok_and(|current| current == *content) {
+                        continue;
+                    }
+                    write_config_file_atomic(path, content)?;
+                }
+                None if path.exists() => {
+                    fs::remove_file(path).map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+                    written::record(path, None);
+                }
+                None => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Fails with a `written::EXTERNALLY_MODIFIED` error if the plan would
+    /// change a file edited by hand since we last wrote it. The error lists
+    /// each such file with the diff executing would apply to it.
+    pub fn check_external_edits(&self, secrets: &[String]) -> Result<(), String> {
+        let edited = Plan {
+            changes: self
+                .changes
+                .iter()
+                .filter(|(path, _)| written::modified(path))
+                .cloned()
+                .collect(),
+        };
+        let files = edited.describe(secrets).files;
+        if files.is_empty() {
+            return Ok(());
+        }
+        let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+        let diffs: Vec<String> = files.iter().map(|f| short_diff(&f.diff)).collect();
+        Err(format!(
+            "{}: {} changed outside the app since it was last written\n{}",
+            written::EXTERNALLY_MODIFIED,
+            paths.join(", "),
+            diffs.join("\n")
+        ))
+    }
+
+    /// What executing the plan would change. `secrets` (typically every
+    /// stored key) are masked wherever they appear, as are values of
+    /// variables and JSON fields whose names look like keys.
+    pub fn describe(&self, secrets: &[String]) -> ActivationPlan {
+        let mut files = Vec::new();
+        let mut env = Vec::new();
+        for (path, after) in &self.changes {
+            let before = fs::read_to_string(path).ok();
+            let action = match (&before, after) {
+                (None, Some(_)) => FileAction::Create,
+                (Some(old), Some(new)) if old != new => FileAction::Overwrite,
+                (Some(_), None) => FileAction::Delete,
+                _ => continue,
+            };
+            let (old, new) = (before.as_deref().unwrap_or(""), after.as_deref().unwrap_or(""));
+            let mut hidden = secrets.to_vec();
+            hidden.extend(secret_values(old));
+            hidden.extend(secret_values(new));
+            let mask = |text: &str| mask_secrets(text, &hidden);
+
+            let old_vars = env_vars(path, old);
+            let new_vars = env_vars(path, new);
+            for (name, old_value) in &old_vars {
+                let new_value = new_vars.iter().find(|(k, _)| k == name).map(|(_, v)| v);
+                let action = match new_value {
+                    None => EnvAction::Delete,
+                    Some(v) if v != old_value => EnvAction::Change,
+                    Some(_) => continue,
+                };
+                env.push(EnvChange {
+                    file: path.display().to_string(),
+                    name: name.clone(),
+                    action,
+                    old: Some(mask(old_value)),
+                    new: new_value.map(|v| mask(v)),
+                });
+            }
+            for (name, new_value) in new_vars.iter().filter(|(k, _)| !old_vars.iter().any(|(o, _)| o == k)) {
+                env.push(EnvChange {
+                    file: path.display().to_string(),
+                    name: name.clone(),
+                    action: EnvAction::Set,
+                    old: None,
+                    new: Some(mask(new_value)),
+                });
+            }
+
+            files.push(FileChange {
+                path: path.display().to_string(),
+                action,
+                diff: mask(&unified_diff(&path.display().to_string(), old, new)),
+            });
+        }
+        ActivationPlan { files, env }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ActivationPlan {
+    pub files: Vec<FileChange>,
+    /// Variable-level view of the env files among `files` (Claude's
+    /// settings.json `env`, `.env` files).
+    pub env: Vec<EnvChange>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileAction {
+    Create,
+    Overwrite,
+    Delete,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileChange {
+    pub path: String,
+    pub action: FileAction,
+    pub diff: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EnvAction {
+    Set,
+    Change,
+    Delete,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EnvChange {
+    pub file: String,
+    pub name: String,
+    pub action: EnvAction,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// The variables an env file defines: `.env` lines, or the `env` object of a
+/// JSON settings file. Empty for anything else.
+fn env_vars(path: &Path, content: &str) -> Vec<(String, String)> {
+    if path.file_name().is_some_and(|n| n == ".env") {
+        return parse_dotenv(content);
+    }
+    let Ok(json) = serde_json::from_str::<serde_json::Value>(content) else {
+        return Vec::new();
+    };
+    json.get("env")
+        .and_then(|env| env.as_object())
+        .map(|env| {
+            env.iter()
+                .filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Variables `before` defines that `after` no longer does, for an env file
+/// as understood by `env_vars`.
+pub fn removed_vars(path: &Path, before: Option<&str>, after: Option<&str>) -> Vec<String> {
+    let after = env_vars(path, after.unwrap_or(""));
+    env_vars(path, before.unwrap_or(""))
+        .into_iter()
+        .map(|(name, _)| name)
+        .filter(|name| !after.iter().any(|(k, _)| k == name))
+        .collect()
+}
+
+fn looks_secret(name: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+    ["KEY", "TOKEN", "SECRET"].iter().any(|s| name.contains(s))
+}
+
+/// Values stored under key-like names, from JSON (any depth) or dotenv text.
+fn secret_values(content: &str) -> Vec<String> {
+    fn walk(value: &serde_json::Value, found: &mut Vec<String>) {
+        match value {
+            serde_json::Value::Object(map) => {
+                for (k, v) in map {
+                    match v.as_str() {
+                        Some(s) if looks_secret(k) => found.push(s.to_string()),
+                        _ => walk(v, found),
+                    }
+                }
+            }
+            serde_json::Value::Array(items) => items.iter().for_each(|v| walk(v, found)),
+            _ => {}
+        }
+    }
+    let mut found = Vec::new();
+    match serde_json::from_str::<serde_json::Value>(content) {
+        Ok(json) => walk(&json, &mut found),
+        Err(_) => found.extend(
+            parse_dotenv(content)
+                .into_iter()
+                .filter(|(k, _)| looks_secret(k))
+                .map(|(_, v)| v),
+        ),
+    }
+    found
+}
+
+fn mask_secrets(text: &str, secrets: &[String]) -> String {
+    let mut secrets: Vec<&String> = secrets.iter().filter(|s| s.len() >= 4).collect();
+    // Longest first, so a key that contains another is masked whole.
+    secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+    let mut text = text.to_string();
+    for secret in secrets {
+        text = text.replace(secret.as_str(), &mask_api_key(secret));
+    }
+    text
+}
+
+/// The first `SHORT_DIFF_LINES` lines of a diff, for an error message.
+fn short_diff(diff: &str) -> String {
+    let lines: Vec<&str> = diff.lines().collect();
+    if lines.len() <= SHORT_DIFF_LINES {
+        return diff.to_string();
+    }
+    format!(
+        "{}\n... {} more lines",
+        lines[..SHORT_DIFF_LINES].join("\n"),
+        lines.len() - SHORT_DIFF_LINES
+    )
+}
+
+/// A unified diff of two small texts, line by line.
+fn unified_diff(name: &str, old: &str, new: &str) -> String {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    // lcs[i][j]: length of the longest common subsequence of a[i..] and b[j..].
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    // (tag, index into a, index into b) per output line.
+    let mut ops = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            ops.push((' ', i, j));
+            i += 1;
+            j += 1;
+        } else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push(('-', i, j));
+            i += 1;
+        } else {
+            ops.push(('+', i, j));
+            j += 1;
+        }
+    }
+
+    let changed: Vec<usize> = (0..ops.len()).filter(|&k| ops[k].0 != ' ').collect();
+    let mut out = format!("--- {}\n+++ {}\n", name, name);
+    let mut k = 0;
+    while k < changed.len() {
+        let start = changed[k].saturating_sub(DIFF_CONTEXT);
+        let mut last = changed[k];
+        while k + 1 < changed.len() && changed[k + 1] <= last + 2 * DIFF_CONTEXT {
+            k += 1;
+            last = changed[k];
+        }
+        k += 1;
+        let hunk = &ops[start..(last + DIFF_CONTEXT + 1).min(ops.len())];
+        let old_count = hunk.iter().filter(|op| op.0 != '+').count();
+        let new_count = hunk.iter().filter(|op| op.0 != '-').count();
+        let line_no = |index: usize, count: usize| if count == 0 { index } else { index + 1 };
+        out.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            line_no(hunk[0].1, old_count),
+            old_count,
+            line_no(hunk[0].2, new_count),
+            new_count
+        ));
+        for &(tag, i, j) in hunk {
+            let line = if tag == '+' { b[j] } else { a[i] };
+            out.push(tag);
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::sandbox;
+
+    fn numbered(lines: std::ops::RangeInclusive<u32>) -> String {
+        lines.map(|n| format!("{}\n", n)).collect()
+    }
+
+    #[test]
+    fn unified_diff_shows_a_change_with_context() {
+        let old = numbered(1..=10);
+        let new = old.replace("5\n", "five\n");
+        assert_eq!(
+            unified_diff("f", &old, &new),
+            "--- f\n+++ f\n@@ -2,7 +2,7 @@\n 2\n 3\n 4\n-5\n+five\n 6\n 7\n 8\n"
+        );
+    }
+
+    #[test]
+    fn unified_diff_splits_distant_changes_into_hunks() {
+        let old = numbered(1..=20);
+        let new: String = old
+            .lines()
+            .filter(|l| *l != "18")
+            .map(|l| if l == "2" { "two\n".to_string() } else { format!("{}\n", l) })
+            .collect();
+        let diff = unified_diff("f", &old, &new);
+        let hunks: Vec<&str> = diff.lines().filter(|l| l.starts_with("@@")).collect();
+        assert_eq!(hunks, ["@@ -1,5 +1,5 @@", "@@ -15,6 +15,5 @@"]);
+    }
+
+    #[test]
+    fn unified_diff_of_a_new_file_starts_at_zero() {
+        assert_eq!(unified_diff("f", "", "a\nb\n"), "--- f\n+++ f\n@@ -0,0 +1,2 @@\n+a\n+b\n");
+        assert_eq!(unified_diff("f", "same\n", "same\n"), "--- f\n+++ f\n");
+    }
+
+    #[test]
+    fn describe_matches_what_execute_leaves_behind() {
+        let sandbox = sandbox();
+        let dir = sandbox.dir.path();
+        let (created, changed, removed, same) = (dir.join("new.json"), dir.join(".env"), dir.join("old"), dir.join("same"));
+        fs::write(&changed, "# keep\nAPI_KEY=sk-old-secret-value\nMODE=a\n").unwrap();
+        fs::write(&removed, "x").unwrap();
+        fs::write(&same, "unchanged").unwrap();
+
+        let mut plan = Plan::default();
+        plan.write(&created, "{\"env\": {\"A\": \"1\"}}".to_string());
+        plan.write(&changed, "# keep\nAPI_KEY=sk-new-secret-value\n".to_string());
+        plan.remove(&removed);
+        plan.write(&same, "unchanged".to_string());
+        plan.remove(&dir.join("missing"));
+
+        let description = plan.describe(&[]);
+        let files: Vec<(String, FileAction)> =
+            description.files.iter().map(|f| (f.path.clone(), f.action)).collect();
+        let path = |p: &Path| p.display().to_string();
+        assert_eq!(
+            files,
+            [
+                (path(&created), FileAction::Create),
+                (path(&changed), FileAction::Overwrite),
+                (path(&removed), FileAction::Delete),
+            ]
+        );
+        let env: Vec<(&str, EnvAction)> = description.env.iter().map(|e| (e.name.as_str(), e.action)).collect();
+        assert_eq!(env, [("A", EnvAction::Set), ("API_KEY", EnvAction::Change), ("MODE", EnvAction::Delete)]);
+        assert!(!description.files[1].diff.contains("secret-value"));
+
+        plan.execute().unwrap();
+        assert_eq!(fs::read_to_string(&created).unwrap(), "{\"env\": {\"A\": \"1\"}}");
+        assert_eq!(fs::read_to_string(&changed).unwrap(), "# keep\nAPI_KEY=sk-new-secret-value\n");
+        assert!(!removed.exists());
+        assert_eq!(fs::read_to_string(&same).unwrap(), "unchanged");
+        assert!(plan.describe(&[]).files.is_empty());
+    }
+}
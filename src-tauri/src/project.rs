@@ -0,0 +1,197 @@
+//! Per-project activation: writes a config's env vars into files inside a
+//! project directory rather than the user-wide tool files. Claude reads
+//! `.claude/settings.local.json`; Gemini picks up the project's `.env`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{
+    expected_applied_values, read_json_object, require_api_key, write_config_file_atomic, write_json_pretty, Config,
+    ConfigType,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProjectActivation {
+    pub config_id: String,
+    pub config_type: ConfigType,
+    pub project_dir: String,
+    /// The file that was written, so cleanup doesn't depend on how targets
+    /// are chosen in later versions.
+    pub target_file: String,
+    /// Exactly these keys are removed again on deactivation.
+    pub written_keys: Vec<String>,
+}
+
+fn target_file(config_type: &ConfigType, project_dir: &Path) -> Result<PathBuf, String> {
+    match config_type {
+        ConfigType::Claude => Ok(project_dir.join(".claude").join("settings.local.json")),
+        ConfigType::Gemini => Ok(project_dir.join(".env")),
+        _ => Err("Per-project activation is only supported for Claude and Gemini configs".to_string()),
+    }
+}
+
+/// Writes `config` into `project_dir`. `stale` lists keys a previous
+/// activation of the same type wrote there, which are dropped if this config
+/// doesn't set them; keys the project had before any activation are never
+/// removed.
+pub fn apply(config: &Config, project_dir: &str, stale: &[String]) -> Result<ProjectActivation, String> {
+    let dir = Path::new(project_dir);
+    if !dir.is_dir() {
+        return Err(format!("Project directory '{}' does not exist", project_dir));
+    }
+    let path = target_file(&config.config_type, dir)?;
+    require_api_key(config)?;
+
+    let set: Vec<(String, String)> = expected_applied_values(config)
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, value?)))
+        .collect();
+    write_vars(&path, &set, stale)?;
+
+    Ok(ProjectActivation {
+        config_id: config.id.clone(),
+        config_type: config.config_type.clone(),
+        project_dir: project_dir.to_string(),
+        target_file: path.display().to_string(),
+        written_keys: set.into_iter().map(|(key, _)| key).collect(),
+    })
+}
+
+pub fn clear(activation: &ProjectActivation) -> Result<(), String> {
+    write_vars(Path::new(&activation.target_file), &[], &activation.written_keys)
+}
+
+fn write_vars(path: &Path, set: &[(String, String)], remove: &[String]) -> Result<(), String> {
+    if !path.exists() && set.is_empty() {
+        return Ok(());
+    }
+    if path.extension().is_some_and(|ext| ext == "json") {
+        write_settings_env(path, set, remove)
+    } else {
+        write_dotenv(path, set, remove)
+    }
+}
+
+fn write_settings_env(path: &Path, set: &[(String, String)], remove: &[String]) -> Result<(), String> {
+    let mut json = read_json_object(path)?;
+    let obj = json
+        .as_object_mut()
+        .ok_or_else(|| format!("{} is not a JSON object", path.display()))?;
+    let env = obj
+        .entry("env".to_string())
+        .or_insert_with(|| serde_json::json!({}))
+        .as_object_mut()
+        .ok_or_else(|| format!("'env' in {} is not an object", path.display()))?;
+    for key in remove {
+        env.shift_remove(key);
+    }
+    for (key, value) in set {
+        env.insert(key.clone(), serde_json::Value::String(value.clone()));
+    }
+    if env.is_empty() {
+        obj.shift_remove("env");
+    }
+    write_json_pretty(path, &json)
+}
+
+fn write_dotenv(path: &Path, set: &[(String, String)], remove: &[String]) -> Result<(), String> {
+    let content = if path.exists() {
+        fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+    } else {
+        String::new()
+    };
+    write_config_file_atomic(path, &merge_dotenv(&content, set, remove))
+}
+
+fn dotenv_key(line: &str) -> Option<&str> {
+    let trimmed = line.trim_start();
+    if trimmed.starts_with('#') {
+        return None;
+    }
+    let trimmed = trimmed.strip_prefix("export ").unwrap_or(trimmed);
+    let (key, _) = trimmed.split_once('=')?;
+    Some(key.trim())
+}
+
+/// Rewrites only the lines for keys in `set` or `remove`. Every other line,
+/// comments included, is kept byte for byte along with its line ending; new
+/// keys are appended using the file's existing line ending.
+fn merge_dotenv(content: &str, set: &[(String, String)], remove: &[String]) -> String {
+    let eol = if content.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut out = String::new();
+    let mut written = HashSet::new();
+
+    for line in content.split_inclusive('\n') {
+        let body = line.trim_end_matches(['\r', '\n']);
+        if let Some(key) = dotenv_key(body) {
+            if let Some((key, value)) = set.iter().find(|(k, _)| k == key) {
+                // Later duplicates of a key we own are dropped.
+                if written.insert(key.as_str()) {
+                    out.push_str(&format!("{}={}", key, value));
+                    out.push_str(&line[body.len()..]);
+                }
+                continue;
+            }
+            if remove.iter().any(|r| r == key) {
+                continue;
+            }
+        }
+        out.push_str(line);
+    }
+
+    let mut missing = set.iter().filter(|(k, _)| !written.contains(k.as_str())).peekable();
+    if missing.peek().is_some() && !out.is_empty() && !out.ends_with('\n') {
+        out.push_str(eol);
+    }
+    for (key, value) in missing {
+        out.push_str(&format!("{}={}{}", key, value, eol));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::sandbox;
+
+    fn gemini(base_url: &str) -> Config {
+        Config {
+            id: "g".to_string(),
+            name: "g".to_string(),
+            config_type: ConfigType::Gemini,
+            api_key: "AIzaSyTestKey0000000000000000000000000".to_string(),
+            base_url: base_url.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn apply_keeps_project_keys_it_never_wrote() {
+        let sandbox = sandbox();
+        let dir = sandbox.dir.path().join("project");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(".env"), "GOOGLE_GEMINI_BASE_URL=https://proxy.internal\n").unwrap();
+
+        let activation = apply(&gemini(""), dir.to_str().unwrap(), &[]).unwrap();
+        let content = fs::read_to_string(dir.join(".env")).unwrap();
+        assert!(content.contains("GOOGLE_GEMINI_BASE_URL=https://proxy.internal"));
+        assert!(content.contains("GEMINI_API_KEY="));
+        assert_eq!(activation.written_keys, ["GEMINI_API_KEY"]);
+    }
+
+    #[test]
+    fn apply_drops_stale_keys_from_a_previous_activation() {
+        let sandbox = sandbox();
+        let dir = sandbox.dir.path().join("project");
+        fs::create_dir_all(&dir).unwrap();
+        let project_dir = dir.to_str().unwrap();
+
+        let first = apply(&gemini("https://relay.example.com"), project_dir, &[]).unwrap();
+        apply(&gemini(""), project_dir, &first.written_keys).unwrap();
+        let content = fs::read_to_string(dir.join(".env")).unwrap();
+        assert!(!content.contains("GOOGLE_GEMINI_BASE_URL"));
+        assert!(content.contains("GEMINI_API_KEY="));
+    }
+}
@@ -0,0 +1,183 @@
+//! Opt-in key rotation: a `RotationPolicy` per type cycles the active config
+//! through a ring of config ids, either every `Hours(n)` or once per app
+//! start, for relays that enforce a daily quota per key. Entries that fail a
+//! connection check are skipped. Activating a config outside the ring by
+//! hand pauses the policy until it's saved again.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+use crate::health::{self, HealthStatus};
+use crate::{activate_for_tool, load_store, load_store_locked, now_millis, save_store, state, ConfigStore, ConfigType};
+
+const CONFIG_ROTATED: &str = "config-rotated";
+/// Gives the webview time to register its listeners before an on-start
+/// rotation.
+const STARTUP_DELAY: Duration = Duration::from_secs(3);
+/// How often the policies are re-read, like failover's.
+const TICK: Duration = Duration::from_secs(30);
+/// How long to wait before trying again when no entry in the ring was
+/// reachable, so a relay outage doesn't get a check every tick.
+const RETRY_DELAY: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RotationInterval {
+    Hours(u32),
+    OnStart,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RotationPolicy {
+    /// The ring, in rotation order.
+    pub config_ids: Vec<String>,
+    pub interval: RotationInterval,
+    #[serde(default)]
+    pub enabled: bool,
+    /// Set when a config outside the ring was activated by hand; cleared by
+    /// saving the policy again.
+    #[serde(default)]
+    pub paused: bool,
+    /// Milliseconds since the epoch. Kept by the app; ignored when saving.
+    #[serde(default)]
+    pub last_rotated_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RotationEvent {
+    pub config_type: ConfigType,
+    pub from_id: Option<String>,
+    pub to_id: String,
+    pub to_name: String,
+}
+
+pub fn start(app: AppHandle) {
+    std::thread::spawn(move || {
+        std::thread::sleep(STARTUP_DELAY);
+        let mut failed: HashMap<ConfigType, Instant> = HashMap::new();
+        let mut first = true;
+        loop {
+            let store = load_store();
+            for (config_type, policy) in &store.rotations {
+                if !policy.enabled || policy.paused || !due(policy, first) {
+                    continue;
+                }
+                if failed.get(config_type).is_some_and(|t| t.elapsed() < RETRY_DELAY) {
+                    continue;
+                }
+                match rotate(config_type, false) {
+                    Ok(Some(event)) => {
+                        failed.remove(config_type);
+                        emit(&app, event);
+                    }
+                    // Busy with a user activation; try again next tick.
+                    Ok(None) => {}
+                    Err(e) => {
+                        eprintln!("rotation: {}", e);
+                        failed.insert(config_type.clone(), Instant::now());
+                    }
+                }
+            }
+            first = false;
+            std::thread::sleep(TICK);
+        }
+    });
+}
+
+fn due(policy: &RotationPolicy, app_start: bool) -> bool {
+    match policy.interval {
+        RotationInterval::OnStart => app_start,
+        RotationInterval::Hours(hours) => {
+            let interval = u64::from(hours) * 60 * 60 * 1000;
+            policy.last_rotated_at.is_none_or(|t| now_millis().saturating_sub(t) >= interval)
+        }
+    }
+}
+
+pub fn emit(app: &AppHandle, event: RotationEvent) {
+    if let Err(e) = app.emit(CONFIG_ROTATED, event) {
+        eprintln!("rotation: failed to emit {}: {}", CONFIG_ROTATED, e);
+    }
+}
+
+/// Activates the first reachable config after the active one in the ring.
+/// With `wait` unset, gives up (`Ok(None)`) instead of waiting for an
+/// activation the user started.
+pub fn rotate(config_type: &ConfigType, wait: bool) -> Result<Option<RotationEvent>, String> {
+    let store = load_store();
+    let policy = store
+        .rotations
+        .get(config_type)
+        .ok_or_else(|| format!("No rotation policy for {}", String::from(config_type.clone())))?;
+    let active = store.configs.iter().find(|c| &c.config_type == config_type && c.is_active);
+    let ring = &policy.config_ids;
+    // The configs after the active one come first, then wrap around.
+    let start = active
+        .and_then(|a| ring.iter().position(|id| *id == a.id))
+        .map_or(0, |i| i + 1);
+    let candidates = ring[start..]
+        .iter()
+        .chain(&ring[..start])
+        .filter(|id| active.is_none_or(|a| a.id != **id))
+        .filter_map(|id| store.configs.iter().find(|c| c.id == *id));
+    for candidate in candidates {
+        let check = health::check_config(candidate, health::DEFAULT_TIMEOUT);
+        if check.status != HealthStatus::Reachable {
+            eprintln!(
+                "rotation: skipping '{}': {}",
+                candidate.name,
+                check.error.unwrap_or_else(|| format!("{:?}", check.status))
+            );
+            continue;
+        }
+        let _lock = if wait {
+            state::lock()
+        } else {
+            match state::try_lock() {
+                Some(lock) => lock,
+                None => return Ok(None),
+            }
+        };
+        // The user may have switched while the checks ran.
+        let current = load_store();
+        let still_active = current.configs.iter().find(|c| &c.config_type == config_type && c.is_active);
+        if still_active.map(|c| &c.id) != active.map(|c| &c.id) {
+            return Ok(None);
+        }
+        activate_for_tool(&candidate.id, false)
+            .map_err(|e| format!("Failed to activate '{}': {}", candidate.name, e))?;
+        let (_lock, mut store) = load_store_locked();
+        if let Some(policy) = store.rotations.get_mut(config_type) {
+            policy.last_rotated_at = Some(now_millis());
+        }
+        save_store(&store)?;
+        return Ok(Some(RotationEvent {
+            config_type: config_type.clone(),
+            from_id: active.map(|c| c.id.clone()),
+            to_id: candidate.id.clone(),
+            to_name: candidate.name.clone(),
+        }));
+    }
+    Err(format!(
+        "No reachable config to rotate to for {}",
+        String::from(config_type.clone())
+    ))
+}
+
+/// Pauses the type's policy if `id` was activated by hand and isn't in its
+/// ring. Called with the store lock held.
+pub fn pause_if_outside(store: &mut ConfigStore, id: &str) -> bool {
+    let Some(config_type) = store.configs.iter().find(|c| c.id == id).map(|c| c.config_type.clone()) else {
+        return false;
+    };
+    match store.rotations.get_mut(&config_type) {
+        Some(policy) if policy.enabled && !policy.paused && !policy.config_ids.iter().any(|i| i == id) => {
+            policy.paused = true;
+            eprintln!("rotation: paused for {}", String::from(config_type));
+            true
+        }
+        _ => false,
+    }
+}
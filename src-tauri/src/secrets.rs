@@ -0,0 +1,45 @@
+//! Optional OS keyring backend for API keys (Keychain, Credential Manager,
+//! Secret Service). With it enabled, configs.json holds `keyring:<id>` in
+//! place of each key; `load_store` swaps the real key back in and `save_store`
+//! moves it out again, so the rest of the backend only ever sees plain keys.
+
+use keyring::Entry;
+
+const SERVICE: &str = "claude-config-manager";
+const REFERENCE_PREFIX: &str = "keyring:";
+
+pub fn is_reference(api_key: &str) -> bool {
+    api_key.starts_with(REFERENCE_PREFIX)
+}
+
+fn entry(config_id: &str) -> Result<Entry, String> {
+    Entry::new(SERVICE, config_id).map_err(|e| format!("Keyring unavailable: {}", e))
+}
+
+/// Looks up the key a `keyring:<id>` reference points to.
+pub fn resolve(reference: &str) -> Result<String, String> {
+    let id = reference.strip_prefix(REFERENCE_PREFIX).unwrap_or(reference);
+    entry(id)?
+        .get_password()
+        .map_err(|e| format!("Failed to read key for config '{}' from keyring: {}", id, e))
+}
+
+/// Saves `secret` under the config's id and returns the reference to store in
+/// its place. Skips the write when the keyring already holds the same value.
+pub fn store(config_id: &str, secret: &str) -> Result<String, String> {
+    let entry = entry(config_id)?;
+    if entry.get_password().ok().as_deref() != Some(secret) {
+        entry
+            .set_password(secret)
+            .map_err(|e| format!("Failed to write key for config '{}' to keyring: {}", config_id, e))?;
+    }
+    Ok(format!("{}{}", REFERENCE_PREFIX, config_id))
+}
+
+/// Removes the config's keyring entry; a missing entry is fine.
+pub fn delete(config_id: &str) -> Result<(), String> {
+    match entry(config_id)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(format!("Failed to remove key for config '{}' from keyring: {}", config_id, e)),
+    }
+}
@@ -0,0 +1,144 @@
+//! The in-memory `ConfigStore` behind `load_store` / `save_store`, and the
+//! lock that makes a read-modify-write-apply sequence atomic.
+//!
+//! The store is read from configs.json once and then served from memory
+//! until the file's size or modification time changes (another instance, the
+//! CLI, a hand edit, a backup restore). Every command that changes the store
+//! holds `lock()` from its `load_store` through `save_store` and any tool
+//! file writes, so two commands can't interleave and lose an update. The lock
+//! is reentrant because commands call each other (a profile activates
+//! configs, `update_config` re-applies). It's a static rather than Tauri
+//! managed state because the CLI, failover, tray and deep link threads use
+//! the same code paths without a `State` to hand.
+
+use std::path::Path;
+use std::sync::{Condvar, Mutex};
+use std::thread::{self, ThreadId};
+use std::time::SystemTime;
+
+use crate::ConfigStore;
+
+/// Size and modification time of configs.json; `None` if it doesn't exist.
+type Stamp = Option<(u64, Option<SystemTime>)>;
+
+struct Owner {
+    thread: Option<ThreadId>,
+    depth: usize,
+}
+
+static OWNER: Mutex<Owner> = Mutex::new(Owner { thread: None, depth: 0 });
+static RELEASED: Condvar = Condvar::new();
+static CACHE: Mutex<Option<(Stamp, ConfigStore)>> = Mutex::new(None);
+
+/// Held while a thread may change the store; released on drop.
+pub struct StoreLock(());
+
+impl Drop for StoreLock {
+    fn drop(&mut self) {
+        let mut owner = OWNER.lock().unwrap_or_else(|e| e.into_inner());
+        owner.depth -= 1;
+        if owner.depth == 0 {
+            owner.thread = None;
+            RELEASED.notify_all();
+        }
+    }
+}
+
+/// Waits for other threads' mutations to finish. Free to call again on a
+/// thread that already holds it.
+pub fn lock() -> StoreLock {
+    let me = thread::current().id();
+    let mut owner = OWNER.lock().unwrap_or_else(|e| e.into_inner());
+    while owner.thread.is_some_and(|t| t != me) {
+        owner = RELEASED.wait(owner).unwrap_or_else(|e| e.into_inner());
+    }
+    owner.thread = Some(me);
+    owner.depth += 1;
+    StoreLock(())
+}
+
+/// `lock` without waiting; `None` while another thread holds it.
+pub fn try_lock() -> Option<StoreLock> {
+    let me = thread::current().id();
+    let mut owner = OWNER.lock().unwrap_or_else(|e| e.into_inner());
+    if owner.thread.is_some_and(|t| t != me) {
+        return None;
+    }
+    owner.thread = Some(me);
+    owner.depth += 1;
+    Some(StoreLock(()))
+}
+
+pub fn stamp(path: &Path) -> Stamp {
+    std::fs::metadata(path).ok().map(|m| (m.len(), m.modified().ok()))
+}
+
+/// The cached store, if configs.json still matches `stamp`.
+pub fn cached(stamp: Stamp) -> Option<ConfigStore> {
+    match &*CACHE.lock().unwrap_or_else(|e| e.into_inner()) {
+        Some((cached_stamp, store)) if *cached_stamp == stamp => Some(store.clone()),
+        _ => None,
+    }
+}
+
+/// Records `store` as the content of configs.json at `stamp`.
+pub fn remember(stamp: Stamp, store: &ConfigStore) {
+    *CACHE.lock().unwrap_or_else(|e| e.into_inner()) = Some((stamp, store.clone()));
+}
+
+/// Drops the cached store, for tests that switch config directories.
+#[cfg(test)]
+pub fn reset() {
+    *CACHE.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use crate::test_support::{add, sandbox};
+    use crate::{activate_config, deactivate_config, find_mismatched_keys, get_configs, load_store, ConfigOptions};
+
+    #[test]
+    fn concurrent_commands_leave_a_consistent_store() {
+        let _sandbox = sandbox();
+        let ids: Vec<String> = (0..4)
+            .map(|i| {
+                let key = format!("sk-ant-api03-concurrent{}000000000", i);
+                add(&format!("c{}", i), "claude", &key, "", ConfigOptions::default()).id
+            })
+            .collect();
+
+        let workers: Vec<_> = (0..8)
+            .map(|worker| {
+                let ids = ids.clone();
+                thread::spawn(move || {
+                    for round in 0..20 {
+                        let id = ids[(worker + round) % ids.len()].clone();
+                        if round % 3 == 2 {
+                            deactivate_config(id, None).unwrap();
+                        } else {
+                            activate_config(id, None).unwrap();
+                        }
+                        assert_eq!(get_configs().len(), ids.len());
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        // What's on disk, not what the last writer cached.
+        super::reset();
+        let store = load_store();
+        assert_eq!(store.configs.len(), ids.len());
+        let activations: u64 = store.configs.iter().map(|c| c.activation_count).sum();
+        assert_eq!(activations, 8 * 14);
+        let active: Vec<_> = store.configs.iter().filter(|c| c.is_active).collect();
+        assert!(active.len() <= 1);
+        if let [config] = active[..] {
+            assert_eq!(find_mismatched_keys(config).unwrap(), Vec::<String>::new());
+        }
+    }
+}
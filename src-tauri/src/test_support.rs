@@ -0,0 +1,55 @@
+//! Shared setup for unit tests that touch the filesystem. Every path getter
+//! reads process-wide env vars and the store is cached in a static
+//! (`state.rs`), so such tests run one at a time under `sandbox()`.
+
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+
+use crate::{add_config, state, Config, ConfigOptions};
+
+static ENV: Mutex<()> = Mutex::new(());
+
+/// A temp home and config directory, in effect until dropped.
+pub struct Sandbox {
+    pub dir: tempfile::TempDir,
+    _env: MutexGuard<'static, ()>,
+}
+
+impl Sandbox {
+    pub fn home(&self) -> PathBuf {
+        self.dir.path().join("home")
+    }
+}
+
+/// Points `HOME`, the XDG dirs and `CLAUDE_CONFIG_MANAGER_CONFIG_DIR` at a
+/// fresh temp directory and forgets the cached store.
+pub fn sandbox() -> Sandbox {
+    let env = ENV.lock().unwrap_or_else(|e| e.into_inner());
+    let dir = tempfile::tempdir().unwrap();
+    let home = dir.path().join("home");
+    std::fs::create_dir_all(&home).unwrap();
+    std::env::set_var("HOME", &home);
+    std::env::set_var("XDG_CONFIG_HOME", home.join(".config"));
+    std::env::set_var("XDG_DATA_HOME", home.join(".local").join("share"));
+    std::env::set_var("CLAUDE_CONFIG_MANAGER_CONFIG_DIR", dir.path().join("app"));
+    std::env::remove_var("CODEX_HOME");
+    state::reset();
+    Sandbox { dir, _env: env }
+}
+
+/// Adds a config through `add_config`, skipping the weak key and duplicate
+/// checks.
+pub fn add(name: &str, config_type: &str, api_key: &str, base_url: &str, options: ConfigOptions) -> Config {
+    add_config(
+        name.to_string(),
+        config_type.to_string(),
+        api_key.to_string(),
+        base_url.to_string(),
+        String::new(),
+        Some(options),
+        Some(true),
+        None,
+        Some(true),
+    )
+    .unwrap()
+}
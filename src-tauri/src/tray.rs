@@ -0,0 +1,152 @@
+//! Tray icon for switching configs without opening the window: a submenu
+//! per type with the active config checked, plus "Deactivate all" and "Open
+//! window". The menu is rebuilt from the store on every `configs-changed`
+//! (see `watcher.rs`). Hidden when `Settings.tray_disabled` is set.
+
+use serde::Serialize;
+use std::sync::OnceLock;
+use tauri::menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu};
+use tauri::tray::TrayIconBuilder;
+use tauri::{AppHandle, Emitter, Manager, Wry};
+
+use crate::{activate_config, deactivate_config, load_store, sorted_configs, Config};
+
+const TRAY_ID: &str = "main";
+const ACTIVATE_PREFIX: &str = "activate:";
+const DEACTIVATE_ALL: &str = "deactivate-all";
+const OPEN_WINDOW: &str = "open-window";
+/// Sent when an action picked from the tray fails, since there's no window
+/// call to return the error to.
+const TRAY_ERROR: &str = "tray-error";
+
+static APP: OnceLock<AppHandle> = OnceLock::new();
+
+#[derive(Debug, Clone, Serialize)]
+struct TrayError {
+    message: String,
+}
+
+fn build_menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
+    let menu = Menu::new(app)?;
+    let configs = sorted_configs(load_store().configs);
+    let mut types: Vec<_> = configs.iter().map(|c| c.config_type.clone()).collect();
+    types.dedup();
+    for config_type in types {
+        let submenu = Submenu::new(app, String::from(config_type.clone()), true)?;
+        for config in configs.iter().filter(|c| c.config_type == config_type) {
+            let id = format!("{}{}", ACTIVATE_PREFIX, config.id);
+            submenu.append(&CheckMenuItem::with_id(app, id, &config.name, true, config.is_active, None::<&str>)?)?;
+        }
+        menu.append(&submenu)?;
+    }
+    if !configs.is_empty() {
+        menu.append(&PredefinedMenuItem::separator(app)?)?;
+    }
+    let any_active = configs.iter().any(|c| c.is_active);
+    menu.append(&MenuItem::with_id(app, DEACTIVATE_ALL, "Deactivate all", any_active, None::<&str>)?)?;
+    menu.append(&MenuItem::with_id(app, OPEN_WINDOW, "Open window", true, None::<&str>)?)?;
+    Ok(menu)
+}
+
+fn show_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+fn deactivate_all() -> Result<(), String> {
+    let active: Vec<Config> = load_store().configs.into_iter().filter(|c| c.is_active).collect();
+    let mut errors = Vec::new();
+    for config in active {
+        if let Err(e) = deactivate_config(config.id.clone(), None) {
+            errors.push(format!("{}: {}", config.name, e));
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("; "))
+    }
+}
+
+fn on_menu_event(app: &AppHandle, id: &str) {
+    if id == OPEN_WINDOW {
+        show_window(app);
+        return;
+    }
+    // Activation writes files and may wait on the store lock; keep it off
+    // the UI thread.
+    let app = app.clone();
+    let id = id.to_string();
+    std::thread::spawn(move || {
+        let result = match id.strip_prefix(ACTIVATE_PREFIX) {
+            Some(config_id) => activate_config(config_id.to_string(), None).map(|result| {
+                if let Some(warning) = result.budget_warning {
+                    eprintln!("tray: '{}' is over its daily budget", warning.name);
+                }
+            }),
+            None if id == DEACTIVATE_ALL => deactivate_all(),
+            None => return,
+        };
+        if let Err(message) = result {
+            eprintln!("tray: {}", message);
+            if let Err(e) = app.emit(TRAY_ERROR, TrayError { message }) {
+                eprintln!("tray: failed to emit {}: {}", TRAY_ERROR, e);
+            }
+        }
+        // A clicked check item toggles itself even when nothing was saved
+        // (a failure, or the config was already active); put the menu back.
+        refresh();
+    });
+}
+
+fn create(app: &AppHandle) -> tauri::Result<()> {
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip("Claude Config Manager")
+        .menu(&build_menu(app)?)
+        .on_menu_event(|app, event| on_menu_event(app, event.id().as_ref()));
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app)?;
+    Ok(())
+}
+
+/// Shows or removes the tray icon to match `disabled`.
+pub fn set_disabled(disabled: bool) {
+    let Some(app) = APP.get() else {
+        return;
+    };
+    if disabled {
+        app.remove_tray_by_id(TRAY_ID);
+    } else if app.tray_by_id(TRAY_ID).is_none() {
+        if let Err(e) = create(app) {
+            eprintln!("tray: failed to create: {}", e);
+        }
+    }
+}
+
+/// Rebuilds the menu from the store. Does nothing without a tray, e.g. in
+/// the CLI or with the tray disabled. Runs on its own thread: the menu API
+/// waits for the main thread, which may in turn be waiting for the store
+/// lock our caller holds.
+pub fn refresh() {
+    let Some(app) = APP.get() else {
+        return;
+    };
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    std::thread::spawn(move || {
+        if let Err(e) = build_menu(app).and_then(|menu| tray.set_menu(Some(menu))) {
+            eprintln!("tray: failed to rebuild menu: {}", e);
+        }
+    });
+}
+
+pub fn start(app: AppHandle) {
+    APP.set(app).ok();
+    set_disabled(load_store().settings.tray_disabled);
+}
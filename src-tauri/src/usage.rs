@@ -0,0 +1,177 @@
+//! This month's spend for a key, from the provider's admin cost APIs: the
+//! OpenAI organization costs / usage endpoints and the Anthropic cost report.
+//! Both need an admin key, so a normal key or a relay gets a typed
+//! `forbidden` / `unsupported` result rather than an error. Gemini has no
+//! such API; `set_usage_note` is the manual fallback there.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::{codex_azure, codex_base_url, now_millis, Config, ConfigType};
+
+const TIMEOUT: Duration = Duration::from_secs(15);
+/// Daily buckets per request; a month always fits in one page.
+const BUCKET_LIMIT: u32 = 31;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageStatus {
+    Ok,
+    /// No usage API for this provider, or the endpoint doesn't exist (relays).
+    Unsupported,
+    /// 401 / 403: the key can't read usage, e.g. not an admin key.
+    Forbidden,
+    /// Network error, 5xx or a response we couldn't read.
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Usage {
+    pub status: UsageStatus,
+    /// Calendar month in UTC, `YYYY-MM`.
+    pub period: String,
+    /// US dollars.
+    pub total_cost: Option<f64>,
+    /// `None` where the provider doesn't report request counts (Anthropic).
+    pub request_count: Option<u64>,
+    /// The provider's responses, for anything the summary leaves out.
+    pub raw: Option<serde_json::Value>,
+    pub error: Option<String>,
+    /// Milliseconds since the Unix epoch.
+    pub fetched_at: u64,
+}
+
+/// Proleptic Gregorian (year, month) for days since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month)
+}
+
+/// The current month as `YYYY-MM` and its first second in Unix time.
+fn current_month() -> (String, i64) {
+    let (year, month) = civil_from_days((now_millis() / 86_400_000) as i64);
+    let start = crate::expiry::days_from_civil(year, month, 1) * 86_400;
+    (format!("{:04}-{:02}", year, month), start)
+}
+
+fn result(status: UsageStatus, period: &str, error: Option<String>) -> Usage {
+    Usage {
+        status,
+        period: period.to_string(),
+        total_cost: None,
+        request_count: None,
+        raw: None,
+        error,
+        fetched_at: now_millis(),
+    }
+}
+
+/// GETs `url` and maps a non-2xx response to the matching status.
+fn get_json(url: &str, headers: &[(&str, String)]) -> Result<serde_json::Value, (UsageStatus, String)> {
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(TIMEOUT))
+        .http_status_as_error(false)
+        .build()
+        .into();
+    let mut request = agent.get(url);
+    for (name, value) in headers {
+        request = request.header(*name, value);
+    }
+    let mut response = request.call().map_err(|e| (UsageStatus::Failed, e.to_string()))?;
+    let code = response.status().as_u16();
+    let status = match code {
+        200..=299 => None,
+        401 | 403 => Some(UsageStatus::Forbidden),
+        400..=499 => Some(UsageStatus::Unsupported),
+        _ => Some(UsageStatus::Failed),
+    };
+    if let Some(status) = status {
+        return Err((status, format!("HTTP {}", code)));
+    }
+    let body = response
+        .body_mut()
+        .read_to_string()
+        .map_err(|e| (UsageStatus::Failed, e.to_string()))?;
+    serde_json::from_str(&body).map_err(|e| (UsageStatus::Failed, format!("Invalid JSON: {}", e)))
+}
+
+/// Every `results` entry across the `data` buckets of a report.
+fn bucket_results(report: &serde_json::Value) -> impl Iterator<Item = &serde_json::Value> {
+    report["data"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|bucket| bucket["results"].as_array())
+        .flatten()
+}
+
+fn fetch_openai(config: &Config, period: &str, start: i64) -> Result<Usage, (UsageStatus, String)> {
+    let base = codex_base_url(config);
+    let auth = [("Authorization", format!("Bearer {}", config.api_key))];
+    let query = format!("start_time={}&bucket_width=1d&limit={}", start, BUCKET_LIMIT);
+    let costs = get_json(&format!("{}/organization/costs?{}", base, query), &auth)?;
+    let total_cost = bucket_results(&costs)
+        .filter_map(|r| r["amount"]["value"].as_f64())
+        .sum();
+    // Request counts are a nice-to-have; the cost already answers the question.
+    let completions = get_json(&format!("{}/organization/usage/completions?{}", base, query), &auth).ok();
+    let request_count = completions
+        .as_ref()
+        .map(|usage| bucket_results(usage).filter_map(|r| r["num_model_requests"].as_u64()).sum());
+    Ok(Usage {
+        total_cost: Some(total_cost),
+        request_count,
+        raw: Some(serde_json::json!({ "costs": costs, "completions": completions })),
+        ..result(UsageStatus::Ok, period, None)
+    })
+}
+
+fn fetch_anthropic(config: &Config, period: &str) -> Result<Usage, (UsageStatus, String)> {
+    let base = if config.base_url.is_empty() {
+        "https://api.anthropic.com"
+    } else {
+        config.base_url.as_str()
+    };
+    let starting_at = format!("{}-01T00:00:00Z", period);
+    let report = get_json(
+        &format!(
+            "{}/v1/organizations/cost_report?starting_at={}&bucket_width=1d&limit={}",
+            base, starting_at, BUCKET_LIMIT
+        ),
+        &[
+            ("x-api-key", config.api_key.clone()),
+            ("anthropic-version", "2023-06-01".to_string()),
+        ],
+    )?;
+    // Amounts are decimal strings in cents.
+    let cents: f64 = bucket_results(&report)
+        .filter_map(|r| r["amount"].as_str()?.parse::<f64>().ok())
+        .sum();
+    Ok(Usage {
+        total_cost: Some(cents / 100.0),
+        raw: Some(report),
+        ..result(UsageStatus::Ok, period, None)
+    })
+}
+
+/// This month's usage for `config`. Never fails: problems come back as a
+/// non-`ok` status with `error` set.
+pub fn fetch(config: &Config) -> Usage {
+    let (period, start) = current_month();
+    let fetched = match config.config_type {
+        ConfigType::Codex if codex_azure(config).is_none() => fetch_openai(config, &period, start),
+        ConfigType::Claude => fetch_anthropic(config, &period),
+        _ => Err((
+            UsageStatus::Unsupported,
+            "This provider has no usage API".to_string(),
+        )),
+    };
+    fetched.unwrap_or_else(|(status, error)| result(status, &period, Some(error)))
+}
@@ -0,0 +1,110 @@
+//! Tells the frontend when configs.json changes, through a `configs-changed`
+//! event carrying the new list (as `get_configs` returns it). Our own saves
+//! report themselves via `saved`; the file watcher picks up everything else,
+//! such as another instance, a hand edit, or a dotfiles sync.
+
+use notify::{RecursiveMode, Watcher};
+use std::collections::hash_map::DefaultHasher;
+use std::ffi::OsString;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::sync::{mpsc, Mutex, OnceLock};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+use crate::{get_config_path, get_configs, resolve_symlink};
+
+const CONFIGS_CHANGED: &str = "configs-changed";
+/// Editors and `save_store` produce bursts of events per save.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+static APP: OnceLock<AppHandle> = OnceLock::new();
+/// Hash of the content last written by us or last reported, so a save isn't
+/// announced a second time when the watcher sees it.
+static LAST_CONTENT: Mutex<Option<u64>> = Mutex::new(None);
+
+fn content_hash(content: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Records `content` as seen; returns false if it already was.
+fn mark_seen(content: &[u8]) -> bool {
+    let hash = Some(content_hash(content));
+    let mut last = LAST_CONTENT.lock().unwrap_or_else(|e| e.into_inner());
+    if *last == hash {
+        return false;
+    }
+    *last = hash;
+    true
+}
+
+fn emit_configs() {
+    crate::tray::refresh();
+    if let Some(app) = APP.get() {
+        if let Err(e) = app.emit(CONFIGS_CHANGED, get_configs()) {
+            eprintln!("watcher: failed to emit {}: {}", CONFIGS_CHANGED, e);
+        }
+    }
+}
+
+/// Called by `save_store` after every successful write. Does nothing until
+/// `start` has run, e.g. in the CLI.
+pub fn saved(content: &[u8]) {
+    mark_seen(content);
+    emit_configs();
+}
+
+/// Watches the directory rather than the file so deleting and recreating
+/// configs.json is noticed too. A symlinked configs.json also has its
+/// target's directory watched.
+pub fn start(app: AppHandle) {
+    if APP.set(app).is_err() {
+        return;
+    }
+    let path = get_config_path();
+    let mut targets: Vec<PathBuf> = vec![path.clone()];
+    let real = resolve_symlink(&path);
+    if real != path {
+        targets.push(real);
+    }
+    let names: Vec<OsString> = targets.iter().filter_map(|p| p.file_name().map(OsString::from)).collect();
+
+    std::thread::spawn(move || {
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = match notify::recommended_watcher(tx) {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                eprintln!("watcher: failed to create file watcher: {}", e);
+                return;
+            }
+        };
+        for dir in targets.iter().filter_map(|p| p.parent()) {
+            if let Err(e) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+                eprintln!("watcher: failed to watch {}: {}", dir.display(), e);
+            }
+        }
+
+        while let Ok(event) = rx.recv() {
+            let relevant = event.is_ok_and(|event| {
+                event
+                    .paths
+                    .iter()
+                    .any(|p| p.file_name().is_some_and(|n| names.iter().any(|name| name == n)))
+            });
+            if !relevant {
+                continue;
+            }
+            while rx.recv_timeout(DEBOUNCE).is_ok() {}
+
+            // A deleted file reads as empty, which `load_store` treats as an
+            // empty store; recreating it then counts as another change.
+            let content = fs::read(&path).unwrap_or_default();
+            if mark_seen(&content) {
+                emit_configs();
+            }
+        }
+    });
+}
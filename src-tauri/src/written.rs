@@ -0,0 +1,74 @@
+//! Hashes of the tool files we rewrite wholesale (Codex auth.json and
+//! config.toml, opencode.json) as we last wrote them, so an apply can tell
+//! that one was edited by hand since and refuse to overwrite it. Kept in
+//! `written-files.json` next to configs.json rather than in the store: every
+//! write records here as it happens, while a `ConfigStore` copy held by the
+//! caller would be saved later and could drop the entry.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{get_codex_dir, get_config_path, get_opencode_config_path, write_file_atomic};
+
+/// Prefix of the error an apply returns for a hand-edited file. The frontend
+/// matches it to offer `force_apply_config`.
+pub const EXTERNALLY_MODIFIED: &str = "Externally modified";
+
+fn hashes_path() -> PathBuf {
+    get_config_path().with_file_name("written-files.json")
+}
+
+fn tracked(path: &Path) -> bool {
+    let codex_dir = get_codex_dir();
+    [codex_dir.join("auth.json"), codex_dir.join("config.toml"), get_opencode_config_path()]
+        .iter()
+        .any(|p| p == path)
+}
+
+/// 64-bit FNV-1a. Only detects edits, so it needn't be cryptographic, and
+/// unlike `DefaultHasher` it's stable across Rust releases.
+fn hash(content: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in content {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+fn load() -> HashMap<String, String> {
+    fs::read_to_string(hashes_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Notes what we just wrote to `path` (`None`: removed it). Untracked paths
+/// are ignored. A failure is logged: the worst case is one needless prompt.
+pub fn record(path: &Path, content: Option<&[u8]>) {
+    if !tracked(path) {
+        return;
+    }
+    let mut hashes = load();
+    let key = path.display().to_string();
+    match content {
+        Some(content) => hashes.insert(key, hash(content)),
+        None => hashes.remove(&key),
+    };
+    let saved = serde_json::to_string_pretty(&hashes)
+        .map_err(|e| e.to_string())
+        .and_then(|json| write_file_atomic(&hashes_path(), json.as_bytes()));
+    if let Err(e) = saved {
+        eprintln!("written: failed to record {}: {}", path.display(), e);
+    }
+}
+
+/// Whether `path` changed since we last wrote it. Files we never wrote, and
+/// ones that have since been deleted, don't count.
+pub fn modified(path: &Path) -> bool {
+    let Some(recorded) = load().remove(&path.display().to_string()) else {
+        return false;
+    };
+    fs::read(path).is_ok_and(|content| hash(&content) != recorded)
+}
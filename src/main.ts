@@ -1,16 +1,189 @@
 import { invoke } from "@tauri-apps/api/core";
 import { getCurrentWindow } from "@tauri-apps/api/window";
+import { listen } from "@tauri-apps/api/event";
 
 type ConfigType = "claude" | "gemini" | "codex";
+// 其他任意字符串是自定义的 passthrough 类型：只记录，不写入任何工具的配置
+type AnyConfigType = ConfigType | (string & {});
+type Tab = ConfigType | "other" | "opencode";
+
+interface CodexOptions {
+  provider_name?: string | null;
+  reasoning_effort?: string | null;
+  wire_api?: string | null;
+  disable_response_storage?: boolean | null; // 不填为 true
+  // Azure OpenAI：base_url 填资源终结点，密钥写入 ~/.codex/.env 的 AZURE_OPENAI_API_KEY
+  azure?: AzureOptions | null;
+}
+
+interface AzureOptions {
+  deployment: string;
+  api_version: string;
+}
+
+interface ConfigOptions {
+  codex?: CodexOptions | null;
+  streaming_enabled?: boolean | null;
+  telemetry_disabled?: boolean | null;
+  timeout_seconds?: number | null;
+  max_retries?: number | null; // 工具自身的重试次数 (0-10)：Claude 写入 ANTHROPIC_MAX_RETRIES，Codex 写入 OPENAI_MAX_RETRIES
+  small_fast_model?: string | null; // Claude: 写入 ANTHROPIC_SMALL_FAST_MODEL
+  // Claude: 扩展思考 token 预算，写入 ANTHROPIC_THINKING_BUDGET 及 OpenCode 的 x-thinking-budget 请求头
+  model_thinking_budget?: number | null;
+  provider_version?: string | null; // Claude: 写入 ANTHROPIC_API_VERSION
+  // [请求头名, 值]：写入 OpenCode provider 的 options.headers；Claude 另写入 ANTHROPIC_API_VERSION_HEADER
+  api_version_header?: [string, string] | null;
+  // Claude: mTLS 客户端证书，写入 CLAUDE_CODE_CLIENT_CERT / CLAUDE_CODE_CLIENT_KEY
+  http_client_cert_path?: string | null;
+  http_client_key_path?: string | null;
+  env_overrides?: EnvVar[];
+  replace_builtin_env?: boolean;
+  gemini_settings_file?: boolean;
+  scopes?: string[]; // Gemini: OAuth2 scopes，逗号分隔写入 GOOGLE_OAUTH_SCOPES
+  notes_file_path?: string | null; // 关联的笔记文件（runbook、账单信息等）
+  expires_at?: string | null; // YYYY-MM-DD 或 RFC 3339，仅用于提醒
+  rotation_note?: string | null;
+  cost_budget_daily_usd?: number | null; // 每日预算（美元），超出后激活时提醒，不阻止
+  webhook_url?: string | null; // 每次激活后 POST 一条通知
+}
+
+interface EnvVar {
+  key: string;
+  value: string;
+}
 
 interface Config {
   id: string;
   name: string;
+  config_type: AnyConfigType;
+  api_key: string; // 列表里是掩码，完整 key 需调用 reveal_api_key
+  key_set: boolean;
+  base_url: string;
+  model: string;
+  is_active: boolean; // 写入工具自身配置文件
+  opencode_active: boolean; // 写入 opencode.json 对应 provider
+  active_targets: ("tool" | "opencode")[];
+  expired: boolean; // options.expires_at 已过
+  options: ConfigOptions;
+  is_shared: boolean;
+  tags: string[];
+  // 毫秒时间戳，旧配置可能为 null
+  created_at: number | null;
+  updated_at: number | null;
+  last_activated_at: number | null;
+  activation_count: number;
+  last_benchmark: Benchmark | null;
+  last_health: HealthCheck | null; // 上次“全部检测”的结果
+  last_usage: Usage | null;
+  usage_note: string | null; // 无法查询用量时手动记录
+  sort_index: number;
+}
+
+// fetch_usage：本月花费，需要管理员 key；查不到时 status 不为 ok
+interface Usage {
+  status: "ok" | "unsupported" | "forbidden" | "failed";
+  period: string; // YYYY-MM
+  total_cost: number | null; // 美元
+  request_count: number | null;
+  raw: unknown;
+  error: string | null;
+  fetched_at: number;
+}
+
+interface HealthCheck {
+  status: "reachable" | "auth_failed" | "server_error" | "unreachable";
+  http_status: number | null;
+  latency_ms: number;
+  error: string | null;
+  checked_at: number;
+}
+
+// check_all_configs 的返回值与 health-check-progress 事件内容
+interface HealthCheckResult extends HealthCheck {
+  id: string;
+  name: string;
+}
+
+const HEALTH_STATUS_LABELS = { reachable: "正常", auth_failed: "鉴权失败", server_error: "服务端错误", unreachable: "无法连接" };
+
+interface Benchmark {
+  min_latency_ms: number | null;
+  avg_latency_ms: number | null;
+  error_rate: number;
+  auth_ok: boolean;
+  measured_at: number;
+}
+
+interface Profile {
+  id: string;
+  name: string;
+  entries: Record<string, string>; // 类型 -> 配置 id
+  opencode: boolean;
+}
+
+interface ProfileActivationResult {
+  entries: { config_type: AnyConfigType; config_id: string; error: string | null }[];
+  opencode_error: string | null;
+}
+
+interface ValidationIssue {
+  field: "name" | "api_key" | "base_url";
+  severity: "error" | "warning";
+  message: string;
+}
+
+interface TagCount {
+  tag: string;
+  count: number;
+}
+
+interface VerifyResult {
+  all_ok: boolean;
+  failed_vars: string[];
+}
+
+interface ConfigStatus {
+  id: string;
+  name: string;
+  config_type: AnyConfigType;
+  state: "in_sync" | "drifted" | "missing";
+  drifted: { key: string; expected: string | null; actual: string | null }[];
+}
+
+interface OpenCodeApplyResult {
+  model: string | null;
+  providers: { slot: ConfigType; provider: string; status: "updated" | "created" | "not_selected" | "skipped" }[];
+}
+
+interface OpenCodeProviderInfo {
+  provider: string;
+  key_set: boolean;
+  masked_key: string;
+  base_url: string;
+}
+
+interface DetectedConfig {
+  id: string;
+  source: string;
   config_type: ConfigType;
-  api_key: string;
+  masked_key: string;
   base_url: string;
   model: string;
-  is_active: boolean;
+  already_imported: boolean;
+}
+
+// preview_activation / preview_deactivation：切换会写入的内容，密钥已打码
+interface ActivationPlan {
+  files: { path: string; action: "create" | "overwrite" | "delete"; diff: string }[];
+  env: { file: string; name: string; action: "set" | "change" | "delete"; old: string | null; new: string | null }[];
+}
+
+interface ProjectActivation {
+  config_id: string;
+  config_type: AnyConfigType;
+  project_dir: string;
+  target_file: string;
+  written_keys: string[];
 }
 
 const CONFIG_TYPE_LABELS: Record<ConfigType, string> = {
@@ -25,20 +198,86 @@ const CONFIG_TYPE_COLORS: Record<ConfigType, string> = {
   codex: "#8b5cf6", // purple
 };
 
+const OTHER_TYPE_COLOR = "#6b7280"; // gray
+
+function isBuiltinType(type: string): type is ConfigType {
+  return Object.prototype.hasOwnProperty.call(CONFIG_TYPE_LABELS, type);
+}
+
+function typeLabel(type: AnyConfigType): string {
+  return isBuiltinType(type) ? CONFIG_TYPE_LABELS[type] : type;
+}
+
+function typeColor(type: AnyConfigType): string {
+  return isBuiltinType(type) ? CONFIG_TYPE_COLORS[type] : OTHER_TYPE_COLOR;
+}
+
+interface Settings {
+  notifications_enabled: boolean;
+  tray_disabled?: boolean; // 隐藏托盘图标
+  // 留空使用默认位置；支持 ~、$VAR、${VAR}、%VAR%
+  codex_dir?: string | null;
+  opencode_config_path?: string | null;
+  claude_settings_path?: string | null;
+  opencode_auth_store?: boolean; // OpenCode 密钥写入 auth.json，opencode.json 的 apiKey 留空
+}
+
+interface RotationPolicy {
+  config_ids: string[];
+  interval: { hours: number } | "on_start";
+  enabled: boolean;
+  paused: boolean;
+  last_rotated_at?: number | null;
+}
+
+interface ActivateResult {
+  budget_warning: { name: string; budget_usd: number; spent_usd: number } | null;
+}
+
+type AddConfigError =
+  | { kind: "weak_key"; reason: string }
+  | { kind: "duplicate"; existing: string }
+  | { kind: "invalid"; message: string };
+
+interface ConfigEvent {
+  id: string;
+  name: string;
+  config_type: AnyConfigType;
+}
+
 let configs: Config[] = [];
+let settings: Settings = { notifications_enabled: false };
 let editingConfig: Config | null = null;
-let currentTab: ConfigType | "opencode" = "claude";
+let currentTab: Tab = "claude";
+// 已激活但与实际文件不一致的配置
+let outOfSync = new Map<string, ConfigStatus>();
+let projectActivations: ProjectActivation[] = [];
+let tagCounts: TagCount[] = [];
+let profiles: Profile[] = [];
+// 标签筛选，null 表示不筛选
+let tagFilter: string | null = null;
 
 async function loadConfigs() {
   try {
+    await invoke("check_store").catch((e) => showToast(`${e}`));
     configs = await invoke<Config[]>("get_configs");
+    const statuses = await invoke<ConfigStatus[]>("verify_active_configs");
+    outOfSync = new Map(statuses.filter((s) => s.state !== "in_sync").map((s) => [s.id, s]));
+    projectActivations = await invoke<ProjectActivation[]>("get_project_activations");
+    tagCounts = await invoke<TagCount[]>("list_tags");
+    profiles = await invoke<Profile[]>("list_profiles");
+    settings = await invoke<Settings>("get_settings");
+    if (tagFilter && !tagCounts.some((t) => t.tag === tagFilter)) tagFilter = null;
     renderConfigs();
   } catch (e) {
     console.error("Failed to load configs:", e);
   }
 }
 
-async function saveConfig(config: Omit<Config, "id" | "is_active">) {
+async function saveConfig(
+  config: Pick<Config, "name" | "config_type" | "api_key" | "base_url" | "model" | "tags" | "options">,
+  confirmed: { weakKey?: boolean; duplicate?: boolean } = {}
+) {
   try {
     if (editingConfig) {
       await invoke("update_config", {
@@ -47,6 +286,8 @@ async function saveConfig(config: Omit<Config, "id" | "is_active">) {
         apiKey: config.api_key,
         baseUrl: config.base_url,
         model: config.model,
+        options: config.options,
+        tags: config.tags,
       });
     } else {
       await invoke("add_config", {
@@ -55,17 +296,197 @@ async function saveConfig(config: Omit<Config, "id" | "is_active">) {
         apiKey: config.api_key,
         baseUrl: config.base_url,
         model: config.model,
+        options: config.options,
+        tags: config.tags,
+        force: confirmed.duplicate ?? false,
+        allowWeakKey: confirmed.weakKey ?? false,
       });
     }
     await loadConfigs();
     closeModal();
     showToast(editingConfig ? "配置已更新" : "配置已添加");
   } catch (e) {
-    console.error("Failed to save config:", e);
+    // 疑似把密码填成了 key、重复配置：各自确认后再添加
+    const err = e as AddConfigError;
+    if (err?.kind === "weak_key") {
+      if (confirm(`API key 看起来像密码（${err.reason}）\n仍要添加吗？`)) {
+        await saveConfig(config, { ...confirmed, weakKey: true });
+      }
+      return;
+    }
+    if (err?.kind === "duplicate") {
+      if (confirm(`已存在相同的配置「${err.existing}」\n仍要添加吗？`)) {
+        await saveConfig(config, { ...confirmed, duplicate: true });
+      }
+      return;
+    }
+    console.error("Failed to save config:", err?.kind === "invalid" ? err.message : e);
     showToast("保存失败");
   }
 }
 
+// 首次运行时发现本机已有的密钥，询问是否导入
+async function offerDetectedImport() {
+  if (configs.length > 0) return;
+  try {
+    const detected = await invoke<DetectedConfig[]>("detect_existing_configs");
+    const items = detected.filter((d) => !d.already_imported);
+    if (items.length === 0) return;
+    const list = items
+      .map((d) => `${CONFIG_TYPE_LABELS[d.config_type]}: ${d.masked_key} (${d.source})`)
+      .join("\n");
+    if (!confirm(`发现本机已有配置：\n${list}\n是否导入？`)) return;
+    const imported = await invoke<Config[]>("import_detected", { items });
+    await loadConfigs();
+    showToast(`已导入 ${imported.length} 个配置`);
+  } catch (e) {
+    console.error("Failed to import detected configs:", e);
+  }
+}
+
+interface ExternalImportResult {
+  imported: Config[];
+  skipped: [string, string][]; // [名称, 原因]
+}
+
+// 从其他 key 切换工具的导出文件导入
+async function importExternal() {
+  const format = prompt(
+    "导入格式：generic（[{ name, provider, apiKey, baseUrl }] 数组）/ cherry-studio / env（.env 文件，每个服务商一个配置）",
+    "generic"
+  )?.trim();
+  if (!format) return;
+  const path = prompt("导出文件路径（绝对路径）")?.trim();
+  if (!path) return;
+  if (format === "env") {
+    try {
+      const imported = await invoke<Config[]>("import_from_env_file", { path });
+      await loadConfigs();
+      showToast(imported.length ? `已导入 ${imported.length} 个配置` : "文件中的密钥均已存在");
+    } catch (e) {
+      console.error("Failed to import env file:", e);
+      showToast(`导入失败: ${e}`);
+    }
+    return;
+  }
+  try {
+    const result = await invoke<ExternalImportResult>("import_external", { path, format });
+    await loadConfigs();
+    const skipped = result.skipped.map(([name, reason]) => `${name}: ${reason}`).join("\n");
+    if (skipped) alert(`已导入 ${result.imported.length} 个配置，跳过 ${result.skipped.length} 个：\n${skipped}`);
+    else showToast(`已导入 ${result.imported.length} 个配置`);
+  } catch (e) {
+    console.error("Failed to import external configs:", e);
+    showToast(`导入失败: ${e}`);
+  }
+}
+
+async function duplicateConfig(id: string) {
+  try {
+    await invoke("duplicate_config", { id });
+    await loadConfigs();
+    showToast("配置已复制");
+  } catch (e) {
+    console.error("Failed to duplicate config:", e);
+    showToast("复制失败");
+  }
+}
+
+async function toggleNotifications() {
+  const next = { ...settings, notifications_enabled: !settings.notifications_enabled };
+  try {
+    await invoke("update_settings", { settings: next });
+    settings = next;
+    if (settings.notifications_enabled && "Notification" in window && Notification.permission === "default") {
+      await Notification.requestPermission();
+    }
+    renderConfigs();
+    showToast(settings.notifications_enabled ? "已开启切换通知" : "已关闭切换通知");
+  } catch (e) {
+    console.error("Failed to save settings:", e);
+    showToast("保存设置失败");
+  }
+}
+
+async function toggleTray() {
+  const next = { ...settings, tray_disabled: !settings.tray_disabled };
+  try {
+    await invoke("update_settings", { settings: next });
+    settings = next;
+    renderConfigs();
+    showToast(settings.tray_disabled ? "已隐藏托盘图标" : "已显示托盘图标");
+  } catch (e) {
+    console.error("Failed to save settings:", e);
+    showToast("保存设置失败");
+  }
+}
+
+async function toggleOpenCodeAuthStore() {
+  const next = { ...settings, opencode_auth_store: !settings.opencode_auth_store };
+  try {
+    await invoke("update_settings", { settings: next });
+    settings = next;
+    showToast(settings.opencode_auth_store ? "下次应用时密钥将写入 auth.json" : "下次应用时密钥将写入 opencode.json");
+  } catch (e) {
+    console.error("Failed to save settings:", e);
+    showToast("保存设置失败");
+    renderConfigs();
+  }
+}
+
+// 依次询问三个工具文件的位置，取消则不保存
+async function editToolPaths() {
+  const fields: [keyof Settings, string][] = [
+    ["claude_settings_path", "Claude settings.json 路径（留空为 ~/.claude/settings.json）"],
+    ["codex_dir", "Codex 目录（留空为 $CODEX_HOME 或 ~/.codex）"],
+    ["opencode_config_path", "opencode.json 路径（留空为 ~/.config/opencode/opencode.json）"],
+  ];
+  const next: Settings = { ...settings };
+  for (const [field, label] of fields) {
+    const value = prompt(label, (settings[field] as string | null | undefined) || "");
+    if (value === null) return;
+    (next as any)[field] = value.trim() || null;
+  }
+  try {
+    await invoke("update_settings", { settings: next });
+    settings = next;
+    await loadConfigs();
+    showToast("路径设置已保存");
+  } catch (e) {
+    showToast(`${e}`);
+  }
+}
+
+// 开启通知时优先用系统通知，webview 不支持或未授权时退回应用内提示
+function notifySwitch(text: string) {
+  if (!settings.notifications_enabled) return;
+  if ("Notification" in window && Notification.permission === "granted") {
+    new Notification("Config Manager", { body: text });
+  } else {
+    showToast(text);
+  }
+}
+
+// configs 已按后端规则排好序（类型、sort_index、名称），这里只交换相邻两项
+async function moveConfig(id: string, delta: -1 | 1) {
+  const config = configs.find((c) => c.id === id);
+  if (!config) return;
+  const sameType = configs.filter((c) => c.config_type === config.config_type);
+  const neighbor = sameType[sameType.indexOf(config) + delta];
+  if (!neighbor) return;
+  const ids = configs.map((c) => c.id);
+  const i = ids.indexOf(id);
+  const j = ids.indexOf(neighbor.id);
+  [ids[i], ids[j]] = [ids[j], ids[i]];
+  try {
+    await invoke("reorder_configs", { orderedIds: ids });
+    await loadConfigs();
+  } catch (e) {
+    console.error("Failed to reorder configs:", e);
+    showToast("排序失败");
+  }
+}
+
 async function deleteConfig(id: string) {
   try {
     await invoke("delete_config", { id });
@@ -76,20 +497,375 @@ async function deleteConfig(id: string) {
   }
 }
 
-async function activateConfig(id: string) {
+// 工具配置文件在应用外被改过时，后端拒绝覆盖并返回这个前缀的错误
+const EXTERNALLY_MODIFIED = "Externally modified";
+
+function confirmOverwrite(e: unknown): boolean {
+  return String(e).startsWith(EXTERNALLY_MODIFIED) && confirm(`${e}\n\n文件在应用外被修改过。覆盖这些修改吗？（取消则保留文件不变）`);
+}
+
+async function activateConfig(id: string, force = false) {
   showLoading("正在切换配置...");
   try {
-    await invoke("activate_config", { id });
+    const result = await invoke<ActivateResult>(force ? "force_apply_config" : "activate_config", { id });
+    const verify = await invoke<VerifyResult>("verify_apply_success", { id });
     await loadConfigs();
     hideLoading();
-    showToast("配置已激活");
+    const budget = result.budget_warning;
+    if (budget) {
+      showToast(`已激活，但「${budget.name}」今日已花费 $${budget.spent_usd.toFixed(2)}，超出每日预算 $${budget.budget_usd.toFixed(2)}`);
+    } else {
+      showToast(verify.all_ok ? "配置已激活" : `已激活，但未生效: ${verify.failed_vars.join(", ")}`);
+    }
   } catch (e) {
+    hideLoading();
+    if (!force && confirmOverwrite(e)) {
+      await activateConfig(id, true);
+      return;
+    }
     console.error("Failed to activate config:", e);
+    showToast("切换失败");
+  }
+}
+
+const FILE_ACTION_LABELS = { create: "新建", overwrite: "修改", delete: "删除" };
+
+// 先预览切换会改动的文件和环境变量，确认后再激活
+async function previewActivation(id: string) {
+  try {
+    const plan = await invoke<ActivationPlan>("preview_activation", { id });
+    if (plan.files.length === 0) {
+      showToast("激活不会改动任何文件");
+      return;
+    }
+    const files = plan.files.map((f) => `${FILE_ACTION_LABELS[f.action]} ${f.path}`);
+    const env = plan.env.map((v) =>
+      v.action === "delete" ? `- ${v.name}` : `${v.action === "set" ? "+" : "~"} ${v.name}=${v.new}`
+    );
+    if (confirm(`${files.join("\n")}${env.length ? `\n\n${env.join("\n")}` : ""}\n\n确定激活吗？`)) {
+      await activateConfig(id);
+    }
+  } catch (e) {
+    console.error("Failed to preview activation:", e);
+    showToast(`预览失败: ${e}`);
+  }
+}
+
+async function repairConfig(id: string) {
+  showLoading("正在重新写入配置...");
+  try {
+    const status = await invoke<ConfigStatus>("repair", { id });
+    await loadConfigs();
+    hideLoading();
+    showToast(status.state === "in_sync" ? "配置已修复" : "修复后仍不一致");
+  } catch (e) {
+    console.error("Failed to repair config:", e);
+    hideLoading();
+    showToast("修复失败");
+  }
+}
+
+async function activateForProject(id: string) {
+  const projectDir = prompt("项目目录（绝对路径）")?.trim();
+  if (!projectDir) return;
+  try {
+    const activation = await invoke<ProjectActivation>("activate_config_for_project", { id, projectDir });
+    await loadConfigs();
+    showToast(`已写入 ${activation.target_file}`);
+  } catch (e) {
+    console.error("Failed to activate config for project:", e);
+    showToast(`项目激活失败: ${e}`);
+  }
+}
+
+async function deactivateForProject(id: string, projectDir: string) {
+  try {
+    await invoke("deactivate_config_for_project", { id, projectDir });
+    await loadConfigs();
+    showToast("已从项目中移除");
+  } catch (e) {
+    console.error("Failed to deactivate config for project:", e);
+    showToast("移除失败");
+  }
+}
+
+// 把各类型当前激活的配置保存为一个配置组
+async function saveProfileFromActive() {
+  const entries: Record<string, string> = {};
+  for (const config of configs) {
+    if (config.is_active) entries[config.config_type] = config.id;
+  }
+  if (Object.keys(entries).length === 0) {
+    showToast("当前没有激活的配置");
+    return;
+  }
+  const name = prompt("配置组名称")?.trim();
+  if (!name) return;
+  const opencode = confirm("激活该配置组时是否同时写入 OpenCode？");
+  try {
+    await invoke("create_profile", { name, entries, opencode });
+    await loadConfigs();
+    showToast("配置组已保存");
+  } catch (e) {
+    console.error("Failed to create profile:", e);
+    showToast(`保存失败: ${e}`);
+  }
+}
+
+async function activateProfile(id: string) {
+  showLoading("正在切换配置组...");
+  try {
+    const result = await invoke<ProfileActivationResult>("activate_profile", { id });
+    await loadConfigs();
+    hideLoading();
+    const failed = result.entries.filter((e) => e.error).map((e) => typeLabel(e.config_type));
+    if (result.opencode_error) failed.push("OpenCode");
+    showToast(failed.length ? `部分失败: ${failed.join(", ")}` : "配置组已激活");
+  } catch (e) {
+    console.error("Failed to activate profile:", e);
     hideLoading();
     showToast("切换失败");
   }
 }
 
+async function deleteProfile(id: string) {
+  try {
+    await invoke("delete_profile", { id });
+    await loadConfigs();
+    showToast("配置组已删除");
+  } catch (e) {
+    console.error("Failed to delete profile:", e);
+  }
+}
+
+function renderProfileBar(): string {
+  return `
+    <div class="tag-bar">
+      ${profiles
+        .map(
+          (p) => `<button class="btn btn-secondary btn-sm" onclick="activateProfile('${p.id}')" title="${escapeHtml(
+            Object.keys(p.entries).map(typeLabel).join(" + ") + (p.opencode ? " + OpenCode" : "")
+          )}">${escapeHtml(p.name)}</button><button class="btn btn-icon" onclick="deleteProfile('${p.id}')" title="删除配置组">×</button>`
+        )
+        .join("")}
+      <button class="btn btn-secondary btn-sm" onclick="saveProfileFromActive()" title="把当前激活的配置保存为配置组，一键切换">+ 配置组</button>
+    </div>
+  `;
+}
+
+async function reactivatePrevious() {
+  if (currentTab === "opencode" || currentTab === "other") return;
+  showLoading("正在切回上一个配置...");
+  try {
+    const config = await invoke<Config>("reactivate_previous", { configType: currentTab });
+    await loadConfigs();
+    hideLoading();
+    showToast(`已切回: ${config.name}`);
+  } catch (e) {
+    console.error("Failed to reactivate previous config:", e);
+    hideLoading();
+    showToast("撤销失败");
+  }
+}
+
+// 按顺序在几个 key 之间定时轮换（中转站按 key 限每日额度时用）；留空配置列表即删除
+async function editRotation() {
+  if (currentTab === "opencode" || currentTab === "other") return;
+  const configType = currentTab;
+  try {
+    const policy = await invoke<RotationPolicy | null>("get_rotation_policy", { configType });
+    const names = (policy?.config_ids ?? [])
+      .map((id) => configs.find((c) => c.id === id)?.name)
+      .filter((n): n is string => !!n);
+    const paused = policy?.paused ? "（已暂停：手动切换到了列表外的配置，保存即恢复）\n" : "";
+    const input = prompt(`${paused}参与轮换的配置名称，按顺序用逗号分隔（留空删除轮换）`, names.join(", "));
+    if (input === null) return;
+    const ring = input.split(/[,，]/).map((n) => n.trim()).filter((n) => n);
+    const ids: string[] = [];
+    for (const name of ring) {
+      const config = configs.find((c) => c.config_type === configType && c.name === name);
+      if (!config) {
+        showToast(`找不到配置「${name}」`);
+        return;
+      }
+      ids.push(config.id);
+    }
+    let interval: RotationPolicy["interval"] = "on_start";
+    if (ids.length > 0) {
+      const current = policy && policy.interval !== "on_start" ? String(policy.interval.hours) : policy ? "start" : "24";
+      const answer = prompt("轮换间隔（小时），或输入 start 表示每次启动时轮换", current)?.trim();
+      if (!answer) return;
+      if (answer !== "start") {
+        const hours = Number(answer);
+        if (!Number.isInteger(hours) || hours < 1) {
+          showToast("间隔必须是正整数小时");
+          return;
+        }
+        interval = { hours };
+      }
+    }
+    await invoke("set_rotation_policy", {
+      configType,
+      policy: { config_ids: ids, interval, enabled: ids.length > 0, paused: false },
+    });
+    showToast(ids.length > 0 ? "已保存轮换设置" : "已删除轮换");
+  } catch (e) {
+    console.error("Failed to save rotation policy:", e);
+    showToast(`保存轮换设置失败: ${e}`);
+  }
+}
+
+async function rotateNow() {
+  if (currentTab === "opencode" || currentTab === "other") return;
+  showLoading("正在轮换...");
+  try {
+    const config = await invoke<Config>("rotate_now", { configType: currentTab });
+    await loadConfigs();
+    hideLoading();
+    showToast(`已轮换到: ${config.name}`);
+  } catch (e) {
+    console.error("Failed to rotate:", e);
+    hideLoading();
+    showToast(`轮换失败: ${e}`);
+  }
+}
+
+// 把当前标签页激活的配置导出为环境变量脚本，复制到剪贴板
+async function exportEnvSnippet() {
+  if (currentTab === "opencode") return;
+  const format = prompt("导出格式：dotenv / posix / powershell", "posix")?.trim();
+  if (!format) return;
+  try {
+    const snippet = await invoke<string>("export_env_snippet", { configType: currentTab, format });
+    await navigator.clipboard.writeText(snippet);
+    showToast("已复制到剪贴板");
+  } catch (e) {
+    console.error("Failed to export env snippet:", e);
+    showToast(`导出失败: ${e}`);
+  }
+}
+
+async function benchmarkConfigs() {
+  if (currentTab === "opencode") return;
+  showLoading("正在测速...");
+  try {
+    const results = await invoke<{ name: string; avg_latency_ms: number | null }[]>("benchmark_configs", {
+      configType: currentTab,
+      rounds: 3,
+    });
+    await loadConfigs();
+    hideLoading();
+    const fastest = results.find((r) => r.avg_latency_ms !== null);
+    showToast(fastest ? `最快: ${fastest.name} (${fastest.avg_latency_ms}ms)` : "所有配置均无响应");
+  } catch (e) {
+    console.error("Failed to benchmark configs:", e);
+    hideLoading();
+    showToast(`测速失败: ${e}`);
+  }
+}
+
+// 并发检测所有已保存配置的连通性，结果随 health-check-progress 事件逐个到达
+async function checkAllConfigs() {
+  const total = configs.filter((c) => c.key_set).length;
+  let done = 0;
+  showLoading(`正在检测 0/${total}...`);
+  const spinner = document.querySelector(".loading-spinner");
+  const cancel = document.createElement("button");
+  cancel.className = "btn btn-secondary btn-sm";
+  cancel.textContent = "取消";
+  cancel.onclick = () => invoke("cancel_health_check");
+  spinner?.appendChild(cancel);
+  const unlisten = await listen<HealthCheckResult>("health-check-progress", () => {
+    done++;
+    const text = document.querySelector(".loading-overlay .loading-text");
+    if (text) text.textContent = `正在检测 ${done}/${total}...`;
+  });
+  try {
+    const summary = await invoke<{ results: HealthCheckResult[]; cancelled: boolean }>("check_all_configs");
+    await loadConfigs();
+    const alive = summary.results.filter((r) => r.status === "reachable").length;
+    showToast(`${summary.cancelled ? "已取消，" : ""}${alive}/${summary.results.length} 个配置正常`);
+  } catch (e) {
+    console.error("Failed to check configs:", e);
+    showToast(`检测失败: ${e}`);
+  } finally {
+    unlisten();
+    hideLoading();
+  }
+}
+
+const USAGE_STATUS_LABELS = { unsupported: "该服务商不支持查询用量", forbidden: "该 key 无权查询用量", failed: "查询用量失败" };
+
+// 查询本月用量；查不到时改为手动记录
+async function fetchUsage(id: string) {
+  showLoading("正在查询用量...");
+  try {
+    const usage = await invoke<Usage>("fetch_usage", { id });
+    await loadConfigs();
+    hideLoading();
+    if (usage.status === "ok") {
+      showToast(`${usage.period} 花费 $${(usage.total_cost ?? 0).toFixed(2)}`);
+      return;
+    }
+    const config = configs.find((c) => c.id === id);
+    const note = prompt(`${USAGE_STATUS_LABELS[usage.status]}，可手动记录用量（留空清除）`, config?.usage_note ?? "");
+    if (note === null) return;
+    await invoke("set_usage_note", { id, text: note });
+    await loadConfigs();
+  } catch (e) {
+    console.error("Failed to fetch usage:", e);
+    hideLoading();
+    showToast(`查询用量失败: ${e}`);
+  }
+}
+
+async function openConfigNotes(id: string) {
+  try {
+    await invoke("open_config_notes", { id });
+  } catch (e) {
+    console.error("Failed to open notes:", e);
+    showToast(`打开笔记失败: ${e}`);
+  }
+}
+
+interface BackupInfo {
+  filename: string;
+  timestamp: number;
+  config_count: number | null;
+}
+
+// 从 configs.json 的自动备份中选一个恢复（恢复前会先备份当前文件）
+async function restoreBackup() {
+  try {
+    const backups = (await invoke<BackupInfo[]>("list_backups")).slice(0, 10);
+    if (!backups.length) {
+      showToast("还没有备份");
+      return;
+    }
+    const list = backups
+      .map((b, i) => `${i + 1}. ${new Date(b.timestamp).toLocaleString()}（${b.config_count ?? "?"} 个配置）`)
+      .join("\n");
+    const choice = Number(prompt(`输入要恢复的备份序号：\n${list}`)?.trim());
+    const backup = backups[choice - 1];
+    if (!backup) return;
+    await invoke("restore_backup", { filename: backup.filename });
+    await loadConfigs();
+    showToast("已恢复备份");
+  } catch (e) {
+    console.error("Failed to restore backup:", e);
+    showToast(`恢复失败: ${e}`);
+  }
+}
+
+async function openConfigDirectory() {
+  try {
+    await invoke("open_config_directory");
+  } catch (e) {
+    console.error("Failed to open config directory:", e);
+    showToast("打开配置目录失败");
+  }
+}
+
 async function restoreClaudeLogin() {
   showLoading("正在恢复官方登录...");
   try {
@@ -104,7 +880,7 @@ async function restoreClaudeLogin() {
   }
 }
 
-async function applyOpenCodeConfig() {
+async function applyOpenCodeConfig(force = false) {
   const claudeSelect = document.getElementById("opencode-claude") as HTMLSelectElement;
   const geminiSelect = document.getElementById("opencode-gemini") as HTMLSelectElement;
   const codexSelect = document.getElementById("opencode-codex") as HTMLSelectElement;
@@ -120,45 +896,85 @@ async function applyOpenCodeConfig() {
     return;
   }
 
+  const providerName = (id: string) =>
+    (document.getElementById(id) as HTMLInputElement | null)?.value.trim() || null;
+
   showLoading("正在应用 OpenCode 配置...");
   try {
-    const setModel = await invoke<string | null>("apply_opencode_config", {
+    const result = await invoke<OpenCodeApplyResult>("apply_opencode_config", {
       claudeId: claudeId || null,
       geminiId: geminiId || null,
       codexId: codexId || null,
       primary,
+      claudeProvider: providerName("opencode-claude-provider"),
+      geminiProvider: providerName("opencode-gemini-provider"),
+      codexProvider: providerName("opencode-codex-provider"),
+      force,
+      configVariant: providerName("opencode-variant"),
     });
     hideLoading();
-    showToast(setModel ? `已应用,默认模型: ${setModel}` : "已应用(未设置默认模型)");
+    refreshOpenCodeStatus();
+    const created = result.providers.filter((p) => p.status === "created").map((p) => p.provider);
+    const summary = created.length > 0 ? `,新建: ${created.join(", ")}` : "";
+    showToast(
+      (result.model ? `已应用,默认模型: ${result.model}` : "已应用(未设置默认模型)") + summary
+    );
   } catch (e) {
-    console.error("Failed to apply opencode config:", e);
     hideLoading();
+    if (!force && confirmOverwrite(e)) {
+      await applyOpenCodeConfig(true);
+      return;
+    }
+    if (await offerOpenCodeReset(e)) return;
+    console.error("Failed to apply opencode config:", e);
     showToast("应用失败: " + e);
   }
 }
 
-function getConfigsByType(type: ConfigType): Config[] {
-  return configs.filter((c) => c.config_type === type);
+function getConfigsByType(type: ConfigType | "other"): Config[] {
+  const tagged = tagFilter ? configs.filter((c) => c.tags.includes(tagFilter!)) : configs;
+  if (type === "other") return tagged.filter((c) => !isBuiltinType(c.config_type));
+  return tagged.filter((c) => c.config_type === type);
+}
+
+function setTagFilter(tag: string | null) {
+  tagFilter = tagFilter === tag ? null : tag;
+  renderConfigs();
 }
 
-function getKeyLabel(type: ConfigType): string {
+function renderTagBar(): string {
+  if (tagCounts.length === 0) return "";
+  return `
+    <div class="tag-bar">
+      ${tagCounts
+        .map(
+          (t) => `<button class="btn btn-secondary btn-sm ${tagFilter === t.tag ? "active" : ""}" onclick="setTagFilter('${escapeHtml(
+            t.tag.replace(/\\/g, "\\\\").replace(/'/g, "\\'")
+          )}')">${escapeHtml(t.tag)} (${t.count})</button>`
+        )
+        .join("")}
+    </div>
+  `;
+}
+
+function getKeyLabel(type: AnyConfigType): string {
   switch (type) {
     case "claude":
       return "ANTHROPIC_AUTH_TOKEN";
     case "gemini":
       return "GEMINI_API_KEY";
-    case "codex":
+    default:
       return "API Key";
   }
 }
 
-function getUrlLabel(type: ConfigType): string {
+function getUrlLabel(type: AnyConfigType): string {
   switch (type) {
     case "claude":
       return "ANTHROPIC_BASE_URL";
     case "gemini":
       return "GOOGLE_GEMINI_BASE_URL";
-    case "codex":
+    default:
       return "Base URL";
   }
 }
@@ -173,6 +989,44 @@ function renderConfigs() {
     <div class="header" id="drag-region">
       <h1>Config Manager</h1>
       <div class="header-actions">
+        <button class="btn btn-icon" onclick="restoreBackup()" title="从自动备份恢复配置">
+          <svg width="14" height="14" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2">
+            <circle cx="12" cy="12" r="9"/>
+            <polyline points="12 7 12 12 15 14"/>
+          </svg>
+        </button>
+        <button class="btn btn-icon" onclick="toggleNotifications()" title="${
+          settings.notifications_enabled ? "切换配置时的桌面通知：已开启" : "切换配置时的桌面通知：已关闭"
+        }">
+          <svg width="14" height="14" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2">
+            <path d="M18 8A6 6 0 0 0 6 8c0 7-3 9-3 9h18s-3-2-3-9"/>
+            <path d="M13.73 21a2 2 0 0 1-3.46 0"/>
+            ${settings.notifications_enabled ? "" : `<line x1="3" y1="3" x2="21" y2="21"/>`}
+          </svg>
+        </button>
+        <button class="btn btn-icon" onclick="checkAllConfigs()" title="检测所有已保存配置的连通性">
+          <svg width="14" height="14" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2">
+            <polyline points="22 12 18 12 15 21 9 3 6 12 2 12"/>
+          </svg>
+        </button>
+        <button class="btn btn-icon" onclick="toggleTray()" title="${settings.tray_disabled ? "托盘图标：已隐藏" : "托盘图标：已显示"}">
+          <svg width="14" height="14" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2">
+            <rect x="3" y="4" width="18" height="14" rx="2"/>
+            <path d="M3 14h18"/>
+            ${settings.tray_disabled ? `<line x1="3" y1="3" x2="21" y2="21"/>` : ""}
+          </svg>
+        </button>
+        <button class="btn btn-icon" onclick="editToolPaths()" title="工具配置文件位置">
+          <svg width="14" height="14" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2">
+            <circle cx="12" cy="12" r="3"/>
+            <path d="M19.4 15a1.65 1.65 0 0 0 .33 1.82l.06.06a2 2 0 1 1-2.83 2.83l-.06-.06a1.65 1.65 0 0 0-1.82-.33 1.65 1.65 0 0 0-1 1.51V21a2 2 0 1 1-4 0v-.09A1.65 1.65 0 0 0 9 19.4a1.65 1.65 0 0 0-1.82.33l-.06.06a2 2 0 1 1-2.83-2.83l.06-.06a1.65 1.65 0 0 0 .33-1.82 1.65 1.65 0 0 0-1.51-1H3a2 2 0 1 1 0-4h.09A1.65 1.65 0 0 0 4.6 9a1.65 1.65 0 0 0-.33-1.82l-.06-.06a2 2 0 1 1 2.83-2.83l.06.06a1.65 1.65 0 0 0 1.82.33H9a1.65 1.65 0 0 0 1-1.51V3a2 2 0 1 1 4 0v.09a1.65 1.65 0 0 0 1 1.51 1.65 1.65 0 0 0 1.82-.33l.06-.06a2 2 0 1 1 2.83 2.83l-.06.06a1.65 1.65 0 0 0-.33 1.82V9a1.65 1.65 0 0 0 1.51 1H21a2 2 0 1 1 0 4h-.09a1.65 1.65 0 0 0-1.51 1z"/>
+          </svg>
+        </button>
+        <button class="btn btn-icon" onclick="openConfigDirectory()" title="打开配置目录（configs.json 所在文件夹）">
+          <svg width="14" height="14" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2">
+            <path d="M22 19a2 2 0 0 1-2 2H4a2 2 0 0 1-2-2V5a2 2 0 0 1 2-2h5l2 3h9a2 2 0 0 1 2 2z"/>
+          </svg>
+        </button>
         ${
           currentTab === "claude"
             ? `
@@ -189,6 +1043,31 @@ function renderConfigs() {
         ${
           currentTab !== "opencode"
             ? `
+        ${
+          currentTab !== "other"
+            ? `<button class="btn btn-secondary btn-sm" onclick="reactivatePrevious()" title="切回上一次激活的配置">
+          撤销切换
+        </button>
+        <button class="btn btn-secondary btn-sm" onclick="addFromClipboard()" title="从剪贴板中的 key、.env 或 JSON 识别配置">
+          从剪贴板添加
+        </button>
+        <button class="btn btn-secondary btn-sm" onclick="importExternal()" title="从其他 key 切换工具（如 Cherry Studio）的导出文件导入">
+          导入
+        </button>
+        <button class="btn btn-secondary btn-sm" onclick="editRotation()" title="设置在几个配置之间按时间或每次启动自动轮换">
+          轮换设置
+        </button>
+        <button class="btn btn-secondary btn-sm" onclick="rotateNow()" title="立即切换到轮换列表中下一个可用的配置">
+          立即轮换
+        </button>
+        <button class="btn btn-secondary btn-sm" onclick="benchmarkConfigs()" title="测量本类型所有配置的 Base URL 延迟">
+          测速
+        </button>
+        <button class="btn btn-secondary btn-sm" onclick="exportEnvSnippet()" title="把当前激活的配置导出为 .env / shell 脚本，给不用本工具的同事">
+          导出变量
+        </button>`
+            : ""
+        }
         <button class="btn btn-primary" onclick="openModal()">
           <svg width="12" height="12" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2">
             <line x1="12" y1="5" x2="12" y2="19"></line>
@@ -202,6 +1081,8 @@ function renderConfigs() {
       </div>
     </div>
 
+    ${renderProfileBar()}
+
     <div class="tabs">
       <button class="tab ${currentTab === "claude" ? "active" : ""}" onclick="switchTab('claude')" style="--tab-color: ${CONFIG_TYPE_COLORS.claude}">
         Claude
@@ -212,12 +1093,15 @@ function renderConfigs() {
       <button class="tab ${currentTab === "codex" ? "active" : ""}" onclick="switchTab('codex')" style="--tab-color: ${CONFIG_TYPE_COLORS.codex}">
         Codex
       </button>
+      <button class="tab ${currentTab === "other" ? "active" : ""}" onclick="switchTab('other')" style="--tab-color: ${OTHER_TYPE_COLOR}" title="自定义类型，只记录不写入系统">
+        其他
+      </button>
       <button class="tab ${currentTab === "opencode" ? "active" : ""}" onclick="switchTab('opencode')" style="--tab-color: #10b981">
         OpenCode
       </button>
     </div>
 
-    ${currentTab === "opencode" ? renderOpenCodePanel() : renderConfigList(tabConfigs)}
+    ${currentTab === "opencode" ? renderOpenCodePanel() : renderTagBar() + renderConfigList(tabConfigs)}
 
     <div class="status-bar">
       <span>共 ${configs.length} 个配置</span>
@@ -234,12 +1118,56 @@ function renderConfigs() {
         }
       </span>
       `
-          : '<span class="status-active">选择配置并应用</span>'
+          : '<span class="status-active" id="opencode-status">选择配置并应用</span>'
       }
     </div>
   `;
 
   setupDragRegion();
+  if (currentTab === "opencode") refreshOpenCodeStatus();
+}
+
+// opencode.json 中缺少模板里的 provider 时，后端错误会提示 reset_opencode_config
+async function offerOpenCodeReset(e: unknown): Promise<boolean> {
+  if (!String(e).includes("reset_opencode_config") || !confirm(`${e}\n\n从模板恢复缺失的 provider 吗？`)) return false;
+  try {
+    const restored = await invoke<string[]>("reset_opencode_config");
+    showToast(restored.length ? `已恢复: ${restored.join(", ")}` : "没有需要恢复的 provider");
+  } catch (err) {
+    console.error("Failed to reset opencode config:", err);
+    showToast("恢复失败: " + err);
+  }
+  return true;
+}
+
+async function clearOpenCodeConfig() {
+  try {
+    const status = await invoke<OpenCodeProviderInfo[]>("get_opencode_status");
+    const withKey = status.filter((p) => p.key_set);
+    if (withKey.length === 0) {
+      showToast("OpenCode 中没有已配置的密钥");
+      return;
+    }
+    const list = withKey.map((p) => `${p.provider}: ${p.masked_key}`).join("\n");
+    if (!confirm(`将清除以下密钥：\n${list}\n确定吗？`)) return;
+    await invoke("clear_opencode_config", { providerSlots: withKey.map((p) => p.provider) });
+    showToast("已清除 OpenCode 密钥");
+  } catch (e) {
+    console.error("Failed to clear opencode config:", e);
+    showToast("清除失败: " + e);
+  }
+}
+
+async function refreshOpenCodeStatus() {
+  try {
+    const [model, smallModel] = await invoke<[string, string]>("get_opencode_current_model");
+    const status = document.getElementById("opencode-status");
+    if (status && model) {
+      status.textContent = `当前: ${model}${smallModel ? ` / ${smallModel}` : ""}`;
+    }
+  } catch (e) {
+    console.error("Failed to read opencode model:", e);
+  }
 }
 
 function renderConfigList(tabConfigs: Config[]): string {
@@ -250,7 +1178,7 @@ function renderConfigList(tabConfigs: Config[]): string {
           <svg viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="1.5">
             <path d="M9 12h6m-3-3v6m-7 4h14a2 2 0 002-2V7a2 2 0 00-2-2H5a2 2 0 00-2 2v10a2 2 0 002 2z"/>
           </svg>
-          <p>暂无 ${CONFIG_TYPE_LABELS[currentTab as ConfigType]} 配置</p>
+          <p>暂无 ${currentTab === "other" ? "自定义类型" : CONFIG_TYPE_LABELS[currentTab as ConfigType]} 配置</p>
         </div>
       </div>
     `;
@@ -261,32 +1189,128 @@ function renderConfigList(tabConfigs: Config[]): string {
       ${tabConfigs
         .map(
           (config) => `
-        <div class="config-item ${config.is_active ? "active" : ""}" onclick="activateConfig('${config.id}')" style="--type-color: ${CONFIG_TYPE_COLORS[config.config_type]}">
+        <div class="config-item ${config.is_active ? "active" : ""}" onclick="activateConfig('${config.id}')" style="--type-color: ${typeColor(config.config_type)}">
           <div class="config-header">
             <div class="config-name-wrapper">
-              <span class="config-type-badge" style="background: ${CONFIG_TYPE_COLORS[config.config_type]}">${CONFIG_TYPE_LABELS[config.config_type]}</span>
+              <span class="config-type-badge" style="background: ${typeColor(config.config_type)}">${escapeHtml(typeLabel(config.config_type))}</span>
               <span class="config-name">${escapeHtml(config.name)}</span>
             </div>
             <div class="config-actions">
               ${config.is_active ? '<span class="active-badge">当前</span>' : ""}
+              ${config.opencode_active ? '<span class="active-badge" title="当前写入 opencode.json 的配置">OpenCode</span>' : ""}
+              ${
+                config.expired
+                  ? `<span class="active-badge" title="${escapeHtml(
+                      config.options.rotation_note || `已于 ${config.options.expires_at} 过期`
+                    )}">已过期</span>`
+                  : ""
+              }
+              ${
+                outOfSync.has(config.id)
+                  ? `<button class="btn btn-secondary btn-sm" onclick="event.stopPropagation(); repairConfig('${config.id}')" title="${escapeHtml(
+                      outOfSync.get(config.id)!.drifted.map((d) => d.key).join(", ")
+                    )} 与配置不一致，点击重新写入">未同步</button>`
+                  : ""
+              }
+              ${
+                config.is_shared
+                  ? '<span class="active-badge" title="团队共享配置，只能激活，不能编辑或删除">共享</span>'
+                  : `
               <button class="btn btn-icon" onclick="event.stopPropagation(); editConfig('${config.id}')" title="编辑">
                 <svg width="14" height="14" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2">
                   <path d="M11 4H4a2 2 0 00-2 2v14a2 2 0 002 2h14a2 2 0 002-2v-7"/>
                   <path d="M18.5 2.5a2.121 2.121 0 013 3L12 15l-4 1 1-4 9.5-9.5z"/>
                 </svg>
               </button>
+              `
+              }
+              <button class="btn btn-secondary btn-sm" onclick="event.stopPropagation(); fetchUsage('${config.id}')" title="查询本月花费">用量</button>
+              <button class="btn btn-secondary btn-sm" onclick="event.stopPropagation(); previewActivation('${config.id}')" title="查看激活会改动哪些文件">预览</button>
+              ${
+                config.config_type === "claude" || config.config_type === "gemini"
+                  ? `<button class="btn btn-secondary btn-sm" onclick="event.stopPropagation(); activateForProject('${config.id}')" title="只对某个项目目录生效">项目</button>`
+                  : ""
+              }
+              ${
+                config.options.notes_file_path
+                  ? `<button class="btn btn-secondary btn-sm" onclick="event.stopPropagation(); openConfigNotes('${config.id}')" title="${escapeHtml(
+                      config.options.notes_file_path
+                    )}">笔记</button>`
+                  : ""
+              }
+              <button class="btn btn-icon" onclick="event.stopPropagation(); moveConfig('${config.id}', -1)" title="上移">
+                <svg width="14" height="14" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2">
+                  <polyline points="18 15 12 9 6 15"/>
+                </svg>
+              </button>
+              <button class="btn btn-icon" onclick="event.stopPropagation(); moveConfig('${config.id}', 1)" title="下移">
+                <svg width="14" height="14" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2">
+                  <polyline points="6 9 12 15 18 9"/>
+                </svg>
+              </button>
+              <button class="btn btn-icon" onclick="event.stopPropagation(); duplicateConfig('${config.id}')" title="复制">
+                <svg width="14" height="14" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2">
+                  <rect x="9" y="9" width="13" height="13" rx="2"/>
+                  <path d="M5 15H4a2 2 0 01-2-2V4a2 2 0 012-2h9a2 2 0 012 2v1"/>
+                </svg>
+              </button>
+              ${
+                config.is_shared
+                  ? ""
+                  : `
               <button class="btn btn-icon btn-danger" onclick="event.stopPropagation(); deleteConfig('${config.id}')" title="删除">
                 <svg width="14" height="14" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2">
                   <polyline points="3,6 5,6 21,6"/>
                   <path d="M19,6v14a2,2,0,0,1-2,2H7a2,2,0,0,1-2-2V6m3,0V4a2,2,0,0,1,2-2h4a2,2,0,0,1,2,2v2"/>
                 </svg>
               </button>
+              `
+              }
             </div>
           </div>
           <div class="config-details">
-            <p><strong>Key:</strong> ${maskToken(config.api_key)}</p>
+            <p><strong>Key:</strong> ${config.key_set ? escapeHtml(config.api_key) : "未设置"}</p>
             <p><strong>URL:</strong> ${escapeHtml(config.base_url) || "默认"}</p>
             ${config.model ? `<p><strong>Model:</strong> ${escapeHtml(config.model)}</p>` : ""}
+            ${config.tags.length ? `<p><strong>标签:</strong> ${config.tags.map(escapeHtml).join(", ")}</p>` : ""}
+            ${
+              config.last_activated_at
+                ? `<p><strong>上次激活:</strong> ${new Date(config.last_activated_at).toLocaleString()} (共 ${config.activation_count} 次)</p>`
+                : ""
+            }
+            ${
+              config.last_benchmark
+                ? `<p><strong>延迟:</strong> ${
+                    config.last_benchmark.avg_latency_ms !== null ? `${config.last_benchmark.avg_latency_ms}ms` : "无响应"
+                  }${config.last_benchmark.auth_ok ? "" : "（鉴权失败）"}，测于 ${new Date(
+                    config.last_benchmark.measured_at
+                  ).toLocaleString()}</p>`
+                : ""
+            }
+            ${
+              config.last_health
+                ? `<p><strong>连通性:</strong> ${HEALTH_STATUS_LABELS[config.last_health.status]}${
+                    config.last_health.status === "unreachable" ? "" : ` (${config.last_health.latency_ms}ms)`
+                  }，测于 ${new Date(config.last_health.checked_at).toLocaleString()}</p>`
+                : ""
+            }
+            ${
+              config.last_usage?.status === "ok"
+                ? `<p><strong>用量:</strong> ${config.last_usage.period} $${(config.last_usage.total_cost ?? 0).toFixed(2)}${
+                    config.last_usage.request_count !== null ? `，${config.last_usage.request_count} 次请求` : ""
+                  }，查于 ${new Date(config.last_usage.fetched_at).toLocaleString()}</p>`
+                : ""
+            }
+            ${config.usage_note ? `<p><strong>用量备注:</strong> ${escapeHtml(config.usage_note)}</p>` : ""}
+            ${projectActivations
+              .filter((a) => a.config_id === config.id)
+              .map(
+                (a) => `<p><strong>项目:</strong> ${escapeHtml(a.project_dir)}
+                  <button class="btn btn-secondary btn-sm" onclick="event.stopPropagation(); deactivateForProject('${config.id}', '${escapeHtml(
+                    a.project_dir.replace(/\\/g, "\\\\").replace(/'/g, "\\'")
+                  )}')">移除</button></p>`
+              )
+              .join("")}
           </div>
         </div>
       `
@@ -319,6 +1343,7 @@ function renderOpenCodePanel(): string {
             <option value="">-- 不使用 --</option>
             ${claudeConfigs.map((c) => `<option value="${c.id}">${escapeHtml(c.name)}</option>`).join("")}
           </select>
+          <input type="text" id="opencode-claude-provider" placeholder="provider: foxcode-claude" autocomplete="off">
         </div>
 
         <div class="form-group">
@@ -327,6 +1352,7 @@ function renderOpenCodePanel(): string {
             <option value="">-- 不使用 --</option>
             ${geminiConfigs.map((c) => `<option value="${c.id}">${escapeHtml(c.name)}</option>`).join("")}
           </select>
+          <input type="text" id="opencode-gemini-provider" placeholder="provider: foxcode-gemini" autocomplete="off">
         </div>
 
         <div class="form-group">
@@ -335,6 +1361,14 @@ function renderOpenCodePanel(): string {
             <option value="">-- 不使用 --</option>
             ${codexConfigs.map((c) => `<option value="${c.id}">${escapeHtml(c.name)}</option>`).join("")}
           </select>
+          <input type="text" id="opencode-codex-provider" placeholder="provider: foxcode-oai" autocomplete="off">
+        </div>
+
+        <div class="form-group">
+          <label>
+            <input type="checkbox" id="opencode-auth-store" ${settings.opencode_auth_store ? "checked" : ""} onchange="toggleOpenCodeAuthStore()">
+            密钥写入 ~/.local/share/opencode/auth.json（opencode.json 中不保存密钥）
+          </label>
         </div>
 
         <div class="form-group">
@@ -345,6 +1379,11 @@ function renderOpenCodePanel(): string {
             <option value="codex">Codex</option>
           </select>
         </div>
+
+        <div class="form-group">
+          <label for="opencode-variant">写入变体 (可选，写入 opencode-&lt;变体&gt;.json，如 work、personal)</label>
+          <input type="text" id="opencode-variant" placeholder="留空写入 opencode.json" autocomplete="off">
+        </div>
       </div>
 
       <button class="btn btn-primary btn-full" onclick="applyOpenCodeConfig()">
@@ -354,6 +1393,10 @@ function renderOpenCodePanel(): string {
         应用到 OpenCode
       </button>
 
+      <button class="btn btn-secondary btn-full" onclick="clearOpenCodeConfig()">
+        清除 OpenCode 中的密钥
+      </button>
+
       <div class="opencode-path">
         <small>配置将写入: ~/.config/opencode/opencode.json</small>
       </div>
@@ -361,11 +1404,41 @@ function renderOpenCodePanel(): string {
   `;
 }
 
-function switchTab(tab: ConfigType | "opencode") {
+function switchTab(tab: Tab) {
   currentTab = tab;
   renderConfigs();
 }
 
+interface ParsedCredential {
+  config_type: AnyConfigType;
+  api_key: string;
+  base_url: string;
+  model: string;
+  confident: boolean;
+  source: string | null;
+}
+
+// 从剪贴板里的 key / .env / JSON 识别配置，预填添加表单（识别到多个时取第一个）
+async function addFromClipboard() {
+  try {
+    const text = await navigator.clipboard.readText();
+    const [found, ...rest] = await invoke<ParsedCredential[]>("parse_credential", { text });
+    openModal();
+    const typeSelect = document.getElementById("config_type") as HTMLSelectElement;
+    typeSelect.value = found.config_type;
+    typeSelect.dispatchEvent(new Event("change"));
+    (document.getElementById("api_key") as HTMLInputElement).value = found.api_key;
+    (document.getElementById("base_url") as HTMLInputElement).value = found.base_url;
+    (document.getElementById("model") as HTMLInputElement).value = found.model;
+    const hints = [found.confident ? "" : "类型为推测，请确认", rest.length ? `另有 ${rest.length} 个 key 未填入` : ""];
+    const hint = hints.filter((h) => h).join("；");
+    if (hint) showToast(hint);
+  } catch (e) {
+    console.error("Failed to parse clipboard:", e);
+    showToast(`剪贴板中未识别到 API Key`);
+  }
+}
+
 function openModal(config?: Config) {
   editingConfig = config || null;
   const configType = config?.config_type || (currentTab === "opencode" ? "claude" : currentTab);
@@ -390,23 +1463,81 @@ function openModal(config?: Config) {
             <option value="claude" ${configType === "claude" ? "selected" : ""}>Claude</option>
             <option value="gemini" ${configType === "gemini" ? "selected" : ""}>Gemini</option>
             <option value="codex" ${configType === "codex" ? "selected" : ""}>Codex</option>
+            <option value="other" ${configType === "other" ? "selected" : ""}>其他 (仅记录，不写入系统)</option>
           </select>
         </div>
+        <div class="form-group" id="custom-type-group" style="${configType === "other" ? "" : "display: none"}">
+          <label for="custom_type">类型名称</label>
+          <input type="text" id="custom_type" placeholder="例如: my-proxy" autocomplete="off">
+        </div>
         `
             : ""
         }
         <div class="form-group">
-          <label for="api_key" id="key-label">${getKeyLabel(configType as ConfigType)}</label>
+          <label for="api_key" id="key-label">${getKeyLabel(configType)}</label>
           <input type="password" id="api_key" placeholder="sk-..." value="${escapeHtml(config?.api_key || "")}" required autocomplete="off">
+          ${config ? `<button type="button" class="btn btn-secondary btn-sm" onclick="revealApiKey()">显示</button>` : ""}
         </div>
         <div class="form-group">
-          <label for="base_url" id="url-label">${getUrlLabel(configType as ConfigType)} (可选)</label>
+          <label for="base_url" id="url-label">${getUrlLabel(configType)} (可选)</label>
           <input type="text" id="base_url" placeholder="https://api.example.com" value="${escapeHtml(config?.base_url || "")}" autocomplete="off">
         </div>
         <div class="form-group">
           <label for="model">Model (可选)</label>
           <input type="text" id="model" placeholder="例如: claude-sonnet-4-6-20250514" value="${escapeHtml(config?.model || "")}" autocomplete="off">
         </div>
+        <div class="form-group" id="small-model-group" style="${configType === "claude" ? "" : "display: none"}">
+          <label for="small_fast_model">Small/Fast Model (可选，写入 ANTHROPIC_SMALL_FAST_MODEL)</label>
+          <input type="text" id="small_fast_model" placeholder="例如: claude-haiku-4-5-20251001" value="${escapeHtml(
+            config?.options.small_fast_model || ""
+          )}" autocomplete="off">
+          <label for="model_thinking_budget">Thinking Budget (可选，扩展思考 token 数，至少 1024)</label>
+          <input type="number" id="model_thinking_budget" min="1024" step="1" placeholder="例如: 8000" value="${
+            config?.options.model_thinking_budget ?? ""
+          }" autocomplete="off">
+        </div>
+        <div class="form-group">
+          <label for="cost_budget_daily_usd">每日预算 (可选，美元，超出后激活时提醒)</label>
+          <input type="number" id="cost_budget_daily_usd" min="0" step="0.01" placeholder="例如: 20" value="${
+            config?.options.cost_budget_daily_usd ?? ""
+          }" autocomplete="off">
+        </div>
+        <div class="form-group" id="scopes-group" style="${configType === "gemini" ? "" : "display: none"}">
+          <label for="scopes">OAuth Scopes (可选，逗号分隔，写入 GOOGLE_OAUTH_SCOPES)</label>
+          <input type="text" id="scopes" placeholder="例如: https://www.googleapis.com/auth/cloud-platform" value="${escapeHtml(
+            (config?.options.scopes ?? []).join(", ")
+          )}" autocomplete="off">
+        </div>
+        <div class="form-group" id="max-retries-group" style="${
+          configType === "claude" || configType === "codex" ? "" : "display: none"
+        }">
+          <label for="max_retries">最大重试次数 (可选，0-10，由 Claude Code / Codex 自身重试失败的请求)</label>
+          <input type="number" id="max_retries" min="0" max="10" step="1" placeholder="例如: 3" value="${
+            config?.options.max_retries ?? ""
+          }" autocomplete="off">
+        </div>
+        <div class="form-group" id="client-cert-group" style="${configType === "claude" ? "" : "display: none"}">
+          <label for="http_client_cert_path">客户端证书 (可选，mTLS 代理需要)</label>
+          <input type="text" id="http_client_cert_path" placeholder="证书 PEM 文件的绝对路径" value="${escapeHtml(
+            config?.options.http_client_cert_path || ""
+          )}" autocomplete="off">
+          <input type="text" id="http_client_key_path" placeholder="私钥文件路径（与证书同文件时留空）" value="${escapeHtml(
+            config?.options.http_client_key_path || ""
+          )}" autocomplete="off">
+        </div>
+        <div class="form-group" id="version-header-group" style="${isBuiltinType(configType) ? "" : "display: none"}">
+          <label for="version_header_name">版本请求头 (可选，服务商要求时填写)</label>
+          <input type="text" id="version_header_name" placeholder="请求头名，例如: anthropic-version" value="${escapeHtml(
+            config?.options.api_version_header?.[0] || ""
+          )}" autocomplete="off">
+          <input type="text" id="version_header_value" placeholder="值，例如: 2023-06-01" value="${escapeHtml(
+            config?.options.api_version_header?.[1] || ""
+          )}" autocomplete="off">
+        </div>
+        <div class="form-group">
+          <label for="tags">标签 (可选，逗号分隔)</label>
+          <input type="text" id="tags" placeholder="例如: work, relay" value="${escapeHtml(config?.tags.join(", ") || "")}" autocomplete="off">
+        </div>
         <div class="modal-actions">
           <button type="button" class="btn btn-secondary" onclick="closeModal()">取消</button>
           <button type="submit" class="btn btn-primary">${config ? "保存" : "添加"}</button>
@@ -420,22 +1551,86 @@ function openModal(config?: Config) {
   const typeSelect = document.getElementById("config_type") as HTMLSelectElement;
   if (typeSelect) {
     typeSelect.addEventListener("change", () => {
-      const type = typeSelect.value as ConfigType;
+      const type = typeSelect.value;
       document.getElementById("key-label")!.textContent = getKeyLabel(type);
       document.getElementById("url-label")!.textContent = getUrlLabel(type) + " (可选)";
+      document.getElementById("custom-type-group")!.style.display = type === "other" ? "" : "none";
+      document.getElementById("small-model-group")!.style.display = type === "claude" ? "" : "none";
+      document.getElementById("client-cert-group")!.style.display = type === "claude" ? "" : "none";
+      document.getElementById("scopes-group")!.style.display = type === "gemini" ? "" : "none";
+      document.getElementById("max-retries-group")!.style.display = type === "claude" || type === "codex" ? "" : "none";
+      document.getElementById("version-header-group")!.style.display = isBuiltinType(type) ? "" : "none";
     });
   }
 
-  document.getElementById("config-form")!.onsubmit = (e) => {
+  document.getElementById("config-form")!.onsubmit = async (e) => {
     e.preventDefault();
     const name = (document.getElementById("name") as HTMLInputElement).value;
     const api_key = (document.getElementById("api_key") as HTMLInputElement).value;
     const base_url = (document.getElementById("base_url") as HTMLInputElement).value;
     const model = (document.getElementById("model") as HTMLInputElement).value;
-    const config_type = editingConfig
-      ? editingConfig.config_type
-      : ((document.getElementById("config_type") as HTMLSelectElement).value as ConfigType);
-    saveConfig({ name, config_type, api_key, base_url, model });
+    const tags = (document.getElementById("tags") as HTMLInputElement).value
+      .split(",")
+      .map((t) => t.trim())
+      .filter((t) => t);
+    let config_type: AnyConfigType;
+    if (editingConfig) {
+      config_type = editingConfig.config_type;
+    } else {
+      config_type = (document.getElementById("config_type") as HTMLSelectElement).value;
+      if (config_type === "other") {
+        config_type = (document.getElementById("custom_type") as HTMLInputElement).value.trim();
+        if (!config_type) {
+          showToast("请填写类型名称");
+          return;
+        }
+      }
+    }
+    const smallFastModel = (document.getElementById("small_fast_model") as HTMLInputElement).value.trim();
+    const thinkingBudget = (document.getElementById("model_thinking_budget") as HTMLInputElement).value.trim();
+    const maxRetries = (document.getElementById("max_retries") as HTMLInputElement).value.trim();
+    const dailyBudget = (document.getElementById("cost_budget_daily_usd") as HTMLInputElement).value.trim();
+    const scopes = (document.getElementById("scopes") as HTMLInputElement).value
+      .split(",")
+      .map((s) => s.trim())
+      .filter((s) => s);
+    const headerName = (document.getElementById("version_header_name") as HTMLInputElement).value.trim();
+    const headerValue = (document.getElementById("version_header_value") as HTMLInputElement).value.trim();
+    const certPath = (document.getElementById("http_client_cert_path") as HTMLInputElement).value.trim();
+    const keyPath = (document.getElementById("http_client_key_path") as HTMLInputElement).value.trim();
+    const options: ConfigOptions = {
+      ...editingConfig?.options,
+      small_fast_model: config_type === "claude" && smallFastModel ? smallFastModel : null,
+      model_thinking_budget: config_type === "claude" && thinkingBudget ? Number(thinkingBudget) : null,
+      scopes: config_type === "gemini" ? scopes : [],
+      cost_budget_daily_usd: dailyBudget ? Number(dailyBudget) : null,
+      max_retries: (config_type === "claude" || config_type === "codex") && maxRetries ? Number(maxRetries) : null,
+      api_version_header: isBuiltinType(config_type) && headerName ? [headerName, headerValue] : null,
+      http_client_cert_path: config_type === "claude" && certPath ? certPath : null,
+      http_client_key_path: config_type === "claude" && keyPath ? keyPath : null,
+    };
+    const issues = await invoke<ValidationIssue[]>("validate_config", {
+      configType: config_type,
+      name,
+      apiKey: api_key,
+      baseUrl: base_url,
+      options,
+      id: editingConfig?.id,
+    }).catch((err) => {
+      console.error("Failed to validate config:", err);
+      return [] as ValidationIssue[];
+    });
+    for (const field of ["name", "api_key", "base_url"]) {
+      document.getElementById(field)!.classList.toggle("invalid", issues.some((i) => i.field === field && i.severity === "error"));
+    }
+    const errors = issues.filter((i) => i.severity === "error");
+    if (errors.length) {
+      showToast(errors.map((i) => i.message).join("; "));
+      return;
+    }
+    const warnings = issues.filter((i) => i.severity === "warning");
+    if (warnings.length && !confirm(`${warnings.map((i) => i.message).join("\n")}\n仍要保存吗？`)) return;
+    saveConfig({ name, config_type, api_key, base_url, model, tags, options });
   };
 
   modal.onclick = (e) => {
@@ -449,6 +1644,19 @@ function closeModal() {
   editingConfig = null;
 }
 
+// 编辑弹窗里默认是掩码，点击“显示”才取回完整 key
+async function revealApiKey() {
+  if (!editingConfig) return;
+  try {
+    const key = await invoke<string>("reveal_api_key", { id: editingConfig.id });
+    const input = document.getElementById("api_key") as HTMLInputElement;
+    input.value = key;
+    input.type = "text";
+  } catch (e) {
+    console.error("Failed to reveal api key:", e);
+  }
+}
+
 function editConfig(id: string) {
   const config = configs.find((c) => c.id === id);
   if (config) openModal(config);
@@ -495,11 +1703,6 @@ function escapeHtml(str: string): string {
     .replace(/"/g, "&quot;");
 }
 
-function maskToken(token: string): string {
-  if (!token || token.length < 10) return "****";
-  return token.slice(0, 7) + "..." + token.slice(-4);
-}
-
 function setupDragRegion() {
   const dragRegion = document.getElementById("drag-region");
   if (dragRegion) {
@@ -516,13 +1719,78 @@ function setupDragRegion() {
 
 // Expose functions to global scope for onclick handlers
 (window as any).openModal = openModal;
+(window as any).addFromClipboard = addFromClipboard;
+(window as any).openConfigDirectory = openConfigDirectory;
+(window as any).restoreBackup = restoreBackup;
+(window as any).openConfigNotes = openConfigNotes;
+(window as any).fetchUsage = fetchUsage;
+(window as any).benchmarkConfigs = benchmarkConfigs;
+(window as any).exportEnvSnippet = exportEnvSnippet;
 (window as any).closeModal = closeModal;
 (window as any).editConfig = editConfig;
+(window as any).revealApiKey = revealApiKey;
 (window as any).deleteConfig = deleteConfig;
+(window as any).duplicateConfig = duplicateConfig;
+(window as any).moveConfig = moveConfig;
+(window as any).toggleNotifications = toggleNotifications;
+(window as any).editToolPaths = editToolPaths;
+(window as any).toggleTray = toggleTray;
+(window as any).importExternal = importExternal;
+(window as any).checkAllConfigs = checkAllConfigs;
+(window as any).toggleOpenCodeAuthStore = toggleOpenCodeAuthStore;
 (window as any).activateConfig = activateConfig;
 (window as any).switchTab = switchTab;
+(window as any).setTagFilter = setTagFilter;
+(window as any).saveProfileFromActive = saveProfileFromActive;
+(window as any).activateProfile = activateProfile;
+(window as any).deleteProfile = deleteProfile;
 (window as any).applyOpenCodeConfig = applyOpenCodeConfig;
+(window as any).clearOpenCodeConfig = clearOpenCodeConfig;
 (window as any).restoreClaudeLogin = restoreClaudeLogin;
+(window as any).reactivatePrevious = reactivatePrevious;
+(window as any).editRotation = editRotation;
+(window as any).rotateNow = rotateNow;
+(window as any).repairConfig = repairConfig;
+(window as any).previewActivation = previewActivation;
+(window as any).activateForProject = activateForProject;
+(window as any).deactivateForProject = deactivateForProject;
 
 // Initialize
-loadConfigs();
+loadConfigs().then(offerDetectedImport);
+// configs.json 被其他实例或手动修改时刷新
+listen<Config[]>("configs-changed", () => loadConfigs());
+// 自动故障切换：当前配置连续检测失败后已切到备用配置
+listen<{ config_type: AnyConfigType; from_name: string; to_name: string }>("config-failover", (e) =>
+  showToast(`${typeLabel(e.payload.config_type)}「${e.payload.from_name}」连续不可用，已切换到「${e.payload.to_name}」`)
+);
+// 定时轮换：已切到轮换列表中的下一个配置
+listen<{ config_type: AnyConfigType; to_name: string }>("config-rotated", (e) =>
+  showToast(`${typeLabel(e.payload.config_type)} 已轮换到「${e.payload.to_name}」`)
+);
+// 任何途径的切换（包括故障切换、方案、编辑后重新应用）都会触发这些事件
+listen<ConfigEvent>("config-activated", (e) =>
+  notifySwitch(`${typeLabel(e.payload.config_type)}: 已切换到「${e.payload.name}」`)
+);
+listen<ConfigEvent>("config-deactivated", (e) =>
+  notifySwitch(`${typeLabel(e.payload.config_type)}: 已停用「${e.payload.name}」`)
+);
+listen<ConfigEvent>("opencode-updated", (e) =>
+  notifySwitch(`OpenCode: 已写入「${e.payload.name}」`)
+);
+// 即将过期的 key：启动后和运行中定期提醒
+listen<Config[]>("configs-expiring", (e) =>
+  showToast(`${e.payload.length} 个配置的 key 即将过期：${e.payload.map((c) => c.name).join("、")}`)
+);
+// ccm:// 链接（Stream Deck、脚本）处理失败；成功时上面的切换事件已有提示
+listen<{ url: string; message: string }>("deep-link-error", (e) =>
+  showToast(`链接 ${e.payload.url} 处理失败: ${e.payload.message}`)
+);
+// 托盘菜单里的切换失败：窗口可能没打开，优先用系统通知
+listen<{ message: string }>("tray-error", (e) => {
+  const text = `托盘操作失败: ${e.payload.message}`;
+  if ("Notification" in window && Notification.permission === "granted") {
+    new Notification("Config Manager", { body: text });
+  } else {
+    showToast(text);
+  }
+});